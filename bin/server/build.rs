@@ -21,7 +21,9 @@ fn make_version_code(version: &str) -> u32 {
 }
 
 fn main() {
-    println!("cargo:rustc-env=SQLX_OFFLINE=true"); // sqlx本地化编译
+    // sqlx本地化编译；重新生成 .sqlx 时由 cargo sqlx prepare 设置 SQLX_OFFLINE=false 连接数据库
+    let sqlx_offline = env::var("SQLX_OFFLINE").unwrap_or_else(|_| "true".to_string());
+    println!("cargo:rustc-env=SQLX_OFFLINE={}", sqlx_offline);
     // 读取 Cargo.toml
     let manifest_path = Path::new("Cargo.toml");
     let content = fs::read_to_string(manifest_path).expect("无法读取 Cargo.toml");
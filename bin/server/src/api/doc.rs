@@ -2,6 +2,12 @@
 
 use crate::api::handler::__path_handle_request;
 use crate::api::handler::__path_ping;
+use crate::api::handler::manager::message::group::__path_handle_delete_group_message;
+use crate::api::handler::manager::message::group::__path_handle_get_full_group_message;
+use crate::api::handler::manager::message::group::__path_handle_get_recent_group_messages;
+use crate::api::handler::manager::message::group::__path_handle_get_recent_messages_of_group;
+use crate::api::handler::manager::message::group::__path_handle_get_user_recent_group_messages;
+use crate::api::handler::manager::message::import::__path_handle_import_messages;
 use crate::api::handler::manager::message::privite::__path_handle_delete_message;
 use crate::api::handler::manager::message::privite::__path_handle_get_message;
 use crate::api::handler::manager::message::privite::__path_handle_get_recent_messages;
@@ -25,6 +31,9 @@ use crate::api::handler::request::friend::add_friend::__path_handle_add_friend;
 use crate::api::handler::request::friend::info_friend::__path_handle_info_friend;
 use crate::api::handler::request::friend::list_friend::__path_handle_list_friend;
 use crate::api::handler::request::friend::list_friend::__path_handle_list_friend_with_status;
+use crate::api::handler::request::friend::pin_friend::__path_handle_list_private_pins;
+use crate::api::handler::request::friend::pin_friend::__path_handle_pin_private_message;
+use crate::api::handler::request::friend::pin_friend::__path_handle_unpin_private_message;
 use crate::api::handler::request::friend::status_friend::__path_handle_get_status_by_userid;
 use crate::api::handler::request::group::creat_group::__path_handle_creat_group;
 use crate::api::handler::request::group::info_group::__path_handle_info_group;
@@ -32,8 +41,12 @@ use crate::api::handler::request::group::join_group::__path_handle_join_group;
 use crate::api::handler::request::group::leave_group::__path_handle_leave_group;
 use crate::api::handler::request::group::list_group::__path_handle_list_group;
 use crate::api::handler::request::group::members_group::__path_handle_members_group;
+use crate::api::handler::request::group::pin_group::__path_handle_list_group_pins;
+use crate::api::handler::request::group::pin_group::__path_handle_pin_group_message;
+use crate::api::handler::request::group::pin_group::__path_handle_unpin_group_message;
 use crate::api::handler::request::message::group::__path_handle_get_all_group_messages_after_timestamp;
 use crate::api::handler::request::message::group::__path_handle_get_group_message;
+use crate::api::handler::request::message::group::__path_handle_get_group_thread;
 use crate::api::handler::request::message::group::__path_handle_get_group_messages_after_timestamp;
 use crate::api::handler::request::message::group::__path_handle_get_latest_timestamp_of_all_group_messages;
 use crate::api::handler::request::message::group::__path_handle_get_latest_timestamp_of_group;
@@ -44,6 +57,24 @@ use crate::api::handler::request::message::private::__path_handle_get_latest_tim
 use crate::api::handler::request::message::private::__path_handle_get_latest_timestamps_of_all_private_chats;
 use crate::api::handler::request::message::private::__path_handle_get_private_messages_after_timestamp;
 use crate::api::handler::request::message::private::__path_handle_get_session_message;
+use crate::api::handler::request::message::unread::__path_handle_get_unread_counts;
+use crate::api::handler::request::message::mention::__path_handle_get_unread_mentions;
+use crate::api::handler::request::message::search::__path_handle_search_messages;
+use crate::api::handler::request::conversation::list_conversation::__path_handle_list_conversation;
+use crate::api::handler::request::conversation::settings_conversation::__path_handle_update_conversation_settings;
+use crate::api::handler::request::conversation::settings_conversation::__path_handle_update_message_ttl;
+use crate::api::handler::request::message::recall::__path_handle_delete_own_message;
+use crate::api::handler::request::message::recall::__path_handle_edit_message;
+use crate::api::handler::request::message::recall::__path_handle_get_message_edits;
+use crate::api::handler::request::message::recall::__path_handle_recall_message;
+use crate::api::handler::request::message::export::__path_handle_export_messages;
+use crate::api::handler::request::message::forward::__path_handle_forward_messages;
+use crate::api::handler::request::message::reaction::__path_handle_add_reaction;
+use crate::api::handler::request::message::reaction::__path_handle_remove_reaction;
+use crate::api::handler::request::message::scheduled::__path_handle_cancel_scheduled_message;
+use crate::api::handler::request::message::scheduled::__path_handle_list_scheduled_messages;
+use crate::api::handler::request::message::scheduled::__path_handle_schedule_message;
+use crate::api::handler::request::message::scheduled::__path_handle_update_scheduled_message;
 use crate::api::handler::request::user::avatar::__path_handle_upload_avatar;
 use crate::api::handler::request::user::contact::__path_handle_get_contact_list;
 use crate::api::handler::request::user::contact::__path_handle_get_contact_timestamps;
@@ -64,7 +95,10 @@ use utoipa::OpenApi;
         handle_login, handle_logout, handle_register, handle_passwd, handle_connect, handle_check_session,
         handle_delete_me, handle_patch_me, handle_put_me, handle_get_me, handle_upload_avatar, handle_get_contact_timestamps, handle_get_contact_list,
         handle_info_friend, handle_add_friend, handle_list_friend, handle_list_friend_with_status, handle_get_status_by_userid,
+        handle_list_private_pins, handle_pin_private_message, handle_unpin_private_message,
         handle_creat_group, handle_info_group, handle_join_group, handle_leave_group, handle_list_group, handle_members_group,
+        handle_list_group_pins, handle_pin_group_message, handle_unpin_group_message,
+        handle_list_conversation, handle_update_conversation_settings, handle_update_message_ttl,
         // 下面都是manager接口，即必须管理员权限才能访问的api
         // ----------------message----------------
         handle_get_group_message, handle_get_session_message,
@@ -73,10 +107,16 @@ use utoipa::OpenApi;
         handle_get_private_messages_after_timestamp,
         handle_get_group_messages_after_timestamp, handle_get_all_group_messages_after_timestamp,
         handle_get_latest_timestamp_of_all_group_messages, handle_get_latest_timestamp_of_group,
-        handle_get_latest_timestamps_of_all_groups,
+        handle_get_latest_timestamps_of_all_groups, handle_get_group_thread, handle_get_unread_counts,
+        handle_recall_message, handle_delete_own_message, handle_edit_message, handle_get_message_edits,
+        handle_add_reaction, handle_remove_reaction, handle_get_unread_mentions, handle_search_messages,
+        handle_list_scheduled_messages, handle_schedule_message, handle_update_scheduled_message, handle_cancel_scheduled_message,
+        handle_forward_messages, handle_export_messages,
         // ----------------user----------------
         handle_tree_online, handle_delete_session,
         handle_delete_message, handle_get_message, handle_get_recent_messages, handle_get_user_recent_messages,
+        handle_delete_group_message, handle_get_full_group_message, handle_get_recent_group_messages,
+        handle_get_recent_messages_of_group, handle_get_user_recent_group_messages, handle_import_messages,
         handle_user_get_count, handle_delete_user, handle_get_userinfo, handle_delete_friendship, handle_get_friends, handle_list_user, handle_change_role
     ),
     components(
@@ -92,6 +132,7 @@ use utoipa::OpenApi;
     tags(
         (name = "测试接口", description = "仅用来测试对http的请求是否正常"),
         (name = "request/auth", description = "常规api：账号注册与身份认证"),
+        (name = "request/conversation", description = "常规api：会话列表"),
         (name = "request/friend", description = "常规api：好友"),
         (name = "request/group", description = "常规api：群组"),
         (name = "request/message", description = "常规api：聊天记录"),
@@ -1,4 +1,5 @@
 use thiserror::Error;
+use uchat_model::frame::FrameError;
 // use bcrypt::BcryptError;
 
 use crate::db::error::DBError;
@@ -21,6 +22,9 @@ pub enum RequestError {
     // SessionNotFound,
     #[error("Json序列化失败")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("二进制帧编解码失败: {0}")]
+    FrameError(#[from] FrameError),
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,49 @@
+use crate::server::AppState;
+use axum::{Json, extract::Extension, response::IntoResponse};
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use log::debug;
+use uchat_model::{
+    Empty, ManagerResponse,
+    manager::{ImportMessagesRequest, ImportSummary},
+};
+
+/// 导入由导出接口生成的 JSON 聊天记录，重复导入同一来源不会产生重复消息
+#[utoipa::path(
+    post,
+    path = "/manager/message/import",
+    request_body = ImportMessagesRequest,
+    responses(
+        (status = 200, description = "导入成功", body = ManagerResponse<ImportSummary>),
+        (status = 400, description = "来源标识或导出文件版本不合法", body = ManagerResponse<Empty>),
+        (status = 401, description = "认证失败", body = ManagerResponse<Empty>),
+        (status = 403, description = "权限不足", body = ManagerResponse<Empty>),
+        (status = 500, description = "服务器错误，已提交的批次保留，可重新导入", body = ManagerResponse<Empty>)
+    ),
+    tag = "manager/message"
+)]
+pub async fn handle_import_messages(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<ImportMessagesRequest>,
+) -> impl IntoResponse {
+    debug!("manager请求：导入聊天记录，来源 {}", payload.source);
+
+    let session_id = if let Some(session_id_cookie) = cookies.get("session_id") {
+        session_id_cookie.to_string()
+    } else {
+        return ManagerResponse::<()>::unauthorized().into_response();
+    };
+
+    let manager_lock = state.manager.lock().await;
+
+    // 验证权限
+    match manager_lock.check_session_role(&session_id).await {
+        Some(role) if role.is_admin() => manager_lock
+            .import_messages(payload)
+            .await
+            .into_response(),
+        Some(_) => ManagerResponse::<()>::forbidden().into_response(),
+        None => ManagerResponse::<()>::unauthorized().into_response(),
+    }
+}
@@ -1 +1,3 @@
+pub mod group;
+pub mod import;
 pub mod privite;
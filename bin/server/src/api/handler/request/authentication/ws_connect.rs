@@ -1,20 +1,30 @@
-use crate::api::handler::ws::handle_socket;
+use crate::api::handler::ws::{close_upgrade_required, handle_socket};
 use crate::server::AppState;
 use axum::{
-    extract::{Extension, ws::WebSocketUpgrade},
+    extract::{Extension, Query, ws::WebSocketUpgrade},
     response::{IntoResponse, Response},
 };
 use axum_extra::extract::TypedHeader;
 use headers::Cookie;
 use log::debug;
-use uchat_model::request::RequestResponse;
+use uchat_model::frame::negotiate_version;
+use uchat_model::message::{LEGACY_FRAME_VERSION, WsEncoding};
+use uchat_model::request::{RequestResponse, WsConnectQuery};
 /// 处理WebSocket升级请求
 /// 签名已调整为标准的WebSocket升级处理器，并从 Cookie 中提取 session_id
+/// 编码方式优先取查询参数 encoding，其次取 Sec-WebSocket-Protocol 协商结果，默认 JSON
+/// 两者同时提供时回显与 encoding 对应的子协议，客户端未提供该子协议时拒绝升级
+/// 帧版本取客户端声明的 [min_version, max_version] 与服务端支持范围的最高交集，
+/// 没有交集时仍完成升级，随后以 4426（upgrade required）关闭连接
 #[utoipa::path(
     get,
     path = "/auth/ws",
+    params(
+        WsConnectQuery
+    ),
     responses(
-        (status = 101, description = "WebSocket 协议升级成功"),
+        (status = 101, description = "WebSocket 协议升级成功；帧版本不兼容时随后以关闭码 4426 关闭"),
+        (status = 400, description = "查询参数 encoding 与子协议冲突"),
         (status = 401, description = "认证失败，缺少或非法 session_id Cookie")
     ),
     tag = "request/auth"
@@ -23,6 +33,7 @@ pub async fn handle_connect(
     ws: WebSocketUpgrade,                      // Axum 提供的 WebSocket 升级器
     Extension(state): Extension<AppState>,     // 获取共享的应用程序状态
     TypedHeader(cookies): TypedHeader<Cookie>, // 提取 HTTP 请求中的所有 Cookie
+    Query(query): Query<WsConnectQuery>,       // 连接参数（编码方式等）
 ) -> Response {
     debug!("收到WebSocket升级请求");
 
@@ -41,7 +52,48 @@ pub async fn handle_connect(
         }
     };
     drop(request_lock); // 及时释放 Mutex 锁
+
+    // 协商编码方式：查询参数 > 子协议 > 默认 JSON
+    let ws = ws.protocols(WsEncoding::PROTOCOLS);
+    let negotiated = ws
+        .selected_protocol()
+        .and_then(|p| p.to_str().ok())
+        .and_then(WsEncoding::from_protocol);
+    let (ws, encoding) = match (query.encoding, negotiated) {
+        // 查询参数覆盖子协议时，回显与之对应的子协议；客户端没有提供该子协议则视为冲突
+        (Some(encoding), Some(negotiated)) if encoding != negotiated => {
+            let ws = ws.protocols([encoding.protocol()]);
+            if ws.selected_protocol().is_none() {
+                debug!(
+                    "WebSocket 编码方式冲突：查询参数 {:?}，子协议 {:?}",
+                    encoding, negotiated
+                );
+                return RequestResponse::<()>::bad_request(format!(
+                    "查询参数 encoding 与子协议 {} 冲突",
+                    negotiated.protocol()
+                ))
+                .into_response();
+            }
+            (ws, encoding)
+        }
+        (Some(encoding), _) => (ws, encoding),
+        (None, negotiated) => (ws, negotiated.unwrap_or_default()),
+    };
+    debug!("WebSocket 连接协商编码方式: {:?}", encoding);
+
+    // 协商帧版本：未声明版本的旧客户端视为只支持 v1
+    let client_min = query.min_version.unwrap_or(LEGACY_FRAME_VERSION);
+    let client_max = query.max_version.unwrap_or(LEGACY_FRAME_VERSION);
+    let Some(version) = negotiate_version(client_min, client_max) else {
+        debug!(
+            "WebSocket 帧版本协商失败，客户端支持 {}-{}",
+            client_min, client_max
+        );
+        return ws.on_upgrade(move |socket| close_upgrade_required(socket, client_min, client_max));
+    };
+    debug!("WebSocket 连接协商帧版本: {}", version);
+
     // 使用 ws.on_upgrade 方法将 HTTP 连接升级为 WebSocket 连接
     // 然后将控制权交给 handle_socket 函数来处理 WebSocket 帧，并传递 session_id
-    ws.on_upgrade(move |socket| handle_socket(socket, session_id, state))
+    ws.on_upgrade(move |socket| handle_socket(socket, session_id, encoding, version, state))
 }
@@ -0,0 +1,43 @@
+use axum::{Extension, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{ConversationSummary, Empty, request::RequestResponse};
+
+/// 获取会话列表
+/// 包含全部私聊（有过消息往来的用户与好友）与所在的群，置顶的在前，其余按最后一条消息的时间递减
+#[utoipa::path(
+    get,
+    path = "/conversation/list",
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<ConversationSummary>>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/conversation"
+)]
+pub async fn handle_list_conversation(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+) -> impl IntoResponse {
+    debug!("处理获取会话列表请求");
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock.get_conversations(user_id).await.into_response()
+}
@@ -0,0 +1,2 @@
+pub mod list_conversation;
+pub mod settings_conversation;
@@ -0,0 +1,95 @@
+use axum::{Extension, Json, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    ConversationSettings, Empty,
+    request::{RequestResponse, UpdateConversationSettingsRequest, UpdateMessageTtlRequest},
+};
+
+/// 修改会话的置顶、免打扰设置
+#[utoipa::path(
+    patch,
+    path = "/conversation/settings",
+    request_body = UpdateConversationSettingsRequest,
+    responses(
+        (status = 200, description = "设置成功", body = RequestResponse<ConversationSettings>),
+        (status = 400, description = "参数错误", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在该群中", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/conversation"
+)]
+pub async fn handle_update_conversation_settings(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<UpdateConversationSettingsRequest>,
+) -> impl IntoResponse {
+    debug!("处理修改会话设置请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .update_conversation_settings(user_id, payload.conversation, payload.pinned, payload.muted)
+        .await
+        .into_response()
+}
+
+/// 设置会话的消息有效期（阅后即焚），私聊双方均可设置，群聊只有群管理员可以设置
+#[utoipa::path(
+    put,
+    path = "/conversation/ttl",
+    request_body = UpdateMessageTtlRequest,
+    responses(
+        (status = 200, description = "设置成功", body = RequestResponse<Empty>),
+        (status = 400, description = "参数错误", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是群管理员", body = RequestResponse<Empty>),
+        (status = 404, description = "私聊对方用户不存在", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/conversation"
+)]
+pub async fn handle_update_message_ttl(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<UpdateMessageTtlRequest>,
+) -> impl IntoResponse {
+    debug!("处理设置消息有效期请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .set_message_ttl(user_id, payload.conversation, payload.ttl_secs)
+        .await
+        .into_response()
+}
@@ -1,4 +1,5 @@
 pub mod add_friend;
 pub mod info_friend;
 pub mod list_friend;
+pub mod pin_friend;
 pub mod status_friend;
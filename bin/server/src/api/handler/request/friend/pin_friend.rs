@@ -0,0 +1,139 @@
+use axum::{Extension, extract::Path, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{Conversation, Empty, MessageId, PinnedMessage, request::RequestResponse};
+
+/// 获取与该用户私聊中的置顶消息（置顶时间递减）
+#[utoipa::path(
+    get,
+    path = "/friend/{friend_id}/pins",
+    params(
+        ("friend_id" = u32, Path, description = "对方用户ID")
+    ),
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<PinnedMessage>>),
+        (status = 400, description = "对方是自己", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/friend"
+)]
+pub async fn handle_list_private_pins(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path(friend_id): Path<u32>,
+) -> impl IntoResponse {
+    debug!("处理获取私聊置顶消息请求: {}", friend_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .get_pinned_messages(user_id, Conversation::Private(friend_id))
+        .await
+        .into_response()
+}
+
+/// 置顶与该用户私聊中的消息，私聊双方均可操作；置顶状态变化时向会话参与者推送 MessagePinned
+#[utoipa::path(
+    post,
+    path = "/friend/{friend_id}/pins/{message_id}",
+    params(
+        ("friend_id" = u32, Path, description = "对方用户ID"),
+        ("message_id" = u64, Path, description = "消息ID")
+    ),
+    responses(
+        (status = 200, description = "置顶成功", body = RequestResponse<Empty>),
+        (status = 400, description = "消息已撤回、置顶数量已达上限或对方是自己", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 404, description = "消息不存在或不在该会话中", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/friend"
+)]
+pub async fn handle_pin_private_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path((friend_id, message_id)): Path<(u32, MessageId)>,
+) -> impl IntoResponse {
+    debug!("处理置顶私聊消息请求: {} {}", friend_id, message_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .set_message_pinned(user_id, Conversation::Private(friend_id), message_id, true)
+        .await
+        .into_response()
+}
+
+/// 取消置顶与该用户私聊中的消息，私聊双方均可操作；置顶状态变化时向会话参与者推送 MessagePinned
+#[utoipa::path(
+    delete,
+    path = "/friend/{friend_id}/pins/{message_id}",
+    params(
+        ("friend_id" = u32, Path, description = "对方用户ID"),
+        ("message_id" = u64, Path, description = "消息ID")
+    ),
+    responses(
+        (status = 200, description = "取消置顶成功", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 404, description = "消息未置顶", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/friend"
+)]
+pub async fn handle_unpin_private_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path((friend_id, message_id)): Path<(u32, MessageId)>,
+) -> impl IntoResponse {
+    debug!("处理取消置顶私聊消息请求: {} {}", friend_id, message_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .set_message_pinned(user_id, Conversation::Private(friend_id), message_id, false)
+        .await
+        .into_response()
+}
@@ -17,7 +17,7 @@ use uchat_model::{
         GroupRequest
     ),
     responses(
-        (status = 200, description = "获取成功", body = RequestResponse<GroupDetailedInfo>),
+        (status = 200, description = "获取成功，请求者是群成员时附带群置顶消息", body = RequestResponse<GroupDetailedInfo>),
         (status = 401, description = "认证失败", body = RequestResponse<Empty>),
         (status = 404, description = "找不到群组", body = RequestResponse<Empty>),
         (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
@@ -39,12 +39,15 @@ pub async fn handle_info_group(
     let session_id = session_id.unwrap();
 
     let request_lock = state.request.lock().await;
-    let _user_id = match request_lock.check_session(&session_id).await {
+    let user_id = match request_lock.check_session(&session_id).await {
         Some(uid) => uid,
         None => {
             return RequestResponse::<()>::unauthorized().into_response();
         }
     };
 
-    request_lock.get_groupinfo(payload.id).await.into_response()
+    request_lock
+        .get_groupinfo(user_id, payload.id)
+        .await
+        .into_response()
 }
@@ -4,3 +4,4 @@ pub mod join_group;
 pub mod leave_group;
 pub mod list_group;
 pub mod members_group;
+pub mod pin_group;
@@ -0,0 +1,141 @@
+use axum::{Extension, extract::Path, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{Conversation, Empty, MessageId, PinnedMessage, request::RequestResponse};
+
+/// 获取群聊的置顶消息（置顶时间递减）
+#[utoipa::path(
+    get,
+    path = "/group/{group_id}/pins",
+    params(
+        ("group_id" = u32, Path, description = "群组ID")
+    ),
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<PinnedMessage>>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是该群成员", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/group"
+)]
+pub async fn handle_list_group_pins(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path(group_id): Path<u32>,
+) -> impl IntoResponse {
+    debug!("处理获取群置顶消息请求: {}", group_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .get_pinned_messages(user_id, Conversation::Group(group_id))
+        .await
+        .into_response()
+}
+
+/// 置顶群聊中的消息，只有群管理员可以操作；置顶状态变化时向会话参与者推送 MessagePinned
+#[utoipa::path(
+    post,
+    path = "/group/{group_id}/pins/{message_id}",
+    params(
+        ("group_id" = u32, Path, description = "群组ID"),
+        ("message_id" = u64, Path, description = "消息ID")
+    ),
+    responses(
+        (status = 200, description = "置顶成功", body = RequestResponse<Empty>),
+        (status = 400, description = "消息已撤回或置顶数量已达上限", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是群管理员", body = RequestResponse<Empty>),
+        (status = 404, description = "消息不存在或不在该会话中", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/group"
+)]
+pub async fn handle_pin_group_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path((group_id, message_id)): Path<(u32, MessageId)>,
+) -> impl IntoResponse {
+    debug!("处理置顶群消息请求: {} {}", group_id, message_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .set_message_pinned(user_id, Conversation::Group(group_id), message_id, true)
+        .await
+        .into_response()
+}
+
+/// 取消置顶群聊中的消息，只有群管理员可以操作；置顶状态变化时向会话参与者推送 MessagePinned
+#[utoipa::path(
+    delete,
+    path = "/group/{group_id}/pins/{message_id}",
+    params(
+        ("group_id" = u32, Path, description = "群组ID"),
+        ("message_id" = u64, Path, description = "消息ID")
+    ),
+    responses(
+        (status = 200, description = "取消置顶成功", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是群管理员", body = RequestResponse<Empty>),
+        (status = 404, description = "消息未置顶", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/group"
+)]
+pub async fn handle_unpin_group_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path((group_id, message_id)): Path<(u32, MessageId)>,
+) -> impl IntoResponse {
+    debug!("处理取消置顶群消息请求: {} {}", group_id, message_id);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .set_message_pinned(user_id, Conversation::Group(group_id), message_id, false)
+        .await
+        .into_response()
+}
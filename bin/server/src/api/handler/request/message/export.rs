@@ -0,0 +1,81 @@
+use axum::{
+    Extension,
+    extract::Query,
+    http::header,
+    response::IntoResponse,
+};
+use log::debug;
+
+use crate::{api::request::ExportOutput, server::AppState};
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty, ExportedFile,
+    request::{ExportQuery, RequestResponse},
+};
+
+/// 导出聊天记录（JSON、CSV 或自包含的 HTML）
+/// 指定 peer_id 或 group_id 时只导出该会话，都缺省时导出全部会话及好友、群组列表
+/// 文件较小时直接作为附件下载，较大时分片上传到对象存储并返回下载地址
+#[utoipa::path(
+    get,
+    path = "/message/export",
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "导出文件（附件下载）；文件过大时返回对象存储中的下载地址", body = RequestResponse<ExportedFile>),
+        (status = 400, description = "参数不合法", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是该群成员", body = RequestResponse<Empty>),
+        (status = 404, description = "用户或群组不存在", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_export_messages(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Query(query): Query<ExportQuery>,
+) -> impl IntoResponse {
+    debug!("处理导出聊天记录请求: {:?}", query);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    let job = match request_lock.prepare_export(user_id, query).await {
+        Ok(job) => job,
+        Err(resp) => return resp.into_response(),
+    };
+    drop(request_lock); // 导出耗时较长，及时释放 Mutex 锁
+
+    match job.run().await {
+        Ok(ExportOutput::File {
+            filename,
+            content_type,
+            data,
+        }) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ],
+            data,
+        )
+            .into_response(),
+        Ok(ExportOutput::Stored(file)) => RequestResponse::ok("导出成功", file).into_response(),
+        Err(resp) => resp.into_response(),
+    }
+}
@@ -0,0 +1,53 @@
+use axum::{Extension, Json, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty, MessageId,
+    request::{ForwardMessagesRequest, RequestResponse},
+};
+
+/// 将一个会话中的若干消息转发到另一个会话（私聊或群聊），保留消息类型与附件并记录转发来源
+#[utoipa::path(
+    post,
+    path = "/message/forward",
+    request_body = ForwardMessagesRequest,
+    responses(
+        (status = 200, description = "转发成功，按原顺序返回新消息的ID", body = RequestResponse<Vec<MessageId>>),
+        (status = 400, description = "消息数量不合法、转发给自己或消息内容不合法", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在源会话或目标会话的群中", body = RequestResponse<Empty>),
+        (status = 404, description = "消息不存在、已撤回或不可见，或私聊对方用户不存在", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误，没有消息被转发", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_forward_messages(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<ForwardMessagesRequest>,
+) -> impl IntoResponse {
+    debug!("处理转发消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .forward_messages(user_id, payload.source, payload.message_ids, payload.target)
+        .await
+        .into_response()
+}
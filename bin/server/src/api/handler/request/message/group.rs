@@ -11,8 +11,8 @@ use crate::server::AppState;
 use axum_extra::extract::TypedHeader;
 use headers::Cookie;
 use uchat_model::{
-    Empty, IdMessagePair, SessionMessage,
-    request::{AfterTimestampQuery, MessageRequest, RequestResponse},
+    Empty, IdMessagePair, MessagePage, MessageThread, SessionMessage,
+    request::{AfterTimestampQuery, MessageRequest, RequestResponse, ThreadQuery},
 };
 
 /// 获取群聊聊天记录
@@ -23,7 +23,8 @@ use uchat_model::{
         MessageRequest
     ),
     responses(
-        (status = 200, description = "获取成功", body = RequestResponse<Vec<SessionMessage>>),
+        (status = 200, description = "获取成功", body = RequestResponse<MessagePage>),
+        (status = 400, description = "分页参数错误", body = RequestResponse<Empty>),
         (status = 401, description = "认证失败", body = RequestResponse<Empty>),
         (status = 404, description = "找不到群组", body = RequestResponse<Empty>),
         (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
@@ -45,7 +46,7 @@ pub async fn handle_get_group_message(
     let session_id = session_id.unwrap();
 
     let request_lock = state.request.lock().await;
-    let _user_id = match request_lock.check_session(&session_id).await {
+    let user_id = match request_lock.check_session(&session_id).await {
         Some(uid) => uid,
         None => {
             return RequestResponse::<()>::unauthorized().into_response();
@@ -53,7 +54,7 @@ pub async fn handle_get_group_message(
     };
 
     request_lock
-        .get_group_messages(payload.id, payload.offset)
+        .get_group_messages(user_id, payload.id, payload.before_id, payload.after_id, payload.limit)
         .await
         .into_response()
 }
@@ -85,13 +86,13 @@ pub async fn handle_get_latest_timestamp_of_group(
 
     let session_id = session_id.unwrap();
     let request_lock = state.request.lock().await;
-    let _user_id = match request_lock.check_session(&session_id).await {
+    let user_id = match request_lock.check_session(&session_id).await {
         Some(uid) => uid,
         None => return RequestResponse::<()>::unauthorized().into_response(),
     };
 
     request_lock
-        .get_latest_timestamp_of_group(group_id)
+        .get_latest_timestamp_of_group(user_id, group_id)
         .await
         .into_response()
 }
@@ -191,13 +192,13 @@ pub async fn handle_get_group_messages_after_timestamp(
 
     let session_id = session_id.unwrap();
     let request_lock = state.request.lock().await;
-    let _user_id = match request_lock.check_session(&session_id).await {
+    let user_id = match request_lock.check_session(&session_id).await {
         Some(uid) => uid,
         None => return RequestResponse::<()>::unauthorized().into_response(),
     };
 
     request_lock
-        .get_group_messages_after_timestamp(group_id, timestamp)
+        .get_group_messages_after_timestamp(user_id, group_id, timestamp)
         .await
         .into_response()
 }
@@ -238,3 +239,51 @@ pub async fn handle_get_all_group_messages_after_timestamp(
         .await
         .into_response()
 }
+
+/// 获取群聊话题：根消息及直接回复它的消息（回复按消息ID游标分页）
+#[utoipa::path(
+    get,
+    path = "/message/group/{group_id}/thread/{message_id}",
+    params(
+        ("group_id" = u32, Path, description = "群组ID"),
+        ("message_id" = u64, Path, description = "根消息ID"),
+        ThreadQuery
+    ),
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<MessageThread>),
+        (status = 400, description = "分页参数错误", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在该群中", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_get_group_thread(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Path((group_id, message_id)): Path<(u32, u64)>,
+    Query(payload): Query<ThreadQuery>,
+) -> impl IntoResponse {
+    debug!("处理获取群聊话题请求: 群 {} 消息 {} {:?}", group_id, message_id, payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .get_group_thread(user_id, group_id, message_id, payload)
+        .await
+        .into_response()
+}
@@ -1,2 +1,10 @@
 pub mod group;
 pub mod private;
+pub mod unread;
+pub mod recall;
+pub mod reaction;
+pub mod mention;
+pub mod search;
+pub mod scheduled;
+pub mod forward;
+pub mod export;
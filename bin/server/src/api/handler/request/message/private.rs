@@ -11,7 +11,7 @@ use crate::server::AppState;
 use axum_extra::extract::TypedHeader;
 use headers::Cookie;
 use uchat_model::{
-    Empty, IdMessagePair, SessionMessage,
+    Empty, IdMessagePair, MessagePage, SessionMessage,
     request::{AfterTimestampQuery, MessageRequest, RequestResponse},
 };
 
@@ -23,7 +23,8 @@ use uchat_model::{
         MessageRequest
     ),
     responses(
-        (status = 200, description = "获取成功", body = RequestResponse<Vec<SessionMessage>>),
+        (status = 200, description = "获取成功", body = RequestResponse<MessagePage>),
+        (status = 400, description = "分页参数错误", body = RequestResponse<Empty>),
         (status = 401, description = "认证失败", body = RequestResponse<Empty>),
         (status = 404, description = "找不到群组", body = RequestResponse<Empty>),
         (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
@@ -53,7 +54,13 @@ pub async fn handle_get_session_message(
     };
 
     request_lock
-        .get_messages(user_id, payload.id, payload.offset)
+        .get_messages(
+            user_id,
+            payload.id,
+            payload.before_id,
+            payload.after_id,
+            payload.limit,
+        )
         .await
         .into_response()
 }
@@ -0,0 +1,108 @@
+use axum::{Extension, Json, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty,
+    request::{ReactionRequest, RequestResponse},
+};
+
+/// 对消息添加表情回应（私聊或群聊）
+#[utoipa::path(
+    post,
+    path = "/message/reaction",
+    request_body = ReactionRequest,
+    responses(
+        (status = 200, description = "操作成功，返回该表情的回应人数", body = RequestResponse<u32>),
+        (status = 400, description = "无效的表情或消息已撤回", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在该群中", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_add_reaction(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<ReactionRequest>,
+) -> impl IntoResponse {
+    debug!("处理添加表情回应请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .update_reaction(
+            user_id,
+            payload.conversation,
+            payload.message_id,
+            payload.emoji,
+            true,
+        )
+        .await
+        .into_response()
+}
+
+/// 取消自己对消息的表情回应
+#[utoipa::path(
+    delete,
+    path = "/message/reaction",
+    request_body = ReactionRequest,
+    responses(
+        (status = 200, description = "操作成功，返回该表情的回应人数", body = RequestResponse<u32>),
+        (status = 400, description = "无效的表情或消息已撤回", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在该群中", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_remove_reaction(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<ReactionRequest>,
+) -> impl IntoResponse {
+    debug!("处理取消表情回应请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .update_reaction(
+            user_id,
+            payload.conversation,
+            payload.message_id,
+            payload.emoji,
+            false,
+        )
+        .await
+        .into_response()
+}
@@ -0,0 +1,202 @@
+use axum::{
+    Extension, Json,
+    extract::Query,
+    response::IntoResponse,
+};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty, MessageEdit,
+    request::{
+        DeleteMessageRequest, EditMessageRequest, MessageEditsQuery, RecallMessageRequest,
+        RequestResponse,
+    },
+};
+
+/// 撤回自己发送的消息（私聊或群聊），超过撤回时限后不可撤回
+#[utoipa::path(
+    post,
+    path = "/message/recall",
+    request_body = RecallMessageRequest,
+    responses(
+        (status = 200, description = "撤回成功", body = RequestResponse<i64>),
+        (status = 400, description = "消息已撤回", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是自己的消息或已超过撤回时限", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_recall_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<RecallMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理撤回消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .recall_message(user_id, payload.conversation, payload.message_id)
+        .await
+        .into_response()
+}
+
+/// 删除消息：默认仅从自己的聊天记录中隐藏；for_everyone 时由发送者在时限内为所有人删除
+#[utoipa::path(
+    post,
+    path = "/message/delete",
+    request_body = DeleteMessageRequest,
+    responses(
+        (status = 200, description = "删除成功，为所有人删除时返回删除时间", body = RequestResponse<Option<i64>>),
+        (status = 400, description = "消息已撤回", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是自己的消息或已超过删除时限", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_delete_own_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<DeleteMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理删除消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    if payload.for_everyone {
+        request_lock
+            .delete_message_for_everyone(user_id, payload.conversation, payload.message_id)
+            .await
+            .into_response()
+    } else {
+        request_lock
+            .hide_message(user_id, payload.conversation, payload.message_id)
+            .await
+            .into_response()
+    }
+}
+
+/// 编辑自己发送的文本消息（私聊或群聊）
+#[utoipa::path(
+    post,
+    path = "/message/edit",
+    request_body = EditMessageRequest,
+    responses(
+        (status = 200, description = "编辑成功", body = RequestResponse<i64>),
+        (status = 400, description = "内容为空、非文本消息或消息已撤回", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是自己的消息", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_edit_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<EditMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理编辑消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .edit_message(
+            user_id,
+            payload.conversation,
+            payload.message_id,
+            payload.message,
+        )
+        .await
+        .into_response()
+}
+
+/// 获取消息的编辑历史
+#[utoipa::path(
+    get,
+    path = "/message/edits",
+    params(
+        MessageEditsQuery
+    ),
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<MessageEdit>>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不是会话参与者", body = RequestResponse<Empty>),
+        (status = 404, description = "找不到消息", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_get_message_edits(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Query(payload): Query<MessageEditsQuery>,
+) -> impl IntoResponse {
+    debug!("处理获取消息编辑历史请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .get_message_edits(user_id, payload.is_group, payload.message_id)
+        .await
+        .into_response()
+}
@@ -0,0 +1,176 @@
+use axum::{Extension, Json, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty, ScheduledMessage,
+    request::{
+        CancelScheduledMessageRequest, RequestResponse, ScheduleMessageRequest,
+        UpdateScheduledMessageRequest,
+    },
+};
+
+/// 获取当前用户创建的全部定时消息（按发送时间排序）
+#[utoipa::path(
+    get,
+    path = "/message/scheduled",
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<ScheduledMessage>>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_list_scheduled_messages(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+) -> impl IntoResponse {
+    debug!("处理获取定时消息请求");
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .list_scheduled_messages(user_id)
+        .await
+        .into_response()
+}
+
+/// 创建定时消息，到达 send_at 后由服务器代为发送
+#[utoipa::path(
+    post,
+    path = "/message/scheduled",
+    request_body = ScheduleMessageRequest,
+    responses(
+        (status = 200, description = "创建成功，返回定时消息ID", body = RequestResponse<u64>),
+        (status = 400, description = "发送时间无效、消息内容为空或缺少附件", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 403, description = "不在该群中", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_schedule_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<ScheduleMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理创建定时消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .schedule_message(user_id, payload)
+        .await
+        .into_response()
+}
+
+/// 修改尚未发送的定时消息的内容或发送时间
+#[utoipa::path(
+    patch,
+    path = "/message/scheduled",
+    request_body = UpdateScheduledMessageRequest,
+    responses(
+        (status = 200, description = "修改成功，返回修改后的定时消息", body = RequestResponse<ScheduledMessage>),
+        (status = 400, description = "发送时间无效或消息内容为空", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 404, description = "定时消息不存在或已开始发送", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_update_scheduled_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<UpdateScheduledMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理修改定时消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .update_scheduled_message(user_id, payload.id, payload.message, payload.send_at)
+        .await
+        .into_response()
+}
+
+/// 取消尚未发送的定时消息
+#[utoipa::path(
+    delete,
+    path = "/message/scheduled",
+    request_body = CancelScheduledMessageRequest,
+    responses(
+        (status = 200, description = "取消成功", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 404, description = "定时消息不存在或已开始发送", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_cancel_scheduled_message(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Json(payload): Json<CancelScheduledMessageRequest>,
+) -> impl IntoResponse {
+    debug!("处理取消定时消息请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .cancel_scheduled_message(user_id, payload.id)
+        .await
+        .into_response()
+}
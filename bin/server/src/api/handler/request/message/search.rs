@@ -0,0 +1,54 @@
+use axum::{Extension, extract::Query, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{
+    Empty, SearchPage,
+    request::{RequestResponse, SearchMessagesQuery},
+};
+
+/// 全文搜索聊天记录
+/// 只搜索本人参与的私聊与仍在的群中未撤回的消息，结果按时间递减并附带关键词命中区间
+#[utoipa::path(
+    get,
+    path = "/message/search",
+    params(
+        SearchMessagesQuery
+    ),
+    responses(
+        (status = 200, description = "搜索成功", body = RequestResponse<SearchPage>),
+        (status = 400, description = "参数错误", body = RequestResponse<Empty>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_search_messages(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+    Query(payload): Query<SearchMessagesQuery>,
+) -> impl IntoResponse {
+    debug!("处理搜索聊天记录请求: {:?}", payload);
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock
+        .search_messages(user_id, payload)
+        .await
+        .into_response()
+}
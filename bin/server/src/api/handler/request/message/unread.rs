@@ -0,0 +1,42 @@
+use axum::{Extension, response::IntoResponse};
+use log::debug;
+
+use crate::server::AppState;
+use axum_extra::extract::TypedHeader;
+use headers::Cookie;
+use uchat_model::{Empty, UnreadCount, request::RequestResponse};
+
+/// 获取当前用户各会话的未读数（由已读游标推导，只返回有未读消息的会话）
+#[utoipa::path(
+    get,
+    path = "/message/unread",
+    responses(
+        (status = 200, description = "获取成功", body = RequestResponse<Vec<UnreadCount>>),
+        (status = 401, description = "认证失败", body = RequestResponse<Empty>),
+        (status = 500, description = "服务器错误", body = RequestResponse<Empty>)
+    ),
+    tag = "request/message"
+)]
+pub async fn handle_get_unread_counts(
+    Extension(state): Extension<AppState>,
+    TypedHeader(cookies): TypedHeader<Cookie>,
+) -> impl IntoResponse {
+    debug!("处理获取未读数请求");
+
+    let session_id = cookies.get("session_id").map(str::to_string);
+    if session_id.is_none() {
+        return RequestResponse::<()>::unauthorized().into_response();
+    }
+
+    let session_id = session_id.unwrap();
+
+    let request_lock = state.request.lock().await;
+    let user_id = match request_lock.check_session(&session_id).await {
+        Some(uid) => uid,
+        None => {
+            return RequestResponse::<()>::unauthorized().into_response();
+        }
+    };
+
+    request_lock.get_unread_counts(user_id).await.into_response()
+}
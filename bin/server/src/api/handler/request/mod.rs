@@ -1,4 +1,5 @@
 pub mod authentication;
+pub mod conversation;
 pub mod friend;
 pub mod group;
 pub mod message;
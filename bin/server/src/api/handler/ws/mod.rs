@@ -1,18 +1,35 @@
+use crate::api::error::RequestError;
+use crate::api::request::OutgoingMessage;
 use crate::server::AppState;
-use axum::extract::ws::{Message, WebSocket};
+use crate::session::Outbound;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
 use futures::{sink::SinkExt, stream::StreamExt};
 use log::{debug, error, info, warn};
 use tokio::sync::mpsc; // tokio::sync::Mutex for Request
-use uchat_model::message::ClientMessage;
+use uchat_model::frame::{
+    DEFAULT_MAX_FRAGMENT_LEN, FrameCodec, FrameError, FrameReassembler, MIN_VERSION, VERSION,
+};
+use uchat_model::message::{CLOSE_UPGRADE_REQUIRED, ClientMessage, ServerMessage, WsEncoding};
 /// 处理 WebSocket 连接的实际逻辑
-pub async fn handle_socket(socket: WebSocket, session_id: String, state: AppState) {
-    info!("WebSocket 连接已建立，会话ID: {}", &session_id);
+/// encoding 为升级时协商出的编码方式，决定本连接所有出站消息的序列化格式
+/// version 为协商出的帧版本，二进制编码下出站帧按该版本打包
+pub async fn handle_socket(
+    socket: WebSocket,
+    session_id: String,
+    encoding: WsEncoding,
+    version: u8,
+    state: AppState,
+) {
+    info!(
+        "WebSocket 连接已建立，会话ID: {}，编码方式: {:?}，帧版本: {}",
+        &session_id, encoding, version
+    );
 
     // 分割 WebSocket 连接为发送端和接收端
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
     // 创建一个 MPSC 通道，用于从其他任务向此 WebSocket 连接发送消息
-    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Outbound>();
 
     // 克隆 session_id 以便在 tokio::spawn 任务中使用
     let session_id_for_task = session_id.clone();
@@ -41,10 +58,34 @@ pub async fn handle_socket(socket: WebSocket, session_id: String, state: AppStat
     // 启动一个独立的 Tokio 任务，用于从 MPSC 接收器接收消息，并将其发送到 WebSocket
     // let ws_sender_clone = ws_sender.clone();
     tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if ws_sender.send(msg).await.is_err() {
-                // 如果发送失败，说明 WebSocket 连接已断开
-                break;
+        // 出站分片消息的 frag_id，连接内自增
+        let mut next_frag_id: u32 = 0;
+        'forward: while let Some(outbound) = rx.recv().await {
+            let msgs = match outbound {
+                Outbound::Raw(msg) => vec![msg],
+                Outbound::Server(server_message) => {
+                    match encode_server_message(encoding, version, &server_message, next_frag_id) {
+                        Ok(msgs) => msgs,
+                        Err(e) => {
+                            error!(
+                                "会话 {} 编码出站消息失败: {}",
+                                &session_id_for_task, e
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+            if msgs.len() > 1 {
+                next_frag_id = next_frag_id.wrapping_add(1);
+            }
+            for msg in msgs {
+                // 关闭帧之后不能再发送任何帧（包括注销会话时的 Close），发送后即结束转发
+                let is_close = matches!(msg, Message::Close(_));
+                if ws_sender.send(msg).await.is_err() || is_close {
+                    // 如果发送失败，说明 WebSocket 连接已断开
+                    break 'forward;
+                }
             }
         }
         info!(
@@ -53,22 +94,50 @@ pub async fn handle_socket(socket: WebSocket, session_id: String, state: AppStat
         );
     });
 
+    // 二进制分片重组器，非分片帧会直接解析返回
+    let mut reassembler = FrameReassembler::<ClientMessage>::default();
+
     // 循环处理后续的 WebSocket 消息
     while let Some(msg_result) = ws_receiver.next().await {
         match msg_result {
             Ok(Message::Text(text)) => {
                 debug!("会话 {} 收到文本消息: {}", session_id, text);
-                socket_handle_text(&session_id, &text, state.clone()).await;
+                match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(msg) => socket_handle_message(&session_id, msg, state.clone()).await,
+                    Err(e) => {
+                        error!("会话 {} 发送消息 {} 解析消息失败: {}", session_id, text, e);
+                    }
+                }
             }
             Ok(Message::Binary(bin)) => {
                 debug!("会话 {} 收到二进制消息 ({} 字节)", session_id, bin.len());
-                // 处理二进制消息
+                match reassembler.push(&bin) {
+                    Ok(Some(msg)) => socket_handle_message(&session_id, msg, state.clone()).await,
+                    Ok(None) => {
+                        debug!(
+                            "会话 {} 收到分片，等待重组（未完成 {} 条）",
+                            session_id,
+                            reassembler.pending_len()
+                        );
+                    }
+                    Err(FrameError::InvalidVersion(v)) => {
+                        warn!("会话 {} 发送了不支持的帧版本 {}，要求升级", session_id, v);
+                        let request_lock = state.request.lock().await;
+                        request_lock
+                            .send_to_session(&session_id, upgrade_required_frame().into())
+                            .await;
+                        break;
+                    }
+                    Err(e) => {
+                        error!("会话 {} 发送的二进制帧解析失败: {}", session_id, e);
+                    }
+                }
             }
             Ok(Message::Ping(pong)) => {
                 debug!("会话 {} 收到 Ping 消息", session_id);
                 let request_lock = state.request.lock().await; // 获取 Request 的 Mutex 锁
                 request_lock
-                    .send_to_session(&session_id, Message::Pong(pong))
+                    .send_to_session(&session_id, Message::Pong(pong).into())
                     .await;
             }
             Ok(Message::Pong(_)) => {
@@ -93,28 +162,132 @@ pub async fn handle_socket(socket: WebSocket, session_id: String, state: AppStat
     info!("WebSocket 处理任务结束，会话ID: {}", session_id);
 }
 
-/// 具体详细的处理文本消息逻辑
+/// 帧版本不兼容时发送的关闭帧
+fn upgrade_required_frame() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: CLOSE_UPGRADE_REQUIRED,
+        reason: format!("upgrade required: 服务端支持帧版本 {}-{}", MIN_VERSION, VERSION).into(),
+    }))
+}
+
+/// 握手时没有共同帧版本：发送 upgrade required 关闭帧后结束连接
+pub async fn close_upgrade_required(mut socket: WebSocket, client_min: u8, client_max: u8) {
+    info!(
+        "客户端帧版本 {}-{} 与服务端 {}-{} 不兼容，关闭连接",
+        client_min, client_max, MIN_VERSION, VERSION
+    );
+    if let Err(e) = socket.send(upgrade_required_frame()).await {
+        warn!("发送 upgrade required 关闭帧失败: {}", e);
+    }
+}
+
+/// 按连接协商的编码方式与帧版本序列化出站消息
+/// 二进制编码下超过 DEFAULT_MAX_FRAGMENT_LEN 的消息会被拆成多个分片帧（v2 起）
+fn encode_server_message(
+    encoding: WsEncoding,
+    version: u8,
+    msg: &ServerMessage,
+    frag_id: u32,
+) -> Result<Vec<Message>, RequestError> {
+    match encoding {
+        WsEncoding::Json => {
+            let json = serde_json::to_string(msg)?;
+            Ok(vec![Message::Text(axum::extract::ws::Utf8Bytes::from(json))])
+        }
+        WsEncoding::Binary => {
+            let frames = msg.to_frames_versioned(version, DEFAULT_MAX_FRAGMENT_LEN, frag_id)?;
+            Ok(frames
+                .into_iter()
+                .map(|frame| Message::Binary(frame.into()))
+                .collect())
+        }
+    }
+}
+
+/// 具体详细的处理客户端消息逻辑（文本帧与二进制帧解码后统一在此处理）
 /// 完成各类信息的处理与转发，并记录数据库
-async fn socket_handle_text(session_id: &str, text: &str, state: AppState) {
-    match serde_json::from_str::<ClientMessage>(text) {
-        Ok(ClientMessage::SendMessage { receiver, message }) => {
-            debug!("私聊：发送给 {}, 内容: {}", receiver, message);
+async fn socket_handle_message(session_id: &str, msg: ClientMessage, state: AppState) {
+    match msg {
+        ClientMessage::SendMessage {
+            receiver,
+            message,
+            client_msg_id,
+            message_type,
+            attachment,
+            reply_to,
+        } => {
+            debug!("私聊：发送给 {}, 类型: {}, 内容: {}", receiver, message_type, message);
 
+            let outgoing = OutgoingMessage {
+                message_type,
+                message,
+                attachment,
+                client_msg_id,
+                reply_to,
+                mentions: Vec::new(),
+                mention_all: false,
+                forwarded_from: None,
+            };
             let req_lock = state.request.lock().await;
             req_lock
-                .send_to_user_v2(session_id, receiver, &message)
+                .send_to_user_v2(session_id, receiver, outgoing)
                 .await;
         }
 
-        Ok(ClientMessage::SendGroupMessage { group_id, message }) => {
-            debug!("群聊：群号 {}, 内容: {}", group_id, message);
+        ClientMessage::SendGroupMessage {
+            group_id,
+            message,
+            client_msg_id,
+            message_type,
+            attachment,
+            reply_to,
+            mentions,
+            mention_all,
+        } => {
+            debug!("群聊：群号 {}, 类型: {}, 内容: {}", group_id, message_type, message);
+            let outgoing = OutgoingMessage {
+                message_type,
+                message,
+                attachment,
+                client_msg_id,
+                reply_to,
+                mentions,
+                mention_all,
+                forwarded_from: None,
+            };
             let req_lock = state.request.lock().await;
             req_lock
-                .send_to_group_v2(session_id, group_id, &message)
+                .send_to_group_v2(session_id, group_id, outgoing)
                 .await;
         }
-        Err(e) => {
-            error!("会话 {} 发送消息 {} 解析消息失败: {}", session_id, text, e);
+
+        ClientMessage::Typing {
+            target,
+            is_group,
+            state: typing_state,
+        } => {
+            debug!("输入状态：会话 {}（群聊: {}）{:?}", target, is_group, typing_state);
+            let req_lock = state.request.lock().await;
+            req_lock
+                .relay_typing(session_id, target, is_group, typing_state)
+                .await;
+        }
+
+        ClientMessage::MarkRead {
+            conversation,
+            up_to_message_id,
+        } => {
+            debug!("标记已读：会话 {:?} 至消息 {}", conversation, up_to_message_id);
+            let req_lock = state.request.lock().await;
+            req_lock
+                .mark_read(session_id, conversation, up_to_message_id)
+                .await;
+        }
+
+        ClientMessage::Received { message_id } => {
+            debug!("确认收到消息 {}", message_id);
+            let req_lock = state.request.lock().await;
+            req_lock.confirm_delivery(session_id, message_id).await;
         }
     }
 }
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use log::{error, info, warn};
+use uchat_model::{
+    Conversation, ExportDocument, ManagerResponse,
+    manager::{ImportMessagesRequest, ImportSummary},
+};
+
+use super::Manager;
+use crate::db::{ImportMessage, error::DBError};
+
+/// 每个事务写入的消息条数
+const IMPORT_BATCH_SIZE: usize = 200;
+/// 来源标识的最大长度，与 imported_messages.source 列一致
+const MAX_SOURCE_LEN: usize = 64;
+
+/// 将源实例中的用户和群组对应到本实例，结果按源ID缓存
+struct IdResolver<'a> {
+    user_map: &'a HashMap<u32, u32>,
+    group_map: &'a HashMap<u32, u32>,
+    users: HashMap<u32, Option<u32>>,
+}
+
+impl Manager {
+    /// 导入由导出接口生成的 JSON 聊天记录，保留原时间戳与发送者
+    /// 每 IMPORT_BATCH_SIZE 条消息一个事务；按 (source, 源消息ID) 去重，中途失败后可用同一 source 重新导入
+    /// 转发来源指向源实例中的消息，导入时不保留；附件沿用原 object_key
+    pub async fn import_messages(
+        &self,
+        request: ImportMessagesRequest,
+    ) -> ManagerResponse<ImportSummary> {
+        let ImportMessagesRequest {
+            source,
+            document,
+            user_map,
+            group_map,
+        } = request;
+        let source = source.trim();
+        if source.is_empty() || source.chars().count() > MAX_SOURCE_LEN {
+            return ManagerResponse::bad_request(format!(
+                "来源标识不能为空且不能超过 {} 个字符",
+                MAX_SOURCE_LEN
+            ));
+        }
+        if document.version != ExportDocument::VERSION {
+            return ManagerResponse::bad_request(format!(
+                "不支持的导出文件版本：{}",
+                document.version
+            ));
+        }
+        info!(
+            "响应manager导入聊天记录: 来源 {}, 用户 {}, 会话 {} 个",
+            source,
+            document.user_id,
+            document.conversations.len()
+        );
+
+        let mut resolver = IdResolver {
+            user_map: &user_map,
+            group_map: &group_map,
+            users: HashMap::new(),
+        };
+        let mut summary = ImportSummary::default();
+        let owner = match resolver
+            .user(self, document.user_id, &document.username)
+            .await
+        {
+            Ok(owner) => owner,
+            Err(e) => {
+                error!("导入聊天记录失败，检查数据库错误: {}", e);
+                return ManagerResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+
+        for conversation in document.conversations {
+            // 私聊为 (导出者, 对方) 在本实例中的ID，群聊为本实例中的群号
+            let target = match conversation.conversation {
+                Conversation::Private(peer_id) => {
+                    match resolver.user(self, peer_id, &conversation.title).await {
+                        Ok(peer) => owner.zip(peer).map(|(owner, peer)| {
+                            (Conversation::Private(peer), owner, peer)
+                        }),
+                        Err(e) => {
+                            error!("导入聊天记录失败，检查数据库错误: {}", e);
+                            return ManagerResponse::err(format!("数据库错误：{}", e));
+                        }
+                    }
+                }
+                Conversation::Group(group_id) => {
+                    match resolver.group(self, group_id, &conversation.title).await {
+                        Ok(group) => group.map(|g| (Conversation::Group(g), 0, 0)),
+                        Err(e) => {
+                            error!("导入聊天记录失败，检查数据库错误: {}", e);
+                            return ManagerResponse::err(format!("数据库错误：{}", e));
+                        }
+                    }
+                }
+            };
+            let Some((target, owner_id, peer_id)) = target else {
+                warn!(
+                    "导入时找不到会话 {:?}（{}）对应的用户或群组，跳过 {} 条消息",
+                    conversation.conversation,
+                    conversation.title,
+                    conversation.messages.len()
+                );
+                summary.unmapped += conversation.messages.len() as u32;
+                continue;
+            };
+
+            let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+            for message in conversation.messages {
+                let sender = match conversation.conversation {
+                    // 私聊消息的发送者只可能是导出者或对方
+                    Conversation::Private(peer) => {
+                        if message.sender_id == document.user_id {
+                            Some((owner_id, Conversation::Private(peer_id)))
+                        } else if message.sender_id == peer {
+                            Some((peer_id, Conversation::Private(owner_id)))
+                        } else {
+                            None
+                        }
+                    }
+                    Conversation::Group(_) => {
+                        match resolver
+                            .user(self, message.sender_id, &message.sender_name)
+                            .await
+                        {
+                            Ok(sender) => sender.map(|s| (s, target)),
+                            Err(e) => {
+                                error!("导入聊天记录失败，检查数据库错误: {}", e);
+                                return ManagerResponse::err(format!("数据库错误：{}", e));
+                            }
+                        }
+                    }
+                };
+                let Some((sender_id, message_conversation)) = sender else {
+                    summary.unmapped += 1;
+                    continue;
+                };
+                batch.push(ImportMessage {
+                    source_message_id: message.message_id,
+                    conversation: message_conversation,
+                    sender_id,
+                    message_type: message.message_type,
+                    message: message.message,
+                    attachment: message.attachment,
+                    timestamp: message.timestamp,
+                    edited_at: message.edited_at,
+                    reply_to: message.reply_to,
+                });
+                if batch.len() == IMPORT_BATCH_SIZE {
+                    if let Err(e) = self.import_batch(source, &batch, &mut summary).await {
+                        return e;
+                    }
+                    batch.clear();
+                }
+            }
+            if let Err(e) = self.import_batch(source, &batch, &mut summary).await {
+                return e;
+            }
+        }
+
+        info!(
+            "导入聊天记录完成: 来源 {}, 导入 {} 条, 重复 {} 条, 无法对应 {} 条",
+            source, summary.imported, summary.duplicated, summary.unmapped
+        );
+        ManagerResponse::ok("导入成功", summary)
+    }
+
+    /// 在一个事务中写入一批消息并累计结果，失败时返回带已导入条数的错误响应
+    async fn import_batch(
+        &self,
+        source: &str,
+        batch: &[ImportMessage],
+        summary: &mut ImportSummary,
+    ) -> Result<(), ManagerResponse<ImportSummary>> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        match self.db.import_messages(source, batch).await {
+            Ok(imported) => {
+                summary.imported += imported;
+                summary.duplicated += batch.len() as u32 - imported;
+                Ok(())
+            }
+            Err(e) => {
+                error!("导入聊天记录失败，检查数据库错误: {}", e);
+                Err(ManagerResponse::err(format!(
+                    "导入中断（已导入 {} 条）：{}",
+                    summary.imported, e
+                )))
+            }
+        }
+    }
+}
+
+impl IdResolver<'_> {
+    /// 源用户对应的本实例用户：优先使用 user_map，否则按用户名唯一匹配
+    async fn user(
+        &mut self,
+        manager: &Manager,
+        source_id: u32,
+        username: &str,
+    ) -> Result<Option<u32>, DBError> {
+        if let Some(id) = self.user_map.get(&source_id) {
+            return Ok(Some(*id));
+        }
+        if let Some(id) = self.users.get(&source_id) {
+            return Ok(*id);
+        }
+        let id = unique_match(manager.db.get_user_ids_by_username(username).await?, username);
+        self.users.insert(source_id, id);
+        Ok(id)
+    }
+
+    /// 源群组对应的本实例群组：优先使用 group_map，否则按群名唯一匹配
+    async fn group(
+        &self,
+        manager: &Manager,
+        source_id: u32,
+        name: &str,
+    ) -> Result<Option<u32>, DBError> {
+        if let Some(id) = self.group_map.get(&source_id) {
+            return Ok(Some(*id));
+        }
+        Ok(unique_match(manager.db.get_group_ids_by_name(name).await?, name))
+    }
+}
+
+/// 名称恰好对应一个ID时返回该ID，重名时无法确定
+fn unique_match(ids: Vec<u32>, name: &str) -> Option<u32> {
+    match ids.as_slice() {
+        [id] => Some(*id),
+        [] => None,
+        _ => {
+            warn!("导入时名称 {} 对应多个ID，请通过映射表指定", name);
+            None
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use log::{error, info};
 
-use uchat_model::{FullPrivateMessage, ManagerResponse, PreviewPrivateMessage};
+use uchat_model::{
+    FullGroupMessage, FullPrivateMessage, ManagerResponse, PreviewGroupMessage,
+    PreviewPrivateMessage,
+};
 
 use super::Manager;
 
@@ -71,4 +74,93 @@ impl Manager {
             }
         }
     }
+    /// 获取服务器最近的群messages
+    pub async fn get_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+    ) -> ManagerResponse<Vec<PreviewGroupMessage>> {
+        info!(
+            "响应manager获取最近群message: count: {}, offset: {}",
+            count, offset
+        );
+        let result = self.db.get_recent_group_messages(count, offset).await;
+        match result {
+            Ok(data) => ManagerResponse::ok("获取成功", data),
+            Err(e) => {
+                error!("获取近期群聊天记录失败，检查数据库错误: {}", e);
+                ManagerResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+    /// 获取某群最近的messages
+    pub async fn get_recent_messages_of_group(
+        &self,
+        count: u32,
+        offset: u32,
+        group_id: u32,
+    ) -> ManagerResponse<Vec<PreviewGroupMessage>> {
+        info!(
+            "响应manager获取群{}最近message: count: {}, offset: {}",
+            group_id, count, offset
+        );
+        let result = self
+            .db
+            .get_recent_messages_of_group(count, offset, group_id)
+            .await;
+        match result {
+            Ok(data) => ManagerResponse::ok("获取成功", data),
+            Err(e) => {
+                error!("获取近期群聊天记录失败，检查数据库错误: {}", e);
+                ManagerResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+    /// 获取某用户最近发送的群messages
+    pub async fn get_user_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+        user_id: u32,
+    ) -> ManagerResponse<Vec<PreviewGroupMessage>> {
+        info!(
+            "响应manager获取用户{}最近群message: count: {}, offset: {}",
+            user_id, count, offset
+        );
+        let result = self
+            .db
+            .get_user_recent_group_messages(count, offset, user_id)
+            .await;
+        match result {
+            Ok(data) => ManagerResponse::ok("获取成功", data),
+            Err(e) => {
+                error!("获取近期群聊天记录失败，检查数据库错误: {}", e);
+                ManagerResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+    /// 根据message id删除群聊天记录
+    pub async fn delete_group_message(&self, message_id: u64) -> ManagerResponse<u64> {
+        info!("响应manager删除群message: {}", message_id);
+        let result = self.db.delete_group_message(message_id).await;
+        match result {
+            Ok(index) => ManagerResponse::ok("删除成功", index),
+            Err(e) => {
+                error!("删除失败，检查数据库错误: {}", e);
+                ManagerResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+    /// 根据message id获取群聊天记录
+    pub async fn get_group_message(&self, message_id: u64) -> ManagerResponse<FullGroupMessage> {
+        info!("响应manager获取群message: {}", message_id);
+        let result = self.db.get_full_group_message(message_id).await;
+        match result {
+            Ok(message) => ManagerResponse::ok("获取成功", message),
+            Err(e) => {
+                error!("获取失败，检查数据库错误: {}", e);
+                ManagerResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
 }
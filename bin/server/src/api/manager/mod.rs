@@ -1,3 +1,4 @@
+pub mod import;
 pub mod message;
 pub mod session;
 pub mod user;
@@ -0,0 +1,56 @@
+use std::cmp::Reverse;
+
+use log::error;
+use uchat_model::{
+    Conversation, ConversationSettings, ConversationSummary, request::RequestResponse,
+};
+
+use super::Request;
+
+impl Request {
+    /// 获取会话列表：置顶的会话在前，其余按最后一条消息的时间递减，没有消息的会话排在最后
+    pub async fn get_conversations(&self, user_id: u32) -> RequestResponse<Vec<ConversationSummary>> {
+        match self.db.get_conversations(user_id).await {
+            Ok(mut list) => {
+                list.sort_by_key(|c| (Reverse(c.pinned), Reverse(c.last_timestamp)));
+                RequestResponse::ok("获取成功", list)
+            }
+            Err(e) => {
+                error!("获取会话列表失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 修改会话的置顶、免打扰设置，群聊要求仍在群中
+    pub async fn update_conversation_settings(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        pinned: Option<bool>,
+        muted: Option<bool>,
+    ) -> RequestResponse<ConversationSettings> {
+        match conversation {
+            Conversation::Private(peer_id) if peer_id == user_id => {
+                return RequestResponse::bad_request("不能设置与自己的会话");
+            }
+            Conversation::Group(group_id) => match self.is_group_member(group_id, user_id).await {
+                Ok(true) => {}
+                Ok(false) => return RequestResponse::forbidden("不在该群中"),
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            },
+            Conversation::Private(_) => {}
+        }
+        match self
+            .db
+            .update_conversation_settings(user_id, conversation, pinned, muted)
+            .await
+        {
+            Ok(settings) => RequestResponse::ok("设置成功", settings),
+            Err(e) => {
+                error!("修改会话设置失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+}
@@ -0,0 +1,651 @@
+// api/request/export.rs
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use log::{debug, error, warn};
+use serde::Serialize;
+use uchat_model::{
+    Conversation, ExportDocument, ExportedFile, ExportedMessage, GroupSimpleInfo, SessionMessage,
+    Timestamp, UserSimpleInfo,
+    request::{ExportFormat, ExportQuery, RequestResponse},
+};
+
+use super::Request;
+use crate::db::{DB, MessageCursor};
+use crate::storage::ObjectStorage;
+
+/// 每批从数据库读取的消息条数
+const EXPORT_BATCH_SIZE: u32 = 500;
+/// 超过该大小（8 MiB）的导出文件保存到对象存储，只返回下载地址
+/// 同时也是分片上传时每个分片的大小，不小于对象存储要求的 5 MiB
+const EXPORT_INLINE_LIMIT: usize = 8 * 1024 * 1024;
+
+/// 导出结果：直接下载的文件，或已保存到对象存储的文件
+pub enum ExportOutput {
+    File {
+        filename: String,
+        content_type: &'static str,
+        data: Vec<u8>,
+    },
+    Stored(ExportedFile),
+}
+
+/// 毫秒时间戳转为 RFC 3339 字符串
+fn format_time(timestamp: Timestamp) -> String {
+    DateTime::from_timestamp_millis(timestamp)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+/// CSV 字段转义：含逗号、引号或换行时整体加引号，内部引号双写
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+h2{border-bottom:1px solid #ccc;padding-bottom:.3em}\
+table{border-collapse:collapse;width:100%;margin-bottom:2em}\
+td{border-bottom:1px solid #eee;padding:.4em;vertical-align:top}\
+td.time{white-space:nowrap;color:#888;width:13em}\
+td.sender{white-space:nowrap;font-weight:bold;width:10em}\
+td.content{white-space:pre-wrap;word-break:break-word}\
+.note{color:#888;font-size:.85em}";
+
+/// 按导出格式逐条写入消息，写入的内容由 ExportSink 按批取走，不在内存中保留完整的导出文件
+struct ExportWriter {
+    format: ExportFormat,
+    buf: Vec<u8>,
+    conversations: usize, // 已写入的会话数
+    messages: usize,      // 当前会话已写入的消息数
+}
+
+impl ExportWriter {
+    fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            buf: Vec::new(),
+            conversations: 0,
+            messages: 0,
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn push_json<T: Serialize + ?Sized>(&mut self, value: &T) -> serde_json::Result<()> {
+        serde_json::to_writer(&mut self.buf, value)
+    }
+
+    /// 写入文件头；JSON 的字段顺序与 ExportDocument 一致
+    fn begin(
+        &mut self,
+        user_id: u32,
+        username: &str,
+        exported_at: Timestamp,
+        friends: &[UserSimpleInfo],
+        groups: &[GroupSimpleInfo],
+    ) -> serde_json::Result<()> {
+        match self.format {
+            ExportFormat::Json => {
+                self.push_str(&format!(
+                    "{{\"version\":{},\"exported_at\":{},\"user_id\":{},\"username\":",
+                    ExportDocument::VERSION, exported_at, user_id
+                ));
+                self.push_json(username)?;
+                self.push_str(",\"friends\":");
+                self.push_json(friends)?;
+                self.push_str(",\"groups\":");
+                self.push_json(groups)?;
+                self.push_str(",\"conversations\":[");
+            }
+            ExportFormat::Csv => {
+                // 带 BOM，便于表格软件识别 UTF-8
+                self.push_str("\u{feff}conversation_type,conversation_id,conversation_title,message_id,sender_id,sender_name,time,message_type,message,attachment_url\r\n");
+            }
+            ExportFormat::Html => {
+                let title = html_escape(&format!("{} 的聊天记录", username));
+                self.push_str(&format!(
+                    "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"note\">用户ID：{user_id}，导出时间：{}</p>\n",
+                    format_time(exported_at)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_conversation(
+        &mut self,
+        conversation: Conversation,
+        title: &str,
+    ) -> serde_json::Result<()> {
+        self.messages = 0;
+        match self.format {
+            ExportFormat::Json => {
+                if self.conversations > 0 {
+                    self.push_str(",");
+                }
+                self.push_str("{\"conversation\":");
+                self.push_json(&conversation)?;
+                self.push_str(",\"title\":");
+                self.push_json(title)?;
+                self.push_str(",\"messages\":[");
+            }
+            ExportFormat::Csv => {}
+            ExportFormat::Html => {
+                let kind = if conversation.is_group() { "群聊" } else { "私聊" };
+                self.push_str(&format!(
+                    "<section>\n<h2>{}</h2>\n<p class=\"note\">{}，ID：{}</p>\n<table>\n",
+                    html_escape(title),
+                    kind,
+                    conversation.id()
+                ));
+            }
+        }
+        self.conversations += 1;
+        Ok(())
+    }
+
+    fn write_message(
+        &mut self,
+        conversation: Conversation,
+        title: &str,
+        message: &ExportedMessage,
+    ) -> serde_json::Result<()> {
+        match self.format {
+            ExportFormat::Json => {
+                if self.messages > 0 {
+                    self.push_str(",");
+                }
+                self.push_json(message)?;
+            }
+            ExportFormat::Csv => {
+                let row = [
+                    conversation.kind_str().to_string(),
+                    conversation.id().to_string(),
+                    csv_field(title),
+                    message.message_id.to_string(),
+                    message.sender_id.to_string(),
+                    csv_field(&message.sender_name),
+                    format_time(message.timestamp),
+                    message.message_type.to_string(),
+                    csv_field(&message.message),
+                    csv_field(message.attachment_url.as_deref().unwrap_or_default()),
+                ];
+                self.push_str(&row.join(","));
+                self.push_str("\r\n");
+            }
+            ExportFormat::Html => {
+                let mut content = html_escape(&message.message);
+                if let Some(url) = &message.attachment_url {
+                    let mime = message
+                        .attachment
+                        .as_ref()
+                        .map(|a| a.mime_type.as_str())
+                        .unwrap_or_default();
+                    content.push_str(&format!(
+                        "\n<a href=\"{}\">[{}附件 {}]</a>",
+                        html_escape(url),
+                        message.message_type,
+                        html_escape(mime)
+                    ));
+                }
+                if message.forwarded_from.is_some() {
+                    content.push_str("\n<span class=\"note\">（转发）</span>");
+                }
+                if message.edited_at.is_some() {
+                    content.push_str("\n<span class=\"note\">（已编辑）</span>");
+                }
+                self.push_str(&format!(
+                    "<tr><td class=\"time\">{}</td><td class=\"sender\">{}</td><td class=\"content\">{}</td></tr>\n",
+                    format_time(message.timestamp),
+                    html_escape(&message.sender_name),
+                    content
+                ));
+            }
+        }
+        self.messages += 1;
+        Ok(())
+    }
+
+    fn end_conversation(&mut self) {
+        match self.format {
+            ExportFormat::Json => self.push_str("]}"),
+            ExportFormat::Csv => {}
+            ExportFormat::Html => {
+                if self.messages == 0 {
+                    self.push_str("<tr><td class=\"note\">没有消息</td></tr>\n");
+                }
+                self.push_str("</table>\n</section>\n");
+            }
+        }
+    }
+
+    /// 取走已写入的内容
+    fn take(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        match self.format {
+            ExportFormat::Json => self.push_str("]}"),
+            ExportFormat::Csv => {}
+            ExportFormat::Html => self.push_str("</body>\n</html>\n"),
+        }
+        self.buf
+    }
+}
+
+/// 导出文件的去向：不超过 EXPORT_INLINE_LIMIT 时留在内存中直接下载
+/// 超过后改为分片上传到对象存储，每攒满一个分片上传一次
+struct ExportSink {
+    storage: Arc<dyn ObjectStorage + Send + Sync>,
+    object_path: String,
+    content_type: &'static str,
+    pending: Vec<u8>,          // 尚未上传的内容
+    upload_id: Option<String>, // 开始分片上传后的 upload_id
+    parts: Vec<(i32, String)>, // 已上传的 (分片序号, ETag)
+    size: u64,                 // 已写入的总字节数
+}
+
+impl ExportSink {
+    fn new(
+        storage: Arc<dyn ObjectStorage + Send + Sync>,
+        object_path: String,
+        content_type: &'static str,
+    ) -> Self {
+        Self {
+            storage,
+            object_path,
+            content_type,
+            pending: Vec::new(),
+            upload_id: None,
+            parts: Vec::new(),
+            size: 0,
+        }
+    }
+
+    /// 追加内容，攒满一个分片时上传
+    async fn write(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        self.size += data.len() as u64;
+        self.pending.extend_from_slice(&data);
+        if self.pending.len() > EXPORT_INLINE_LIMIT {
+            if self.upload_id.is_none() {
+                let upload_id = self
+                    .storage
+                    .create_multipart_upload(&self.object_path, self.content_type)
+                    .await?;
+                self.upload_id = Some(upload_id);
+            }
+            self.upload_pending().await?;
+        }
+        Ok(())
+    }
+
+    async fn upload_pending(&mut self) -> anyhow::Result<()> {
+        let Some(upload_id) = &self.upload_id else {
+            return Ok(());
+        };
+        let part_number = self.parts.len() as i32 + 1;
+        let data = std::mem::take(&mut self.pending);
+        let e_tag = self
+            .storage
+            .upload_part(&self.object_path, upload_id, part_number, data)
+            .await?;
+        self.parts.push((part_number, e_tag));
+        Ok(())
+    }
+
+    /// 结束写入：未开始分片上传时返回内存中的文件，否则上传最后一个分片并合并
+    async fn finish(&mut self, filename: String) -> anyhow::Result<ExportOutput> {
+        let Some(upload_id) = self.upload_id.clone() else {
+            return Ok(ExportOutput::File {
+                filename,
+                content_type: self.content_type,
+                data: std::mem::take(&mut self.pending),
+            });
+        };
+        if !self.pending.is_empty() {
+            self.upload_pending().await?;
+        }
+        let url = self
+            .storage
+            .complete_multipart_upload(&self.object_path, &upload_id, &self.parts)
+            .await?;
+        Ok(ExportOutput::Stored(ExportedFile {
+            url,
+            size: self.size,
+        }))
+    }
+
+    /// 导出失败时放弃已开始的分片上传
+    async fn abort(&self) {
+        if let Some(upload_id) = &self.upload_id
+            && let Err(e) = self
+                .storage
+                .abort_multipart_upload(&self.object_path, upload_id)
+                .await
+        {
+            warn!("放弃导出文件的分片上传失败: {}", e);
+        }
+    }
+}
+
+/// 已通过校验的导出任务，只持有数据库与对象存储的句柄，执行时不占用 Request 的锁
+pub struct ExportJob {
+    db: Arc<dyn DB>,
+    storage: Arc<dyn ObjectStorage + Send + Sync>,
+    user_id: u32,
+    username: String,
+    query: ExportQuery,
+    friends: Vec<UserSimpleInfo>,
+    groups: Vec<GroupSimpleInfo>,
+    conversations: Vec<(Conversation, String)>,
+    names: HashMap<u32, String>, // 发送者用户名缓存
+}
+
+impl Request {
+    /// 校验导出参数并确定要导出的会话：指定 peer_id 或 group_id 时只导出该会话，
+    /// 都缺省时导出用户的全部会话及好友、群组列表
+    pub async fn prepare_export(
+        &self,
+        user_id: u32,
+        query: ExportQuery,
+    ) -> Result<ExportJob, RequestResponse<()>> {
+        if query.start.zip(query.end).is_some_and(|(start, end)| start >= end) {
+            return Err(RequestResponse::bad_request("起始时间必须早于截止时间"));
+        }
+        let user = match self.db.get_userinfo(user_id).await {
+            Ok(Some(user)) => user,
+            Ok(None) => return Err(RequestResponse::not_found()),
+            Err(e) => {
+                error!("获取用户信息失败，检查数据库错误: {}", e);
+                return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+            }
+        };
+
+        let mut names: HashMap<u32, String> = HashMap::new();
+        names.insert(user_id, user.username.clone());
+        let mut friends = Vec::new();
+        let mut groups = Vec::new();
+        let conversations = match (query.peer_id, query.group_id) {
+            (Some(_), Some(_)) => {
+                return Err(RequestResponse::bad_request(
+                    "peer_id 与 group_id 不能同时指定",
+                ));
+            }
+            (Some(peer_id), None) => {
+                if peer_id == user_id {
+                    return Err(RequestResponse::bad_request("不能导出与自己的会话"));
+                }
+                match self.db.get_userinfo(peer_id).await {
+                    Ok(Some(peer)) => {
+                        names.insert(peer_id, peer.username.clone());
+                        vec![(Conversation::Private(peer_id), peer.username)]
+                    }
+                    Ok(None) => return Err(RequestResponse::not_found()),
+                    Err(e) => {
+                        error!("获取用户信息失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                }
+            }
+            (None, Some(group_id)) => {
+                match self.is_group_member(group_id, user_id).await {
+                    Ok(true) => {}
+                    Ok(false) => return Err(RequestResponse::forbidden("不是该群成员")),
+                    Err(e) => return Err(RequestResponse::err(format!("服务器错误：{}", e))),
+                }
+                match self.db.get_groupinfo(group_id).await {
+                    Ok(Some(group)) => vec![(Conversation::Group(group_id), group.title)],
+                    Ok(None) => return Err(RequestResponse::not_found()),
+                    Err(e) => {
+                        error!("获取群组信息失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                }
+            }
+            (None, None) => {
+                friends = match self.db.get_friends(user_id).await {
+                    Ok(friends) => friends,
+                    Err(e) => {
+                        error!("获取好友列表失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                };
+                groups = match self.db.get_groups(user_id).await {
+                    Ok(groups) => groups,
+                    Err(e) => {
+                        error!("获取群组列表失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                };
+                for friend in &friends {
+                    names.insert(friend.user_id, friend.username.clone());
+                }
+                match self.db.get_conversations(user_id).await {
+                    Ok(list) => list
+                        .into_iter()
+                        .map(|summary| (summary.conversation, summary.title))
+                        .collect(),
+                    Err(e) => {
+                        error!("获取会话列表失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                }
+            }
+        };
+
+        Ok(ExportJob {
+            db: self.db.clone(),
+            storage: self.storage.clone(),
+            user_id,
+            username: user.username,
+            query,
+            friends,
+            groups,
+            conversations,
+            names,
+        })
+    }
+}
+
+impl ExportJob {
+    /// 执行导出：消息按批从数据库读取并逐条写入，只包含请求者可见且未撤回的消息
+    /// 导出文件超过 EXPORT_INLINE_LIMIT 时分片上传到对象存储并返回下载地址
+    pub async fn run(mut self) -> Result<ExportOutput, RequestResponse<()>> {
+        let exported_at = Utc::now().timestamp_millis();
+        let format = self.query.format;
+        let object_path = format!(
+            "exports/{}/{}.{}",
+            self.user_id,
+            exported_at,
+            format.extension()
+        );
+        let filename = format!(
+            "uchat_export_{}_{}.{}",
+            self.user_id,
+            exported_at,
+            format.extension()
+        );
+        let mut sink = ExportSink::new(self.storage.clone(), object_path, format.content_type());
+        let result = match self.write_all(exported_at, &mut sink).await {
+            Ok(()) => sink.finish(filename).await.map_err(|e| {
+                error!("导出文件上传失败: {}", e);
+                RequestResponse::err("导出文件上传失败")
+            }),
+            Err(resp) => Err(resp),
+        };
+        match &result {
+            Ok(_) => debug!(
+                "用户 {} 导出聊天记录，格式 {}，大小 {} 字节",
+                self.user_id,
+                format.extension(),
+                sink.size
+            ),
+            Err(_) => sink.abort().await,
+        }
+        result
+    }
+
+    /// 将全部会话写入 sink，每读取一批消息交给 sink 一次
+    async fn write_all(
+        &mut self,
+        exported_at: Timestamp,
+        sink: &mut ExportSink,
+    ) -> Result<(), RequestResponse<()>> {
+        let mut writer = ExportWriter::new(self.query.format);
+        let json_err = |e: serde_json::Error| {
+            error!("导出数据序列化失败: {}", e);
+            RequestResponse::err(format!("导出失败：{}", e))
+        };
+        let upload_err = |e: anyhow::Error| {
+            error!("导出文件上传失败: {}", e);
+            RequestResponse::err("导出文件上传失败")
+        };
+        writer
+            .begin(
+                self.user_id,
+                &self.username,
+                exported_at,
+                &self.friends,
+                &self.groups,
+            )
+            .map_err(json_err)?;
+
+        let (user_id, start, end) = (self.user_id, self.query.start, self.query.end);
+        for (conversation, title) in std::mem::take(&mut self.conversations) {
+            if let Conversation::Group(group_id) = conversation {
+                // 预先取得群成员名字，已退群成员的名字在遇到时单独查询
+                match self.db.get_group_members(group_id).await {
+                    Ok(members) => {
+                        for member in members {
+                            self.names.insert(member.user_id, member.username);
+                        }
+                    }
+                    Err(e) => {
+                        error!("获取群成员失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                }
+            }
+            writer
+                .begin_conversation(conversation, &title)
+                .map_err(json_err)?;
+
+            let mut last_id = 0;
+            loop {
+                let batch = match conversation {
+                    Conversation::Private(peer_id) => {
+                        self.db
+                            .get_messages(
+                                user_id,
+                                peer_id,
+                                MessageCursor::After(last_id),
+                                EXPORT_BATCH_SIZE,
+                            )
+                            .await
+                    }
+                    Conversation::Group(group_id) => {
+                        self.db
+                            .get_group_messages(
+                                user_id,
+                                group_id,
+                                MessageCursor::After(last_id),
+                                EXPORT_BATCH_SIZE,
+                            )
+                            .await
+                    }
+                };
+                let batch = match batch {
+                    Ok(batch) => batch,
+                    Err(e) => {
+                        error!("导出聊天记录失败，检查数据库错误: {}", e);
+                        return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+                    }
+                };
+                let full = batch.len() == EXPORT_BATCH_SIZE as usize;
+                for message in batch {
+                    last_id = message.message_id;
+                    // 导入的历史消息保留原时间戳，消息ID与时间不一定同序，只能逐条按时间过滤
+                    if message.recalled_at.is_some()
+                        || start.is_some_and(|start| message.timestamp < start)
+                        || end.is_some_and(|end| message.timestamp >= end)
+                    {
+                        continue;
+                    }
+                    let sender_name = self.sender_name(message.sender_id).await;
+                    let exported = self.exported_message(message, sender_name);
+                    writer
+                        .write_message(conversation, &title, &exported)
+                        .map_err(json_err)?;
+                }
+                sink.write(writer.take()).await.map_err(upload_err)?;
+                if !full {
+                    break;
+                }
+            }
+            writer.end_conversation();
+        }
+
+        sink.write(writer.finish()).await.map_err(upload_err)
+    }
+
+    /// 查询发送者用户名并缓存，用户已注销时返回空字符串
+    async fn sender_name(&mut self, sender_id: u32) -> String {
+        if let Some(name) = self.names.get(&sender_id) {
+            return name.clone();
+        }
+        let name = match self.db.get_userinfo(sender_id).await {
+            Ok(Some(user)) => user.username,
+            Ok(None) => String::new(),
+            Err(e) => {
+                error!("获取用户信息失败，检查数据库错误: {}", e);
+                String::new()
+            }
+        };
+        self.names.insert(sender_id, name.clone());
+        name
+    }
+
+    fn exported_message(&self, message: SessionMessage, sender_name: String) -> ExportedMessage {
+        let attachment_url = message
+            .attachment
+            .as_ref()
+            .map(|a| self.storage.get_url(&a.object_key));
+        ExportedMessage {
+            message_id: message.message_id,
+            sender_id: message.sender_id,
+            sender_name,
+            timestamp: message.timestamp,
+            message_type: message.message_type,
+            message: message.message,
+            attachment: message.attachment,
+            attachment_url,
+            reply_to: message.reply_to,
+            forwarded_from: message.forwarded_from,
+            edited_at: message.edited_at,
+        }
+    }
+}
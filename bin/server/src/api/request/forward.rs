@@ -0,0 +1,153 @@
+use log::{debug, error};
+use uchat_model::{
+    Conversation, ForwardedFrom, MessageId, message::ServerMessage, request::RequestResponse,
+};
+
+use super::{OutgoingMessage, Request};
+use crate::{db::ForwardMessage, session::Outbound};
+
+/// 单次最多转发的消息条数
+const MAX_FORWARD_MESSAGES: usize = 50;
+
+impl Request {
+    /// 将 source 会话中的消息按原顺序转发到 target 会话，返回新消息的ID
+    /// 所有消息在一个事务中写入，全部成功后才推送给会话参与者，失败时不会留下部分转发
+    /// 只能转发请求者在 source 中可见且未撤回的消息，消息类型与附件保持不变，并记录转发来源
+    pub async fn forward_messages(
+        &self,
+        user_id: u32,
+        source: Conversation,
+        mut message_ids: Vec<MessageId>,
+        target: Conversation,
+    ) -> RequestResponse<Vec<MessageId>> {
+        message_ids.sort_unstable();
+        message_ids.dedup();
+        if message_ids.is_empty() {
+            return RequestResponse::bad_request("没有要转发的消息");
+        }
+        if message_ids.len() > MAX_FORWARD_MESSAGES {
+            return RequestResponse::bad_request(format!(
+                "单次最多转发 {} 条消息",
+                MAX_FORWARD_MESSAGES
+            ));
+        }
+        if target == Conversation::Private(user_id) {
+            return RequestResponse::bad_request("不能转发给自己");
+        }
+        for conversation in [source, target] {
+            if let Conversation::Group(group_id) = conversation {
+                match self.is_group_member(group_id, user_id).await {
+                    Ok(true) => {}
+                    Ok(false) => return RequestResponse::forbidden("不在该群中"),
+                    Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+                }
+            }
+        }
+        if let Conversation::Private(peer_id) = target
+            && let Err(resp) = self.check_private_peer(peer_id).await
+        {
+            return resp;
+        }
+
+        let messages = match self
+            .db
+            .get_messages_by_ids(user_id, source, &message_ids)
+            .await
+        {
+            Ok(messages) => messages,
+            Err(e) => {
+                error!("获取待转发消息失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+        // 任一消息不属于该会话、已撤回或对请求者不可见时整体拒绝
+        if messages.len() != message_ids.len() {
+            return RequestResponse::not_found();
+        }
+
+        let forwards: Vec<ForwardMessage> = messages
+            .into_iter()
+            .map(|message| ForwardMessage {
+                // 转发已转发过的消息时沿用最初的来源
+                forwarded_from: message.forwarded_from.unwrap_or(ForwardedFrom {
+                    is_group: source.is_group(),
+                    message_id: message.message_id,
+                    sender_id: message.sender_id,
+                    timestamp: message.timestamp,
+                }),
+                message_type: message.message_type,
+                message: message.message,
+                attachment: message.attachment,
+            })
+            .collect();
+        // 写入前先整体检查，避免转发不能发送的消息
+        if let Some(reason) = forwards.iter().find_map(|forward| {
+            OutgoingMessage::check_content(
+                forward.message_type,
+                &forward.message,
+                forward.attachment.as_ref(),
+            )
+            .err()
+        }) {
+            return RequestResponse::bad_request(reason);
+        }
+
+        let stored = match self
+            .db
+            .add_forwarded_messages(user_id, target, &forwards)
+            .await
+        {
+            Ok(stored) => stored,
+            Err(e) => {
+                error!("转发消息失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+
+        let mut forwarded = Vec::with_capacity(stored.len());
+        for (forward, (timestamp, message_id)) in forwards.into_iter().zip(stored) {
+            match target {
+                Conversation::Private(receiver_id) => {
+                    let msg = Outbound::from(ServerMessage::SendMessage {
+                        message_id,
+                        sender: user_id,
+                        receiver: receiver_id,
+                        message: forward.message,
+                        timestamp,
+                        message_type: forward.message_type,
+                        attachment: forward.attachment,
+                        reply_to: None,
+                        forwarded_from: Some(forward.forwarded_from),
+                    });
+                    self.send_to_user(receiver_id, msg.clone()).await;
+                    self.send_to_user(user_id, msg).await;
+                }
+                Conversation::Group(group_id) => {
+                    let msg = Outbound::from(ServerMessage::SendGroupMessage {
+                        message_id,
+                        sender: user_id,
+                        group_id,
+                        message: forward.message,
+                        timestamp,
+                        message_type: forward.message_type,
+                        attachment: forward.attachment,
+                        reply_to: None,
+                        mentions: Vec::new(),
+                        mention_all: false,
+                        forwarded_from: Some(forward.forwarded_from),
+                    });
+                    self.send_to_group(group_id, msg).await;
+                }
+            }
+            forwarded.push(message_id);
+        }
+        debug!(
+            "用户 {} 从 {:?} 转发 {} 条消息到 {:?}",
+            user_id,
+            source,
+            forwarded.len(),
+            target
+        );
+        RequestResponse::ok("转发成功", forwarded)
+    }
+}
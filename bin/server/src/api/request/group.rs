@@ -1,4 +1,4 @@
-use uchat_model::{request::RequestResponse, GroupDetailedInfo, GroupSimpleInfo, UserSimpleInfo};
+use uchat_model::{request::RequestResponse, Conversation, GroupDetailedInfo, GroupSimpleInfo, UserSimpleInfo};
 use log::{error, warn};
 
 use super::Request;
@@ -63,10 +63,23 @@ impl Request {
             }
         }
     }
-    /// 返回群组的详细信息
-    pub async fn get_groupinfo(&self, id: u32) -> RequestResponse<GroupDetailedInfo> {
+    /// 返回群组的详细信息，请求者是群成员时附带群置顶消息
+    pub async fn get_groupinfo(&self, user_id: u32, id: u32) -> RequestResponse<GroupDetailedInfo> {
         match self.db.get_groupinfo(id).await {
-            Ok(Some(info)) => RequestResponse::ok("获取成功", info),
+            Ok(Some(mut info)) => {
+                match self.is_group_member(id, user_id).await {
+                    Ok(true) => match self.db.get_pinned_messages(user_id, Conversation::Group(id)).await {
+                        Ok(pinned) => info.pinned = pinned,
+                        Err(e) => {
+                            error!("获取群置顶消息失败，检查数据库错误: {}", e);
+                            return RequestResponse::err(format!("数据库错误：{}", e));
+                        }
+                    },
+                    Ok(false) => {}
+                    Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+                }
+                RequestResponse::ok("获取成功", info)
+            }
             Ok(None) => {
                 warn!("数据库中无群组: {}的信息", id);
                 RequestResponse::not_found()
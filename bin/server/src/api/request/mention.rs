@@ -0,0 +1,81 @@
+use log::error;
+use uchat_model::{
+    MessageId, Timestamp, UnreadMention, message::ServerMessage, request::RequestResponse,
+};
+
+use super::Request;
+use crate::session::Outbound;
+
+impl Request {
+    /// 校验群消息中的 @：被 @ 的用户必须是群成员，@所有人仅群管理员可用
+    /// mentions 会被去重，返回需要推送 @ 通知的成员（@所有人时为除发送者外的全体成员）
+    pub(super) async fn resolve_mentions(
+        &self,
+        sender_id: u32,
+        group_id: u32,
+        mentions: &mut Vec<u32>,
+        mention_all: bool,
+    ) -> Result<Vec<u32>, String> {
+        mentions.sort_unstable();
+        mentions.dedup();
+        if mentions.is_empty() && !mention_all {
+            return Ok(Vec::new());
+        }
+        if mention_all {
+            match self.is_group_admin(group_id, sender_id).await {
+                Ok(true) => {}
+                Ok(false) => return Err("只有群管理员可以@所有人".to_string()),
+                Err(e) => return Err(format!("服务器错误：{}", e)),
+            }
+        }
+        let member_ids = self
+            .get_group_member_ids(group_id)
+            .await
+            .map_err(|e| format!("服务器错误：{}", e))?;
+        if let Some(user_id) = mentions.iter().find(|id| !member_ids.contains(id)) {
+            return Err(format!("用户 {} 不在该群中，无法@", user_id));
+        }
+        let targets = if mention_all { &member_ids } else { &*mentions };
+        Ok(targets
+            .iter()
+            .copied()
+            .filter(|&id| id != sender_id)
+            .collect())
+    }
+
+    /// 向被 @ 的成员单独推送通知（@ 记录已随消息入库）
+    pub(super) async fn notify_mentions(
+        &self,
+        group_id: u32,
+        message_id: MessageId,
+        sender_id: u32,
+        timestamp: Timestamp,
+        mention_all: bool,
+        mentioned: &[u32],
+    ) {
+        if mentioned.is_empty() {
+            return;
+        }
+        let msg = Outbound::from(ServerMessage::Mentioned {
+            message_id,
+            group_id,
+            sender: sender_id,
+            timestamp,
+            mention_all,
+        });
+        for &user_id in mentioned {
+            self.send_to_user(user_id, msg.clone()).await;
+        }
+    }
+
+    /// 获取所在各群中 @ 了当前用户且尚未读到的消息
+    pub async fn get_unread_mentions(&self, user_id: u32) -> RequestResponse<Vec<UnreadMention>> {
+        match self.db.get_unread_mentions(user_id).await {
+            Ok(list) => RequestResponse::ok("获取成功", list),
+            Err(e) => {
+                error!("获取未读@失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+}
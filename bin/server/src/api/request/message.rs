@@ -1,27 +1,167 @@
 use std::sync::Arc;
 
-use axum::extract::ws::Message;
 use futures::{stream::FuturesUnordered, StreamExt};
 use log::{debug, error, warn};
-use uchat_model::{message::ServerMessage, MessageType};
+use uchat_model::{
+    message::{ServerMessage, MAX_MENTIONS},
+    Attachment, Conversation, ForwardedFrom, MessageId, MessageType, Timestamp,
+};
 
 use super::Request;
+use crate::session::Outbound;
 
+/// 一条待发送的消息（由 ClientMessage 解出）
+#[derive(Debug)]
+pub struct OutgoingMessage {
+    pub message_type: MessageType,
+    pub message: String,
+    pub attachment: Option<Attachment>,
+    pub client_msg_id: Option<String>,
+    pub reply_to: Option<MessageId>,
+    pub mentions: Vec<u32>, // 仅群消息使用
+    pub mention_all: bool,
+    pub forwarded_from: Option<ForwardedFrom>, // 仅转发时使用
+}
+
+impl OutgoingMessage {
+    /// 非文本消息必须携带附件，文本消息内容不能为空，@ 的成员数不能超过 MAX_MENTIONS
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mentions.len() > MAX_MENTIONS {
+            return Err(format!("一条消息最多@ {} 人", MAX_MENTIONS));
+        }
+        Self::check_content(self.message_type, &self.message, self.attachment.as_ref())
+    }
+
+    /// 按 validate 的规则检查消息内容
+    pub fn check_content(
+        message_type: MessageType,
+        message: &str,
+        attachment: Option<&Attachment>,
+    ) -> Result<(), String> {
+        if message_type != MessageType::Text && attachment.is_none() {
+            return Err(format!("{} 类型的消息缺少附件", message_type));
+        }
+        if message_type == MessageType::Text && message.trim().is_empty() {
+            return Err("消息内容不能为空".to_string());
+        }
+        Ok(())
+    }
+}
 
 impl Request {
-    /// 根据session_id发送消息
-    pub async fn send_to_session(&self, session_id: &str, msg: Message) {
+    /// 根据session_id发送消息，返回是否成功投递到该连接
+    pub async fn send_to_session(&self, session_id: &str, msg: Outbound) -> bool {
         self.sessions.send_to_session(session_id, msg).await
     }
 
-    /// 发送给用户的所有 WebSocket 连接
-    pub async fn send_to_user(&self, user_id: u32, msg: Message) {
+    /// 发送给用户的所有 WebSocket 连接，返回成功投递的连接数
+    pub async fn send_to_user(&self, user_id: u32, msg: Outbound) -> usize {
         self.sessions.send_to_user(user_id, msg).await
     }
 
+    /// 回执：消息已入库，只发给发送该消息的会话
+    async fn send_ack(
+        &self,
+        session_id: &str,
+        client_msg_id: String,
+        message_id: MessageId,
+        timestamp: Timestamp,
+    ) {
+        let ack = ServerMessage::Ack {
+            client_msg_id,
+            message_id,
+            timestamp,
+        };
+        self.send_to_session(session_id, ack.into()).await;
+    }
+
+    /// 回执：消息未能入库
+    async fn send_nack(&self, session_id: &str, client_msg_id: Option<String>, reason: String) {
+        let nack = ServerMessage::Nack {
+            client_msg_id,
+            reason,
+        };
+        self.send_to_session(session_id, nack.into()).await;
+    }
+
+    /// 投递失败：有发送会话时回执 Nack，并将原因返回给调用方
+    async fn reject(
+        &self,
+        session_id: Option<&str>,
+        client_msg_id: Option<String>,
+        reason: String,
+    ) -> Result<MessageId, String> {
+        if let Some(session_id) = session_id {
+            self.send_nack(session_id, client_msg_id, reason.clone())
+                .await;
+        }
+        Err(reason)
+    }
+
+    /// 引用回复的消息必须属于同一会话且未撤回
+    async fn check_reply_to(
+        &self,
+        sender_id: u32,
+        conversation: Conversation,
+        reply_to: Option<MessageId>,
+    ) -> Result<(), String> {
+        let Some(reply_to) = reply_to else {
+            return Ok(());
+        };
+        self.conversation_message_meta::<()>(sender_id, conversation, reply_to)
+            .await
+            .map(|_| ())
+            .map_err(|resp| format!("无法引用消息 {}：{}", reply_to, resp.message))
+    }
+
+    /// 检查去重窗口：同一发送者重试相同 client_msg_id 时直接回执首次入库的结果
+    /// 命中时返回首次入库的消息ID，调用方不应再次入库
+    async fn ack_if_duplicate(
+        &self,
+        session_id: Option<&str>,
+        sender_id: u32,
+        client_msg_id: Option<&String>,
+    ) -> Option<MessageId> {
+        let (Some(session_id), Some(client_msg_id)) = (session_id, client_msg_id) else {
+            return None;
+        };
+        let (message_id, timestamp) = self.cache.get_client_message(sender_id, client_msg_id).await?;
+        debug!(
+            "用户 {} 重发消息 client_msg_id: {}，命中去重窗口，message_id: {}",
+            sender_id, client_msg_id, message_id
+        );
+        self.send_ack(session_id, client_msg_id.clone(), message_id, timestamp)
+            .await;
+        Some(message_id)
+    }
+
+    /// 消息入库成功后记录去重窗口并回执发送会话
+    async fn ack_stored(
+        &self,
+        session_id: Option<&str>,
+        sender_id: u32,
+        client_msg_id: Option<String>,
+        message_id: MessageId,
+        timestamp: Timestamp,
+    ) {
+        if let (Some(session_id), Some(client_msg_id)) = (session_id, client_msg_id) {
+            self.cache
+                .set_client_message(sender_id, &client_msg_id, message_id, timestamp)
+                .await;
+            self.send_ack(session_id, client_msg_id, message_id, timestamp)
+                .await;
+        }
+    }
+
     /// 发送给用户所有的 WebSocket 连接（v2版）
-    /// 发送给用户所有的 WebSocket 连接（v2版，支持二进制消息以提升效率）
-    pub async fn send_to_user_v2(&self, sender_session_id: &str, receiver_id: u32, msg: &str) {
+    /// 消息以 ServerMessage 投递，各连接按协商的编码（JSON/二进制帧）自行序列化
+    /// 携带 client_msg_id 时向发送会话回执 Ack/Nack，并在去重窗口内忽略重复发送
+    pub async fn send_to_user_v2(
+        &self,
+        sender_session_id: &str,
+        receiver_id: u32,
+        outgoing: OutgoingMessage,
+    ) {
         let Some(sender_id) = self.check_session(sender_session_id).await else {
             warn!(
                 "未能获取会话 {} 对应的用户ID，放弃处理此条消息",
@@ -29,13 +169,63 @@ impl Request {
             );
             return;
         };
+        let _ = self
+            .deliver_private(sender_id, Some(sender_session_id), receiver_id, outgoing)
+            .await;
+    }
+
+    /// 以 sender_id 的身份发送私聊消息，返回入库后的消息ID或失败原因
+    /// sender_session_id 为发起发送的会话，定时消息等没有发送会话时为 None，此时不回执 Ack/Nack
+    pub async fn deliver_private(
+        &self,
+        sender_id: u32,
+        sender_session_id: Option<&str>,
+        receiver_id: u32,
+        outgoing: OutgoingMessage,
+    ) -> Result<MessageId, String> {
+        if let Err(reason) = outgoing.validate() {
+            return self
+                .reject(sender_session_id, outgoing.client_msg_id, reason)
+                .await;
+        }
+        let conversation = Conversation::Private(receiver_id);
+        if let Err(reason) = self
+            .check_reply_to(sender_id, conversation, outgoing.reply_to)
+            .await
+        {
+            return self
+                .reject(sender_session_id, outgoing.client_msg_id, reason)
+                .await;
+        }
+        let OutgoingMessage {
+            message_type,
+            message,
+            attachment,
+            client_msg_id,
+            reply_to,
+            forwarded_from,
+            ..
+        } = outgoing;
+        if let Some(message_id) = self
+            .ack_if_duplicate(sender_session_id, sender_id, client_msg_id.as_ref())
+            .await
+        {
+            return Ok(message_id);
+        }
         // 存储到数据库中
         match self
             .db
-            .add_message(sender_id, receiver_id, MessageType::Text, msg)
+            .add_message(
+                sender_id,
+                receiver_id,
+                message_type,
+                &message,
+                attachment.as_ref(),
+                reply_to,
+                forwarded_from.as_ref(),
+            )
             .await
         {
-            // 新增了消息类型枚举，先在这挖一个坑
             Ok((timestamp, message_id)) => {
                 debug!(
                     "用户 {} 发送私聊消息给用户 {} 成功，消息message_id: {}, timestamp: {}",
@@ -45,42 +235,34 @@ impl Request {
                     message_id,
                     sender: sender_id,
                     receiver: receiver_id,
-                    message: msg.to_string(),
+                    message,
                     timestamp,
+                    message_type,
+                    attachment,
+                    reply_to,
+                    forwarded_from,
                 };
-                // // 使用二进制序列化（如 serde_json），比 JSON 文本更高效
-                // let bin = match serde_json::to_vec(&server_message) {
-                //     Ok(data) => data,
-                //     Err(e) => {
-                //         error!("序列化消息为二进制失败: {:?}", e);
-                //         return;
-                //     }
-                // };
-                // self
-                //     .send_to_user(
-                //         receiver_id,
-                //         Message::Binary(bin.into()),
-                //     )
-                //     .await;
-                let json = match serde_json::to_string(&server_message) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        error!("序列化消息为JSON失败: {:?}", e);
-                        return;
-                    }
-                };
-                // 暂时序列化为text消息
-                let msg = Message::Text(axum::extract::ws::Utf8Bytes::from(json));
-                // 发送给接受用户所有的在线会话
-                self.send_to_user(receiver_id, msg.clone()).await;
+                let msg = Outbound::from(server_message);
+                // 发送给接受用户所有的在线会话，接收方确认收到（ClientMessage::Received）后再回执送达
+                if self.send_to_user(receiver_id, msg.clone()).await == 0 {
+                    debug!(
+                        "用户 {} 当前没有在线连接，消息 {} 暂未送达",
+                        receiver_id, message_id
+                    );
+                }
                 // 发送给发送用户所有的在线会话，也便于多会话登陆消息同步
                 self.send_to_user(sender_id, msg).await;
+                self.ack_stored(sender_session_id, sender_id, client_msg_id, message_id, timestamp)
+                    .await;
+                Ok(message_id)
             }
             Err(e) => {
                 error!(
                     "用户 {} 发送私聊消息给用户 {} 失败: {:?}",
                     sender_id, receiver_id, e
                 ); // 如果数据库操作失败，直接返回
+                self.reject(sender_session_id, client_msg_id, format!("数据库错误：{}", e))
+                    .await
             }
         }
     }
@@ -88,7 +270,12 @@ impl Request {
     /// 根据群号发送群消息
     /// 如果群组不存在或发送失败，返回 false
     /// 先读取群聊成员列表，然后发送消息给每个成员
-    pub async fn send_to_group(&self, group_id: u32, msg: Message) {
+    pub async fn send_to_group(&self, group_id: u32, msg: Outbound) {
+        self.send_to_group_except(group_id, msg, None).await
+    }
+
+    /// 发送给群内除 except 以外的所有成员
+    pub async fn send_to_group_except(&self, group_id: u32, msg: Outbound, except: Option<u32>) {
         // 1. 先查cache
         let member_ids = self.get_group_member_ids(group_id).await;
         let member_ids = match member_ids {
@@ -104,6 +291,9 @@ impl Request {
         let mut tasks = FuturesUnordered::new();
 
         for member_id in member_ids {
+            if Some(member_id) == except {
+                continue;
+            }
             let msg = Arc::clone(&msg); // 引用共享消息
             let sessions = Arc::clone(&sessions);
             tasks.push(tokio::spawn(async move {
@@ -122,7 +312,13 @@ impl Request {
     /// 根据群号发送群消息
     /// 如果群组不存在或发送失败，返回 false
     /// 先读取群聊成员列表，然后发送消息给每个成员
-    pub async fn send_to_group_v2(&self, sender_session_id: &str, group_id: u32, msg: &str) {
+    /// 携带 client_msg_id 时向发送会话回执 Ack/Nack，并在去重窗口内忽略重复发送
+    pub async fn send_to_group_v2(
+        &self,
+        sender_session_id: &str,
+        group_id: u32,
+        outgoing: OutgoingMessage,
+    ) {
         let Some(sender_id) = self.check_session(sender_session_id).await else {
             warn!(
                 "未能获取会话 {} 对应的用户ID，放弃处理此条消息",
@@ -130,8 +326,86 @@ impl Request {
             );
             return;
         };
+        let _ = self
+            .deliver_group(sender_id, Some(sender_session_id), group_id, outgoing)
+            .await;
+    }
+
+    /// 以 sender_id 的身份发送群消息，返回入库后的消息ID或失败原因
+    /// sender_session_id 的含义同 deliver_private
+    pub async fn deliver_group(
+        &self,
+        sender_id: u32,
+        sender_session_id: Option<&str>,
+        group_id: u32,
+        outgoing: OutgoingMessage,
+    ) -> Result<MessageId, String> {
+        if let Err(reason) = outgoing.validate() {
+            return self
+                .reject(sender_session_id, outgoing.client_msg_id, reason)
+                .await;
+        }
+        // 发送者必须是群成员，@ 的校验与消息入库都以此为前提
+        let is_member = match self.is_group_member(group_id, sender_id).await {
+            Ok(is_member) => is_member,
+            Err(e) => {
+                return self
+                    .reject(sender_session_id, outgoing.client_msg_id, format!("服务器错误：{}", e))
+                    .await;
+            }
+        };
+        if !is_member {
+            return self
+                .reject(sender_session_id, outgoing.client_msg_id, "不在该群中".to_string())
+                .await;
+        }
+        let conversation = Conversation::Group(group_id);
+        if let Err(reason) = self
+            .check_reply_to(sender_id, conversation, outgoing.reply_to)
+            .await
+        {
+            return self
+                .reject(sender_session_id, outgoing.client_msg_id, reason)
+                .await;
+        }
+        let OutgoingMessage {
+            message_type,
+            message,
+            attachment,
+            client_msg_id,
+            reply_to,
+            mut mentions,
+            mention_all,
+            forwarded_from,
+        } = outgoing;
+        let mentioned = match self
+            .resolve_mentions(sender_id, group_id, &mut mentions, mention_all)
+            .await
+        {
+            Ok(mentioned) => mentioned,
+            Err(reason) => return self.reject(sender_session_id, client_msg_id, reason).await,
+        };
+        if let Some(message_id) = self
+            .ack_if_duplicate(sender_session_id, sender_id, client_msg_id.as_ref())
+            .await
+        {
+            return Ok(message_id);
+        }
         // 存储到数据库中
-        match self.db.add_group_message(group_id, sender_id, msg).await {
+        match self
+            .db
+            .add_group_message(
+                group_id,
+                sender_id,
+                message_type,
+                &message,
+                attachment.as_ref(),
+                reply_to,
+                forwarded_from.as_ref(),
+                &mentioned,
+            )
+            .await
+        {
             Ok((timestamp, message_id)) => {
                 debug!(
                     "用户 {} 发送群消息给 {} 成功，消息message_id: {}, timestamp: {}",
@@ -141,37 +415,28 @@ impl Request {
                     message_id,
                     sender: sender_id,
                     group_id,
-                    message: msg.to_string(),
+                    message,
                     timestamp,
+                    message_type,
+                    attachment,
+                    reply_to,
+                    mentions,
+                    mention_all,
+                    forwarded_from,
                 };
-                // // 使用二进制序列化（如 serde_json），比 JSON 文本更高效
-                // let bin = match serde_json::to_vec(&server_message) {
-                //     Ok(data) => data,
-                //     Err(e) => {
-                //         error!("序列化消息为二进制失败: {:?}", e);
-                //         return;
-                //     }
-                // };
-                // self.send_to_group(group_id, Message::Binary(bin.into()))
-                //     .await;
-                let json = match serde_json::to_string(&server_message) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        error!("序列化消息为JSON失败: {:?}", e);
-                        return;
-                    }
-                };
-                // let json =
-                //     serde_json::to_string(&server_message).unwrap_or_else(|_| String::from("{}"));
-                self.send_to_group(
-                    group_id,
-                    Message::Text(axum::extract::ws::Utf8Bytes::from(json)),
-                )
-                .await;
+                self.send_to_group(group_id, Outbound::from(server_message))
+                    .await;
+                self.notify_mentions(group_id, message_id, sender_id, timestamp, mention_all, &mentioned)
+                    .await;
+                self.ack_stored(sender_session_id, sender_id, client_msg_id, message_id, timestamp)
+                    .await;
+                Ok(message_id)
             }
             Err(e) => {
                 error!("用户 {} 发送群消息给 {} 失败: {:?}", sender_id, group_id, e); // 如果数据库操作失败，直接返回
+                self.reject(sender_session_id, client_msg_id, format!("数据库错误：{}", e))
+                    .await
             }
         }
     }
-}
\ No newline at end of file
+}
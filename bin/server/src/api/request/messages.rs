@@ -3,33 +3,140 @@
 use std::collections::HashMap;
 
 use super::Request;
+use crate::db::MessageCursor;
 use log::error;
-use uchat_model::{IdMessagePair, SessionMessage, request::RequestResponse};
+use uchat_model::{
+    IdMessagePair, MessageId, MessagePage, MessageThread, SessionMessage,
+    request::{RequestResponse, ThreadQuery},
+};
+
+/// 聊天记录默认每页条数
+const DEFAULT_PAGE_SIZE: u32 = 30;
+/// 聊天记录每页最大条数
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// 解析分页参数：before_id 与 after_id 不能同时指定，limit 限制在 1 到 MAX_PAGE_SIZE 之间
+fn parse_cursor<T>(
+    before_id: Option<MessageId>,
+    after_id: Option<MessageId>,
+    limit: Option<u32>,
+) -> Result<(MessageCursor, u32), RequestResponse<T>> {
+    let cursor = match (before_id, after_id) {
+        (None, None) => MessageCursor::Latest,
+        (Some(id), None) => MessageCursor::Before(id),
+        (None, Some(id)) => MessageCursor::After(id),
+        (Some(_), Some(_)) => {
+            return Err(RequestResponse::bad_request(
+                "before_id 与 after_id 不能同时指定",
+            ));
+        }
+    };
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    Ok((cursor, limit))
+}
+
+/// 取满一页时才返回下一页游标：向后追新消息时为本页最新的消息，否则为本页最早的消息
+fn message_page(messages: Vec<SessionMessage>, cursor: MessageCursor, limit: u32) -> MessagePage {
+    let next_cursor = if messages.len() < limit as usize {
+        None
+    } else if let MessageCursor::After(_) = cursor {
+        messages.last().map(|m| m.message_id)
+    } else {
+        messages.first().map(|m| m.message_id)
+    };
+    MessagePage {
+        messages,
+        next_cursor,
+    }
+}
 
 impl Request {
     /// 获取群聊聊天记录
     pub async fn get_group_messages(
         &self,
+        user_id: u32,
         group_id: u32,
-        offset: u32,
-    ) -> RequestResponse<Vec<SessionMessage>> {
-        match self.db.get_group_messages(group_id, offset).await {
-            Ok(list) => RequestResponse::ok("获取成功", list),
+        before_id: Option<MessageId>,
+        after_id: Option<MessageId>,
+        limit: Option<u32>,
+    ) -> RequestResponse<MessagePage> {
+        let (cursor, limit) = match parse_cursor(before_id, after_id, limit) {
+            Ok(page) => page,
+            Err(resp) => return resp,
+        };
+        match self.db.get_group_messages(user_id, group_id, cursor, limit).await {
+            Ok(list) => RequestResponse::ok("获取成功", message_page(list, cursor, limit)),
             Err(e) => {
                 error!("获取群聊聊天记录失败，检查数据库错误: {}", e);
                 RequestResponse::err(format!("数据库错误：{}", e))
             }
         }
     }
+    /// 获取群聊话题：根消息及直接回复它的消息，只有群成员可以查看
+    pub async fn get_group_thread(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        root_id: MessageId,
+        query: ThreadQuery,
+    ) -> RequestResponse<MessageThread> {
+        let (cursor, limit) = match parse_cursor(query.before_id, query.after_id, query.limit) {
+            Ok(page) => page,
+            Err(resp) => return resp,
+        };
+        match self.is_group_member(group_id, user_id).await {
+            Ok(true) => {}
+            Ok(false) => return RequestResponse::forbidden("不在该群中"),
+            Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+        }
+        let root = match self.db.get_group_message(user_id, group_id, root_id).await {
+            Ok(Some(root)) => root,
+            Ok(None) => return RequestResponse::not_found(),
+            Err(e) => {
+                error!("获取话题根消息失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+        match self
+            .db
+            .get_group_thread_replies(user_id, group_id, root_id, cursor, limit)
+            .await
+        {
+            Ok(replies) => {
+                let MessagePage {
+                    messages: replies,
+                    next_cursor,
+                } = message_page(replies, cursor, limit);
+                RequestResponse::ok(
+                    "获取成功",
+                    MessageThread {
+                        root,
+                        replies,
+                        next_cursor,
+                    },
+                )
+            }
+            Err(e) => {
+                error!("获取话题回复失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
     /// 获取私聊聊天记录
     pub async fn get_messages(
         &self,
         sender: u32,
         receiver: u32,
-        offset: u32,
-    ) -> RequestResponse<Vec<SessionMessage>> {
-        match self.db.get_messages(sender, receiver, offset).await {
-            Ok(list) => RequestResponse::ok("获取成功", list),
+        before_id: Option<MessageId>,
+        after_id: Option<MessageId>,
+        limit: Option<u32>,
+    ) -> RequestResponse<MessagePage> {
+        let (cursor, limit) = match parse_cursor(before_id, after_id, limit) {
+            Ok(page) => page,
+            Err(resp) => return resp,
+        };
+        match self.db.get_messages(sender, receiver, cursor, limit).await {
+            Ok(list) => RequestResponse::ok("获取成功", message_page(list, cursor, limit)),
             Err(e) => {
                 error!("获取群聊聊天记录失败，检查数据库错误: {}", e);
                 RequestResponse::err(format!("数据库错误：{}", e))
@@ -39,9 +146,10 @@ impl Request {
     /// 获取某群聊最新一条消息时间戳
     pub async fn get_latest_timestamp_of_group(
         &self,
+        user_id: u32,
         group_id: u32,
     ) -> RequestResponse<Option<i64>> {
-        match self.db.get_latest_timestamp_of_group(group_id).await {
+        match self.db.get_latest_timestamp_of_group(user_id, group_id).await {
             Ok(timestamp) => RequestResponse::ok("获取成功", timestamp),
             Err(e) => {
                 error!("获取群聊最新消息时间戳失败，检查数据库错误: {}", e);
@@ -85,12 +193,13 @@ impl Request {
     /// 某个群某时间之后的消息
     pub async fn get_group_messages_after_timestamp(
         &self,
+        user_id: u32,
         group_id: u32,
         after: i64,
     ) -> RequestResponse<Vec<SessionMessage>> {
         match self
             .db
-            .get_group_messages_after_timestamp(group_id, after)
+            .get_group_messages_after_timestamp(user_id, group_id, after)
             .await
         {
             Ok(messages) => RequestResponse::ok("获取成功", messages),
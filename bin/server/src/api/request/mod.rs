@@ -5,17 +5,37 @@ mod message;
 mod utils;
 mod group;
 mod friend;
+mod typing;
+mod receipt;
+mod recall;
+mod reaction;
+mod mention;
+mod search;
+mod conversation;
+mod ttl;
+mod scheduled;
+mod forward;
+mod export;
+mod pin;
+
+pub use message::OutgoingMessage;
+pub use ttl::spawn_expire_task;
+pub use scheduled::spawn_schedule_task;
+pub use export::ExportOutput;
 
 use crate::cache::CacheConfig;
 use crate::cache::CacheManagerTrait;
+use crate::config::model::MessageConfig;
 use crate::db::DB;
 use crate::session::SessionConfig;
 use crate::session::SessionManagerTrait;
 use crate::storage::ObjectStorage;
+use dashmap::DashMap;
 use log::{error};
 use std::sync::Arc;
+use std::time::Instant;
 use uchat_model::{
-    ContactList, request::RequestResponse,
+    ContactList, message::TypingState, request::RequestResponse,
 };
 
 pub struct Request {
@@ -23,6 +43,8 @@ pub struct Request {
     sessions: Arc<dyn SessionManagerTrait<Config = SessionConfig>>,
     storage: Arc<dyn ObjectStorage + Send + Sync>,
     cache: Arc<dyn CacheManagerTrait<Config = CacheConfig>>, // 添加缓存管理器
+    typing: DashMap<(u32, u32, bool), (Instant, TypingState)>, // (sender, target, is_group) -> 上次转发的输入状态，用于限流
+    message_config: MessageConfig, // 撤回时限等消息策略
 }
 
 impl Request {
@@ -31,15 +53,30 @@ impl Request {
         sessions: Arc<dyn SessionManagerTrait<Config = SessionConfig>>,
         storage: Arc<dyn ObjectStorage + Send + Sync>,
         cache: Arc<dyn CacheManagerTrait<Config = CacheConfig>>,
+        message_config: MessageConfig,
     ) -> Self {
         Self {
             db,
             sessions,
             storage,
             cache,
+            typing: DashMap::new(),
+            message_config,
         }
     }
 
+    /// 复制一份共享同一组数据库、会话、存储与缓存句柄的 Request，供后台任务脱离全局锁运行
+    /// 输入状态的限流表不共享，复制出的实例不应用于转发输入状态
+    pub fn detached(&self) -> Self {
+        Self::new(
+            self.db.clone(),
+            self.sessions.clone(),
+            self.storage.clone(),
+            self.cache.clone(),
+            self.message_config.clone(),
+        )
+    }
+
 
 
     /// 批量获取所有的用户和好友列表
@@ -0,0 +1,121 @@
+use chrono::Utc;
+use log::{debug, error};
+use uchat_model::{
+    Conversation, MessageId, PinnedMessage, message::ServerMessage, request::RequestResponse,
+};
+
+use super::Request;
+
+/// 每个会话最多置顶的消息条数
+const MAX_PINNED_MESSAGES: usize = 50;
+
+impl Request {
+    /// 置顶或取消置顶会话中的消息，私聊双方均可操作，群聊只有群管理员可以操作
+    /// 置顶状态发生变化时向会话所有参与者推送 MessagePinned
+    pub async fn set_message_pinned(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        pinned: bool,
+    ) -> RequestResponse<()> {
+        match conversation {
+            Conversation::Private(peer_id) if peer_id == user_id => {
+                return RequestResponse::bad_request("不能置顶与自己的会话中的消息");
+            }
+            Conversation::Group(group_id) => match self.is_group_admin(group_id, user_id).await {
+                Ok(true) => {}
+                Ok(false) => return RequestResponse::forbidden("只有群管理员可以置顶消息"),
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            },
+            Conversation::Private(_) => {}
+        }
+
+        let changed = if pinned {
+            // 只能置顶会话中未撤回的消息
+            if let Err(resp) = self
+                .conversation_message_meta(user_id, conversation, message_id)
+                .await
+            {
+                return resp;
+            }
+            match self.db.get_pinned_messages(user_id, conversation).await {
+                Ok(list) if list.len() >= MAX_PINNED_MESSAGES => {
+                    return RequestResponse::bad_request(format!(
+                        "每个会话最多置顶 {} 条消息",
+                        MAX_PINNED_MESSAGES
+                    ));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("获取置顶消息失败，检查数据库错误: {}", e);
+                    return RequestResponse::err(format!("数据库错误：{}", e));
+                }
+            }
+            self.db
+                .pin_message(user_id, conversation, message_id, Utc::now().timestamp_millis())
+                .await
+        } else {
+            self.db
+                .unpin_message(user_id, conversation, message_id)
+                .await
+        };
+
+        match changed {
+            Ok(true) => {
+                debug!(
+                    "用户 {} {}了 {:?} 中的消息 {}",
+                    user_id,
+                    if pinned { "置顶" } else { "取消置顶" },
+                    conversation,
+                    message_id
+                );
+                let timestamp = Utc::now().timestamp_millis();
+                self.push_to_conversation(user_id, conversation, |conversation| {
+                    ServerMessage::MessagePinned {
+                        message_id,
+                        conversation,
+                        user: user_id,
+                        pinned,
+                        timestamp,
+                    }
+                })
+                .await;
+                RequestResponse::ok(if pinned { "置顶成功" } else { "取消置顶成功" }, ())
+            }
+            // 重复置顶不重复推送
+            Ok(false) if pinned => RequestResponse::ok("消息已置顶", ()),
+            Ok(false) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("修改置顶消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 获取会话的置顶消息（置顶时间递减），群聊只有群成员可以查看
+    pub async fn get_pinned_messages(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+    ) -> RequestResponse<Vec<PinnedMessage>> {
+        match conversation {
+            Conversation::Private(peer_id) if peer_id == user_id => {
+                return RequestResponse::bad_request("不能查看与自己的会话");
+            }
+            Conversation::Group(group_id) => match self.is_group_member(group_id, user_id).await {
+                Ok(true) => {}
+                Ok(false) => return RequestResponse::forbidden("不是该群成员"),
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            },
+            Conversation::Private(_) => {}
+        }
+        match self.db.get_pinned_messages(user_id, conversation).await {
+            Ok(list) => RequestResponse::ok("获取成功", list),
+            Err(e) => {
+                error!("获取置顶消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use log::error;
+use uchat_model::{Conversation, MessageId, message::ServerMessage, request::RequestResponse};
+
+use super::Request;
+
+/// 表情字符串的最大字节数，与数据库 emoji 列长度一致
+const MAX_EMOJI_LEN: usize = 32;
+
+impl Request {
+    /// 添加或取消表情回应（added 为 true 表示添加），返回变化后该表情的回应人数
+    /// 确有变化时向会话所有参与者推送 Reaction
+    pub async fn update_reaction(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        emoji: String,
+        added: bool,
+    ) -> RequestResponse<u32> {
+        if emoji.is_empty() || emoji.len() > MAX_EMOJI_LEN || emoji.chars().any(char::is_whitespace) {
+            return RequestResponse::bad_request("无效的表情");
+        }
+        if let Err(resp) = self
+            .conversation_message_meta(user_id, conversation, message_id)
+            .await
+        {
+            return resp;
+        }
+
+        let is_group = conversation.is_group();
+        let changed = if added {
+            self.db.add_reaction(is_group, message_id, user_id, &emoji).await
+        } else {
+            self.db.remove_reaction(is_group, message_id, user_id, &emoji).await
+        };
+        let changed = match changed {
+            Ok(changed) => changed,
+            Err(e) => {
+                error!("更新表情回应失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+        let count = match self.db.get_reaction_count(is_group, message_id, &emoji).await {
+            Ok(count) => count,
+            Err(e) => {
+                error!("获取表情回应人数失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+
+        if changed {
+            self.push_to_conversation(user_id, conversation, |conversation| {
+                ServerMessage::Reaction {
+                    message_id,
+                    conversation,
+                    user: user_id,
+                    emoji: emoji.clone(),
+                    added,
+                    count,
+                }
+            })
+            .await;
+        }
+        RequestResponse::ok("操作成功", count)
+    }
+}
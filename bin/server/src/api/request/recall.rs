@@ -0,0 +1,222 @@
+use chrono::Utc;
+use log::error;
+use uchat_model::{
+    Conversation, MessageEdit, MessageId, MessageType, Timestamp, message::ServerMessage,
+    request::RequestResponse,
+};
+
+use super::Request;
+use crate::db::{MessageMeta, error::DBError};
+
+impl Request {
+    /// 撤回自己发送的消息，只能在发送后 recall_window_secs 内撤回
+    /// 撤回后向会话所有参与者推送 MessageRecalled，返回撤回时间
+    pub async fn recall_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> RequestResponse<Timestamp> {
+        let meta = match self.own_message_meta(user_id, conversation, message_id).await {
+            Ok(meta) => meta,
+            Err(resp) => return resp,
+        };
+        let window_secs = self.message_config.recall_window_secs;
+        if Utc::now().timestamp_millis() - meta.timestamp > window_secs * 1000 {
+            return RequestResponse::forbidden(format!(
+                "消息发送已超过 {} 秒，无法撤回",
+                window_secs
+            ));
+        }
+
+        match self.db.recall_message(conversation.is_group(), message_id).await {
+            Ok(Some(timestamp)) => {
+                self.push_to_conversation(user_id, conversation, |conversation| {
+                    ServerMessage::MessageRecalled {
+                        message_id,
+                        conversation,
+                        sender: user_id,
+                        timestamp,
+                    }
+                })
+                .await;
+                RequestResponse::ok("撤回成功", timestamp)
+            }
+            Ok(None) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("撤回消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 为所有人删除自己发送的消息，只能在发送后 delete_window_secs 内删除
+    /// 删除后向会话所有参与者推送 MessageDeleted，返回删除时间
+    pub async fn delete_message_for_everyone(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> RequestResponse<Timestamp> {
+        let meta = match self.own_message_meta(user_id, conversation, message_id).await {
+            Ok(meta) => meta,
+            Err(resp) => return resp,
+        };
+        let window_secs = self.message_config.delete_window_secs;
+        if Utc::now().timestamp_millis() - meta.timestamp > window_secs * 1000 {
+            return RequestResponse::forbidden(format!(
+                "消息发送已超过 {} 秒，无法为所有人删除",
+                window_secs
+            ));
+        }
+
+        match self
+            .db
+            .delete_message_for_everyone(conversation.is_group(), message_id)
+            .await
+        {
+            Ok(Some(timestamp)) => {
+                self.push_to_conversation(user_id, conversation, |conversation| {
+                    ServerMessage::MessageDeleted {
+                        message_id,
+                        conversation,
+                        sender: user_id,
+                        timestamp,
+                    }
+                })
+                .await;
+                RequestResponse::ok("删除成功", timestamp)
+            }
+            Ok(None) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("删除消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 仅在自己的视角中隐藏消息（已撤回的消息也可隐藏），不通知其他参与者
+    pub async fn hide_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> RequestResponse<()> {
+        if let Err(resp) = self
+            .conversation_message_meta_any::<()>(user_id, conversation, message_id)
+            .await
+        {
+            return resp;
+        }
+
+        match self
+            .db
+            .hide_message(user_id, conversation.is_group(), message_id)
+            .await
+        {
+            Ok(()) => RequestResponse::ok("删除成功", ()),
+            Err(e) => {
+                error!("隐藏消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 编辑自己发送的文本消息，编辑前的内容记入编辑历史
+    /// 编辑后向会话所有参与者推送 MessageEdited，返回编辑时间
+    pub async fn edit_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        message: String,
+    ) -> RequestResponse<Timestamp> {
+        if message.trim().is_empty() {
+            return RequestResponse::bad_request("消息内容不能为空");
+        }
+        let meta = match self.own_message_meta(user_id, conversation, message_id).await {
+            Ok(meta) => meta,
+            Err(resp) => return resp,
+        };
+        if meta.message_type != MessageType::Text {
+            return RequestResponse::bad_request("仅支持编辑文本消息");
+        }
+
+        match self
+            .db
+            .edit_message(conversation.is_group(), message_id, user_id, &message)
+            .await
+        {
+            Ok(edited_at) => {
+                self.push_to_conversation(user_id, conversation, |conversation| {
+                    ServerMessage::MessageEdited {
+                        message_id,
+                        conversation,
+                        sender: user_id,
+                        message: message.clone(),
+                        edited_at,
+                    }
+                })
+                .await;
+                RequestResponse::ok("编辑成功", edited_at)
+            }
+            Err(DBError::NotFound) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("编辑消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 获取消息的编辑历史，只有会话参与者可以查看
+    pub async fn get_message_edits(
+        &self,
+        user_id: u32,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> RequestResponse<Vec<MessageEdit>> {
+        let meta = match self.db.get_message_meta(is_group, message_id).await {
+            Ok(Some(meta)) => meta,
+            Ok(None) => return RequestResponse::not_found(),
+            Err(e) => {
+                error!("获取消息信息失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+        let allowed = if is_group {
+            match self.is_group_member(meta.conversation_id, user_id).await {
+                Ok(allowed) => allowed,
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            }
+        } else {
+            user_id == meta.sender_id || user_id == meta.conversation_id
+        };
+        if !allowed {
+            return RequestResponse::forbidden("无权查看该消息");
+        }
+
+        match self.db.get_message_edits(is_group, message_id).await {
+            Ok(list) => RequestResponse::ok("获取成功", list),
+            Err(e) => {
+                error!("获取消息编辑历史失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 在 conversation_message_meta 的基础上要求消息由 user 本人发送
+    async fn own_message_meta<T>(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<MessageMeta, RequestResponse<T>> {
+        let meta = self
+            .conversation_message_meta(user_id, conversation, message_id)
+            .await?;
+        if meta.sender_id != user_id {
+            return Err(RequestResponse::forbidden("只能操作自己发送的消息"));
+        }
+        Ok(meta)
+    }
+}
@@ -0,0 +1,96 @@
+use log::{error, warn};
+use uchat_model::{
+    Conversation, MessageId, UnreadCount, message::ServerMessage, request::RequestResponse,
+};
+
+use super::Request;
+
+impl Request {
+    /// 标记已读：推进已读游标并推送已读回执
+    /// 私聊发给对方（会话视角为读者本人），同时同步给读者自己的其他连接；群聊发给全体群成员
+    pub async fn mark_read(
+        &self,
+        reader_session_id: &str,
+        conversation: Conversation,
+        up_to_message_id: MessageId,
+    ) {
+        let Some(reader_id) = self.check_session(reader_session_id).await else {
+            warn!(
+                "未能获取会话 {} 对应的用户ID，放弃标记已读",
+                reader_session_id
+            );
+            return;
+        };
+        if let Conversation::Group(group_id) = conversation {
+            match self.is_group_member(group_id, reader_id).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!("用户 {} 不在群 {} 中，放弃标记已读", reader_id, group_id);
+                    return;
+                }
+                Err(e) => {
+                    warn!("标记已读时获取群组 {} 成员失败: {:?}", group_id, e);
+                    return;
+                }
+            }
+        }
+
+        let cursor = match self
+            .db
+            .update_read_cursor(reader_id, conversation, up_to_message_id)
+            .await
+        {
+            Ok(Some(cursor)) => cursor,
+            Ok(None) => return, // 会话中还没有消息
+            Err(e) => {
+                error!("更新已读游标失败，检查数据库错误: {}", e);
+                return;
+            }
+        };
+
+        self.push_to_conversation(reader_id, conversation, |conversation| {
+            ServerMessage::ReadReceipt {
+                reader: reader_id,
+                conversation,
+                up_to_message_id: cursor,
+            }
+        })
+        .await;
+    }
+
+    /// 确认收到私聊消息：首次确认时记录送达时间，并向发送者推送送达回执
+    pub async fn confirm_delivery(&self, receiver_session_id: &str, message_id: MessageId) {
+        let Some(receiver_id) = self.check_session(receiver_session_id).await else {
+            warn!(
+                "未能获取会话 {} 对应的用户ID，放弃确认送达",
+                receiver_session_id
+            );
+            return;
+        };
+        match self.db.mark_delivered(message_id, receiver_id).await {
+            Ok(Some((sender_id, timestamp))) => {
+                let delivered = ServerMessage::Delivered {
+                    message_id,
+                    receiver: receiver_id,
+                    timestamp,
+                };
+                self.send_to_user(sender_id, delivered.into()).await;
+            }
+            Ok(None) => {} // 已由接收者的其他设备确认过，或不是发给该用户的消息
+            Err(e) => {
+                error!("标记消息送达失败，检查数据库错误: {}", e);
+            }
+        }
+    }
+
+    /// 获取当前用户各会话的未读数
+    pub async fn get_unread_counts(&self, user_id: u32) -> RequestResponse<Vec<UnreadCount>> {
+        match self.db.get_unread_counts(user_id).await {
+            Ok(list) => RequestResponse::ok("获取成功", list),
+            Err(e) => {
+                error!("获取未读数失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+}
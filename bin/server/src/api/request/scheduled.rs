@@ -0,0 +1,227 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use log::{debug, error, warn};
+use tokio::sync::Mutex;
+use uchat_model::{
+    Attachment, Conversation, MessageType, ScheduledMessage, ScheduledStatus,
+    request::{RequestResponse, ScheduleMessageRequest},
+};
+
+use super::{OutgoingMessage, Request};
+
+/// 定时消息最多可以提前安排的时长（365 天，毫秒）
+const MAX_SCHEDULE_AHEAD_MILLIS: i64 = 365 * 24 * 3600 * 1000;
+/// 每轮至多投递的定时消息条数，积压的消息留到下一轮
+const SCHEDULE_BATCH_SIZE: u32 = 100;
+/// 领取后超过该时长（毫秒）仍在投递中的定时消息视为投递中断（如服务重启），标记为发送失败
+const SCHEDULE_CLAIM_TIMEOUT_MILLIS: i64 = 10 * 60 * 1000;
+
+/// 检查计划发送时间：必须晚于当前时间且不超过可提前安排的上限
+fn check_send_at(send_at: i64) -> Result<(), String> {
+    let now = Utc::now().timestamp_millis();
+    if send_at <= now {
+        return Err("发送时间必须晚于当前时间".to_string());
+    }
+    if send_at - now > MAX_SCHEDULE_AHEAD_MILLIS {
+        return Err("发送时间不能超过一年之后".to_string());
+    }
+    Ok(())
+}
+
+/// 按实际发送时的规则检查定时消息的内容
+fn check_content(
+    message_type: MessageType,
+    message: &str,
+    attachment: Option<Attachment>,
+) -> Result<(), String> {
+    OutgoingMessage::check_content(message_type, message, attachment.as_ref())
+}
+
+impl Request {
+    /// 创建定时消息，返回定时消息ID
+    pub async fn schedule_message(
+        &self,
+        sender_id: u32,
+        request: ScheduleMessageRequest,
+    ) -> RequestResponse<u64> {
+        if let Err(reason) = check_send_at(request.send_at) {
+            return RequestResponse::bad_request(reason);
+        }
+        if let Err(reason) = check_content(
+            request.message_type,
+            &request.message,
+            request.attachment.clone(),
+        ) {
+            return RequestResponse::bad_request(reason);
+        }
+        match request.conversation {
+            Conversation::Private(peer_id) if peer_id == sender_id => {
+                return RequestResponse::bad_request("不能给自己发送定时消息");
+            }
+            Conversation::Group(group_id) => match self.is_group_member(group_id, sender_id).await {
+                Ok(true) => {}
+                Ok(false) => return RequestResponse::forbidden("不是该群成员"),
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            },
+            Conversation::Private(_) => {}
+        }
+
+        match self.db.add_scheduled_message(sender_id, &request).await {
+            Ok(id) => RequestResponse::ok("创建成功", id),
+            Err(e) => {
+                error!("创建定时消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 获取用户创建的全部定时消息（含已发送与发送失败的）
+    pub async fn list_scheduled_messages(
+        &self,
+        sender_id: u32,
+    ) -> RequestResponse<Vec<ScheduledMessage>> {
+        match self.db.get_scheduled_messages(sender_id).await {
+            Ok(messages) => RequestResponse::ok("获取成功", messages),
+            Err(e) => {
+                error!("获取定时消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 修改尚未发送的定时消息的内容或发送时间
+    pub async fn update_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+        message: Option<String>,
+        send_at: Option<i64>,
+    ) -> RequestResponse<ScheduledMessage> {
+        if let Some(Err(reason)) = send_at.map(check_send_at) {
+            return RequestResponse::bad_request(reason);
+        }
+        // 新内容按该定时消息的类型与附件重新检查
+        if let Some(message) = &message {
+            let scheduled = match self.db.get_scheduled_message(sender_id, id).await {
+                Ok(Some(scheduled)) if scheduled.status == ScheduledStatus::Pending => scheduled,
+                Ok(_) => return RequestResponse::not_found(),
+                Err(e) => {
+                    error!("获取定时消息失败，检查数据库错误: {}", e);
+                    return RequestResponse::err(format!("数据库错误：{}", e));
+                }
+            };
+            if let Err(reason) =
+                check_content(scheduled.message_type, message, scheduled.attachment)
+            {
+                return RequestResponse::bad_request(reason);
+            }
+        }
+        match self
+            .db
+            .update_scheduled_message(sender_id, id, message.as_deref(), send_at)
+            .await
+        {
+            Ok(Some(scheduled)) => RequestResponse::ok("修改成功", scheduled),
+            Ok(None) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("修改定时消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 取消尚未发送的定时消息
+    pub async fn cancel_scheduled_message(&self, sender_id: u32, id: u64) -> RequestResponse<()> {
+        match self.db.cancel_scheduled_message(sender_id, id).await {
+            Ok(true) => RequestResponse::ok("取消成功", ()),
+            Ok(false) => RequestResponse::not_found(),
+            Err(e) => {
+                error!("取消定时消息失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 投递已到发送时间的定时消息，走与在线发送相同的路径，结果回写到定时消息表
+    /// 投递前先领取（状态改为 sending），同一条定时消息不会被投递两次
+    pub async fn deliver_due_scheduled_messages(&self) {
+        let now = Utc::now().timestamp_millis();
+        match self
+            .db
+            .fail_stale_scheduled_messages(now - SCHEDULE_CLAIM_TIMEOUT_MILLIS, "投递中断")
+            .await
+        {
+            Ok(0) => {}
+            Ok(count) => warn!("{} 条定时消息投递中断，已标记为发送失败", count),
+            Err(e) => error!("清理投递中断的定时消息失败，检查数据库错误: {}", e),
+        }
+        let due = match self
+            .db
+            .claim_due_scheduled_messages(now, SCHEDULE_BATCH_SIZE)
+            .await
+        {
+            Ok(due) => due,
+            Err(e) => {
+                error!("领取到期定时消息失败，检查数据库错误: {}", e);
+                return;
+            }
+        };
+        for scheduled in due {
+            let outgoing = OutgoingMessage {
+                message_type: scheduled.message_type,
+                message: scheduled.message,
+                attachment: scheduled.attachment,
+                client_msg_id: None,
+                reply_to: scheduled.reply_to,
+                mentions: scheduled.mentions,
+                mention_all: scheduled.mention_all,
+                forwarded_from: None,
+            };
+            let result = match scheduled.conversation {
+                Conversation::Private(receiver_id) => {
+                    self.deliver_private(scheduled.sender_id, None, receiver_id, outgoing)
+                        .await
+                }
+                // 安排之后可能已经退群，投递时重新检查
+                Conversation::Group(group_id) => {
+                    match self.is_group_member(group_id, scheduled.sender_id).await {
+                        Ok(true) => {
+                            self.deliver_group(scheduled.sender_id, None, group_id, outgoing)
+                                .await
+                        }
+                        Ok(false) => Err("发送者已不是该群成员".to_string()),
+                        Err(e) => Err(format!("服务器错误：{}", e)),
+                    }
+                }
+            };
+            let marked = match result {
+                Ok(message_id) => {
+                    debug!("定时消息 {} 已投递，message_id: {}", scheduled.id, message_id);
+                    self.db.mark_scheduled_sent(scheduled.id, message_id).await
+                }
+                Err(reason) => {
+                    warn!("定时消息 {} 投递失败: {}", scheduled.id, reason);
+                    self.db.mark_scheduled_failed(scheduled.id, &reason).await
+                }
+            };
+            if let Err(e) = marked {
+                error!("更新定时消息 {} 状态失败，检查数据库错误: {}", scheduled.id, e);
+            }
+        }
+    }
+}
+
+/// 启动后台任务，每隔 interval_secs 秒投递一次到期的定时消息
+/// 待发送的定时消息保存在数据库中，服务重启后会在首轮检查时补发；投递途中中断的标记为发送失败，不会重复发送
+pub fn spawn_schedule_task(request: Arc<Mutex<Request>>, interval_secs: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        loop {
+            interval.tick().await;
+            // 只在取出句柄时持有全局锁，投递期间不阻塞其他请求
+            let worker = request.lock().await.detached();
+            worker.deliver_due_scheduled_messages().await;
+        }
+    });
+}
@@ -0,0 +1,105 @@
+use log::error;
+use uchat_model::{
+    HighlightRange, SearchHit, SearchPage,
+    request::{RequestResponse, SearchMessagesQuery},
+};
+
+use super::Request;
+
+/// 搜索结果默认每页条数
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+/// 搜索结果每页最大条数
+const MAX_SEARCH_LIMIT: u32 = 50;
+/// 关键词最大长度（字符数）
+const MAX_QUERY_LEN: usize = 100;
+
+/// 计算关键词在 text 中的命中区间（忽略大小写，以字符计），重叠或相邻的区间会合并
+fn highlight_ranges(text: &str, terms: &[&str]) -> Vec<HighlightRange> {
+    let lower = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect()
+    };
+    let text = lower(text);
+    let mut ranges: Vec<HighlightRange> = Vec::new();
+    for term in terms {
+        let term = lower(term);
+        if term.is_empty() || term.len() > text.len() {
+            continue;
+        }
+        let mut start = 0;
+        while start + term.len() <= text.len() {
+            if text[start..start + term.len()] == term[..] {
+                ranges.push(HighlightRange {
+                    start: start as u32,
+                    end: (start + term.len()) as u32,
+                });
+                start += term.len();
+            } else {
+                start += 1;
+            }
+        }
+    }
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<HighlightRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+impl Request {
+    /// 全文搜索当前用户可见的聊天记录
+    pub async fn search_messages(
+        &self,
+        user_id: u32,
+        mut query: SearchMessagesQuery,
+    ) -> RequestResponse<SearchPage> {
+        query.q = query.q.trim().to_string();
+        if query.q.is_empty() {
+            return RequestResponse::bad_request("关键词不能为空");
+        }
+        if query.q.chars().count() > MAX_QUERY_LEN {
+            return RequestResponse::bad_request(format!(
+                "关键词不能超过 {} 个字符",
+                MAX_QUERY_LEN
+            ));
+        }
+        if query.peer_id.is_some() && query.group_id.is_some() {
+            return RequestResponse::bad_request("peer_id 与 group_id 不能同时指定");
+        }
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+
+        // 多取一条用于判断是否还有下一页
+        let mut rows = match self.db.search_messages(user_id, &query, limit + 1).await {
+            Ok(rows) => rows,
+            Err(e) => {
+                error!("搜索聊天记录失败，检查数据库错误: {}", e);
+                return RequestResponse::err(format!("数据库错误：{}", e));
+            }
+        };
+        let next_offset = if rows.len() > limit as usize {
+            rows.truncate(limit as usize);
+            Some(query.offset + limit)
+        } else {
+            None
+        };
+
+        let terms: Vec<&str> = query.q.split_whitespace().collect();
+        let hits = rows
+            .into_iter()
+            .map(|(conversation, message)| SearchHit {
+                highlights: highlight_ranges(&message.message, &terms),
+                conversation,
+                message,
+            })
+            .collect();
+        RequestResponse::ok("搜索成功", SearchPage { hits, next_offset })
+    }
+}
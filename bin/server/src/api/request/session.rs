@@ -1,5 +1,5 @@
 use super::Request;
-use axum::extract::ws::Message;
+use crate::session::Outbound;
 use uchat_model::{request::RequestResponse, RoleType};
 
 impl Request {
@@ -20,7 +20,7 @@ impl Request {
     pub async fn register_session(
         &self,
         session_id: &str,
-        sender: tokio::sync::mpsc::UnboundedSender<Message>,
+        sender: tokio::sync::mpsc::UnboundedSender<Outbound>,
     ) {
         self.sessions.register_sender(session_id, sender).await;
     }
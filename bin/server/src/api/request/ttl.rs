@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use log::{debug, error};
+use tokio::sync::Mutex;
+use uchat_model::{
+    Conversation, MessageId, message::ServerMessage, request::RequestResponse,
+};
+
+use super::Request;
+
+/// 会话消息有效期的上限（30 天）
+const MAX_MESSAGE_TTL_SECS: u32 = 30 * 24 * 3600;
+/// 每轮每张消息表至多清除的消息条数，积压的消息留到下一轮
+const EXPIRE_BATCH_SIZE: u32 = 500;
+
+impl Request {
+    /// 设置会话的消息有效期，ttl_secs 为 0 表示关闭；只影响之后发送的消息
+    /// 私聊双方均可设置，群聊只有群管理员可以设置；设置后向会话所有参与者推送 MessageTtlChanged
+    pub async fn set_message_ttl(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        ttl_secs: u32,
+    ) -> RequestResponse<()> {
+        if ttl_secs > MAX_MESSAGE_TTL_SECS {
+            return RequestResponse::bad_request(format!(
+                "消息有效期不能超过 {} 秒",
+                MAX_MESSAGE_TTL_SECS
+            ));
+        }
+        match conversation {
+            Conversation::Private(peer_id) if peer_id == user_id => {
+                return RequestResponse::bad_request("不能设置与自己的会话");
+            }
+            Conversation::Group(group_id) => match self.is_group_admin(group_id, user_id).await {
+                Ok(true) => {}
+                Ok(false) => return RequestResponse::forbidden("只有群管理员可以设置消息有效期"),
+                Err(e) => return RequestResponse::err(format!("服务器错误：{}", e)),
+            },
+            Conversation::Private(peer_id) => {
+                if let Err(resp) = self.check_private_peer(peer_id).await {
+                    return resp;
+                }
+            }
+        }
+
+        let ttl = (ttl_secs > 0).then_some(ttl_secs);
+        match self.db.set_message_ttl(user_id, conversation, ttl).await {
+            Ok(()) => {
+                self.push_to_conversation(user_id, conversation, |conversation| {
+                    ServerMessage::MessageTtlChanged {
+                        conversation,
+                        changed_by: user_id,
+                        ttl_secs,
+                    }
+                })
+                .await;
+                RequestResponse::ok("设置成功", ())
+            }
+            Err(e) => {
+                error!("设置消息有效期失败，检查数据库错误: {}", e);
+                RequestResponse::err(format!("数据库错误：{}", e))
+            }
+        }
+    }
+
+    /// 清除已到期的消息，并按会话向在线的参与者推送 MessagesExpired
+    pub async fn purge_expired_messages(&self) {
+        let now = Utc::now().timestamp_millis();
+        let (private, group) = match self
+            .db
+            .purge_expired_messages(now, EXPIRE_BATCH_SIZE)
+            .await
+        {
+            Ok(purged) => purged,
+            Err(e) => {
+                error!("清除到期消息失败，检查数据库错误: {}", e);
+                return;
+            }
+        };
+        if private.is_empty() && group.is_empty() {
+            return;
+        }
+        debug!(
+            "清除到期消息：私聊 {} 条，群聊 {} 条",
+            private.len(),
+            group.len()
+        );
+
+        // 按会话归并，私聊以 (较小用户ID, 较大用户ID) 区分
+        let mut conversations: HashMap<(u32, Conversation), Vec<MessageId>> = HashMap::new();
+        for message in private {
+            let (low, high) = if message.sender_id < message.conversation_id {
+                (message.sender_id, message.conversation_id)
+            } else {
+                (message.conversation_id, message.sender_id)
+            };
+            conversations
+                .entry((low, Conversation::Private(high)))
+                .or_default()
+                .push(message.message_id);
+        }
+        for message in group {
+            conversations
+                .entry((0, Conversation::Group(message.conversation_id)))
+                .or_default()
+                .push(message.message_id);
+        }
+        for ((actor_id, conversation), message_ids) in conversations {
+            self.push_to_conversation(actor_id, conversation, |conversation| {
+                ServerMessage::MessagesExpired {
+                    conversation,
+                    message_ids: message_ids.clone(),
+                }
+            })
+            .await;
+        }
+    }
+}
+
+/// 启动后台任务，每隔 interval_secs 秒清除一次到期消息
+pub fn spawn_expire_task(request: Arc<Mutex<Request>>, interval_secs: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        loop {
+            interval.tick().await;
+            // 只在取出句柄时持有全局锁，清除与推送期间不阻塞其他请求
+            let worker = request.lock().await.detached();
+            worker.purge_expired_messages().await;
+        }
+    });
+}
@@ -0,0 +1,102 @@
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use uchat_model::message::{ServerMessage, TypingState};
+
+use super::Request;
+
+/// 同一状态重复转发的最小间隔，客户端一般每隔几秒重发一次“正在输入”
+const TYPING_REPEAT_INTERVAL: Duration = Duration::from_secs(3);
+/// 状态切换转发的最小间隔，防止 Started/Stopped 交替刷屏
+const TYPING_SWITCH_INTERVAL: Duration = Duration::from_millis(500);
+/// 限流表超过该大小时清理过期条目
+const TYPING_TABLE_SOFT_LIMIT: usize = 4096;
+
+impl Request {
+    /// 转发输入状态，不入库
+    /// 按 (发送者, 会话) 限流：相同状态 TYPING_REPEAT_INTERVAL 内只转发一次，
+    /// 状态切换也至少间隔 TYPING_SWITCH_INTERVAL，超出频率的直接丢弃
+    pub async fn relay_typing(
+        &self,
+        sender_session_id: &str,
+        target: u32,
+        is_group: bool,
+        state: TypingState,
+    ) {
+        let Some(sender_id) = self.check_session(sender_session_id).await else {
+            warn!(
+                "未能获取会话 {} 对应的用户ID，放弃转发输入状态",
+                sender_session_id
+            );
+            return;
+        };
+        if !self.typing_allowed(sender_id, target, is_group, state) {
+            debug!(
+                "用户 {} 在会话 {}（群聊: {}）的输入状态过于频繁，已丢弃",
+                sender_id, target, is_group
+            );
+            return;
+        }
+
+        let typing = ServerMessage::Typing {
+            sender: sender_id,
+            target,
+            is_group,
+            state,
+        };
+        if is_group {
+            match self.get_group_member_ids(target).await {
+                Ok(ids) if ids.contains(&sender_id) => {}
+                Ok(_) => {
+                    warn!("用户 {} 不在群 {} 中，放弃转发输入状态", sender_id, target);
+                    return;
+                }
+                Err(e) => {
+                    warn!("转发输入状态时获取群组 {} 成员失败: {:?}", target, e);
+                    return;
+                }
+            }
+            self.send_to_group_except(target, typing.into(), Some(sender_id))
+                .await;
+        } else {
+            // 私聊只转发给好友，避免向任意用户推送
+            match self.get_friends_ids(sender_id).await {
+                Ok(ids) if ids.contains(&target) => {}
+                Ok(_) => {
+                    warn!("用户 {} 与 {} 不是好友，放弃转发输入状态", sender_id, target);
+                    return;
+                }
+                Err(e) => {
+                    warn!("转发输入状态时获取用户 {} 好友列表失败: {:?}", sender_id, e);
+                    return;
+                }
+            }
+            self.send_to_user(target, typing.into()).await;
+        }
+    }
+
+    /// 限流判断，允许时记录本次转发
+    fn typing_allowed(&self, sender_id: u32, target: u32, is_group: bool, state: TypingState) -> bool {
+        let key = (sender_id, target, is_group);
+        let allowed = match self.typing.get(&key) {
+            Some(last) => {
+                let (at, last_state) = *last;
+                let interval = if last_state == state {
+                    TYPING_REPEAT_INTERVAL
+                } else {
+                    TYPING_SWITCH_INTERVAL
+                };
+                at.elapsed() >= interval
+            }
+            None => true,
+        };
+        if allowed {
+            if self.typing.len() > TYPING_TABLE_SOFT_LIMIT {
+                self.typing
+                    .retain(|_, (at, _)| at.elapsed() < TYPING_REPEAT_INTERVAL);
+            }
+            self.typing.insert(key, (Instant::now(), state));
+        }
+        allowed
+    }
+}
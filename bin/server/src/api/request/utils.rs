@@ -1,7 +1,9 @@
-use axum::extract::ws::Message;
-use log::{debug};
-use uchat_model::{event::content::public::PublicEvent, message::ServerMessage};
-use crate::api::error::RequestError;
+use log::{debug, error};
+use uchat_model::{
+    Conversation, MessageId, event::content::public::PublicEvent, message::ServerMessage,
+    request::RequestResponse,
+};
+use crate::{api::error::RequestError, db::MessageMeta, session::Outbound};
 
 use super::Request;
 
@@ -25,6 +27,79 @@ impl Request {
             Ok(ids)
         }
     }
+    /// 判断用户是否为群成员
+    pub async fn is_group_member(&self, group_id: u32, user_id: u32) -> Result<bool, RequestError> {
+        Ok(self.get_group_member_ids(group_id).await?.contains(&user_id))
+    }
+    /// 判断用户是否为群管理员（目前只有群主）
+    pub async fn is_group_admin(&self, group_id: u32, user_id: u32) -> Result<bool, RequestError> {
+        Ok(self.db.get_group_creator(group_id).await? == Some(user_id))
+    }
+    /// 校验消息属于 user 视角下的该会话（私聊为双方之间的消息，群聊要求 user 仍在群中）且尚未撤回
+    /// 校验失败时返回可直接作为应答的错误
+    pub async fn conversation_message_meta<T>(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<MessageMeta, RequestResponse<T>> {
+        let meta = self
+            .conversation_message_meta_any(user_id, conversation, message_id)
+            .await?;
+        if meta.recalled_at.is_some() {
+            return Err(RequestResponse::bad_request("消息已撤回"));
+        }
+        Ok(meta)
+    }
+    /// 同 conversation_message_meta，但不拒绝已撤回的消息
+    pub async fn conversation_message_meta_any<T>(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<MessageMeta, RequestResponse<T>> {
+        let meta = match self
+            .db
+            .get_message_meta(conversation.is_group(), message_id)
+            .await
+        {
+            Ok(Some(meta)) => meta,
+            Ok(None) => return Err(RequestResponse::not_found()),
+            Err(e) => {
+                error!("获取消息信息失败，检查数据库错误: {}", e);
+                return Err(RequestResponse::err(format!("数据库错误：{}", e)));
+            }
+        };
+        let in_conversation = match conversation {
+            Conversation::Private(peer_id) => {
+                (meta.sender_id == user_id && meta.conversation_id == peer_id)
+                    || (meta.sender_id == peer_id && meta.conversation_id == user_id)
+            }
+            Conversation::Group(group_id) => meta.conversation_id == group_id,
+        };
+        if !in_conversation {
+            return Err(RequestResponse::not_found());
+        }
+        if let Conversation::Group(group_id) = conversation {
+            match self.is_group_member(group_id, user_id).await {
+                Ok(true) => {}
+                Ok(false) => return Err(RequestResponse::forbidden("不在该群中")),
+                Err(e) => return Err(RequestResponse::err(format!("服务器错误：{}", e))),
+            }
+        }
+        Ok(meta)
+    }
+    /// 校验私聊会话的对方用户存在，不存在时返回可直接作为应答的错误
+    pub async fn check_private_peer<T>(&self, peer_id: u32) -> Result<(), RequestResponse<T>> {
+        match self.db.get_userinfo(peer_id).await {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(RequestResponse::not_found()),
+            Err(e) => {
+                error!("获取用户信息失败，检查数据库错误: {}", e);
+                Err(RequestResponse::err(format!("数据库错误：{}", e)))
+            }
+        }
+    }
     /// 获取用户好友ID列表
     /// 先查cache，如果未命中则查数据库并写入cache
     /// 返回好友ID列表
@@ -42,18 +117,35 @@ impl Request {
         }
     }
 
+    /// 向会话推送 actor 触发的事件，build 根据接收方视角下的会话构造消息
+    /// 私聊时对方看到的会话为 actor 本人，actor 自己的其他连接看到的会话为对方；群聊推送给全体成员
+    pub async fn push_to_conversation(
+        &self,
+        actor_id: u32,
+        conversation: Conversation,
+        build: impl Fn(Conversation) -> ServerMessage,
+    ) {
+        match conversation {
+            Conversation::Private(peer_id) => {
+                self.send_to_user(peer_id, build(Conversation::Private(actor_id)).into())
+                    .await;
+                if peer_id != actor_id {
+                    self.send_to_user(actor_id, build(conversation).into()).await;
+                }
+            }
+            Conversation::Group(group_id) => {
+                self.send_to_group(group_id, build(conversation).into()).await;
+            }
+        }
+    }
+
     /// 向该用户好友广播事件
     pub async fn event_broadcast(&self, user_id: u32, event: PublicEvent) -> Result<(), RequestError> {
-        let message = ServerMessage::Event(event);
-        let json = serde_json::to_string(&message).map_err(RequestError::from)?;
+        let message = Outbound::from(ServerMessage::Event(event));
         let friends = self.get_friends_ids(user_id).await?;
         for friend in friends {
-                    self.send_to_user(
-                        friend,
-                        Message::Text(axum::extract::ws::Utf8Bytes::from(&json)),
-                    )
-                    .await;
-                }
+            self.send_to_user(friend, message.clone()).await;
+        }
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,13 +1,34 @@
 use async_trait::async_trait;
 use dashmap::DashMap;
-use std::sync::Arc;
-use super::CacheManagerTrait;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use uchat_model::{MessageId, Timestamp};
+use super::{CacheManagerTrait, CLIENT_MSG_DEDUPE_SECS};
 
 pub struct CacheConfig; // 可扩展参数
 
+/// (sender, client_msg_id) -> (message_id, timestamp, 写入时间)
+type ClientMessages = DashMap<(u32, String), (MessageId, Timestamp, Instant)>;
+
 pub struct MemoryCacheManager {
     group_members: DashMap<u32, Vec<u32>>, // group_id -> member_ids
     friends: DashMap<u32, Vec<u32>>,       // user_id -> friend_ids
+    client_messages: Arc<ClientMessages>,    // 去重窗口内的入库结果
+}
+
+/// 每个去重窗口清理一次过期的 client_msg_id，缓存释放后任务自动退出
+fn spawn_client_message_sweep(client_messages: Weak<ClientMessages>) {
+    let window = Duration::from_secs(CLIENT_MSG_DEDUPE_SECS as u64);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(window);
+        loop {
+            interval.tick().await;
+            let Some(client_messages) = client_messages.upgrade() else {
+                break;
+            };
+            client_messages.retain(|_, v| v.2.elapsed() < window);
+        }
+    });
 }
 
 #[async_trait]
@@ -15,9 +36,12 @@ impl CacheManagerTrait for MemoryCacheManager {
     type Config = CacheConfig;
 
     async fn new_with_config(_: Self::Config) -> Arc<Self> {
+        let client_messages = Arc::new(DashMap::new());
+        spawn_client_message_sweep(Arc::downgrade(&client_messages));
         Arc::new(Self {
             group_members: DashMap::new(),
             friends: DashMap::new(),
+            client_messages,
         })
     }
 
@@ -44,4 +68,18 @@ impl CacheManagerTrait for MemoryCacheManager {
     async fn invalidate_friends(&self, user_id: u32) {
         self.friends.remove(&user_id);
     }
+
+    async fn get_client_message(&self, sender_id: u32, client_msg_id: &str) -> Option<(MessageId, Timestamp)> {
+        let window = Duration::from_secs(CLIENT_MSG_DEDUPE_SECS as u64);
+        self.client_messages
+            .get(&(sender_id, client_msg_id.to_string()))
+            .filter(|v| v.2.elapsed() < window)
+            .map(|v| (v.0, v.1))
+    }
+
+    async fn set_client_message(&self, sender_id: u32, client_msg_id: &str, message_id: MessageId, timestamp: Timestamp) {
+        // 过期条目由 spawn_client_message_sweep 定期清理
+        self.client_messages
+            .insert((sender_id, client_msg_id.to_string()), (message_id, timestamp, Instant::now()));
+    }
 }
\ No newline at end of file
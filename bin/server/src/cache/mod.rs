@@ -10,6 +10,10 @@ pub use crate::cache::redis::CacheConfig;
 
 use async_trait::async_trait;
 use std::sync::Arc;
+use uchat_model::{MessageId, Timestamp};
+
+/// 客户端消息去重窗口（秒）：同一用户在窗口内重发相同 client_msg_id 的消息不会重复入库
+pub const CLIENT_MSG_DEDUPE_SECS: i64 = 300;
 
 #[async_trait]
 pub trait CacheManagerTrait: Send + Sync {
@@ -28,6 +32,10 @@ pub trait CacheManagerTrait: Send + Sync {
     async fn get_friends(&self, user_id: u32) -> Option<Vec<u32>>;
     async fn set_friends(&self, user_id: u32, friends: Vec<u32>);
     async fn invalidate_friends(&self, user_id: u32);
+
+    // 客户端消息去重（sender + client_msg_id -> 已入库的消息），CLIENT_MSG_DEDUPE_SECS 后过期
+    async fn get_client_message(&self, sender_id: u32, client_msg_id: &str) -> Option<(MessageId, Timestamp)>;
+    async fn set_client_message(&self, sender_id: u32, client_msg_id: &str, message_id: MessageId, timestamp: Timestamp);
 }
 
 /// 工厂函数，根据 feature 选择 CacheManager 实现
@@ -1,7 +1,8 @@
 use async_trait::async_trait;
 use std::sync::Arc;
 use crate::redis::SharedRedis;
-use super::CacheManagerTrait;
+use uchat_model::{MessageId, Timestamp};
+use super::{CacheManagerTrait, CLIENT_MSG_DEDUPE_SECS};
 
 pub struct CacheConfig {
     pub redis: SharedRedis,
@@ -85,4 +86,17 @@ impl CacheManagerTrait for RedisCacheManager {
         let key = format!("user:{}:friends", user_id);
         let _ = self.redis.del(&key).await;
     }
+
+    async fn get_client_message(&self, sender_id: u32, client_msg_id: &str) -> Option<(MessageId, Timestamp)> {
+        let key = format!("user:{}:client_msg:{}", sender_id, client_msg_id);
+        let value = self.redis.get(&key).await.ok()??;
+        let (message_id, timestamp) = value.split_once(':')?;
+        Some((message_id.parse().ok()?, timestamp.parse().ok()?))
+    }
+
+    async fn set_client_message(&self, sender_id: u32, client_msg_id: &str, message_id: MessageId, timestamp: Timestamp) {
+        let key = format!("user:{}:client_msg:{}", sender_id, client_msg_id);
+        let value = format!("{}:{}", message_id, timestamp);
+        let _ = self.redis.set_with_expire(&key, &value, CLIENT_MSG_DEDUPE_SECS).await;
+    }
 }
\ No newline at end of file
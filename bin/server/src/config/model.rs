@@ -7,6 +7,8 @@ pub struct AppConfig {
     pub server: ServerConfig,
     pub minio: MinioConfig,
     pub local: LocalStorageConfig,
+    #[serde(default)]
+    pub message: MessageConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -42,6 +44,50 @@ pub struct MinioConfig {
     pub base_url: String,
 }
 
+/// 消息相关的策略配置，整个 [message] 段均可省略
+#[derive(Debug, Deserialize, Clone)]
+pub struct MessageConfig {
+    /// 发送后允许撤回的时长（秒）
+    #[serde(default = "default_recall_window_secs")]
+    pub recall_window_secs: i64,
+    /// 发送后允许为所有人删除的时长（秒）
+    #[serde(default = "default_delete_window_secs")]
+    pub delete_window_secs: i64,
+    /// 清除到期阅后即焚消息的间隔（秒）
+    #[serde(default = "default_expire_interval_secs")]
+    pub expire_interval_secs: u64,
+    /// 检查并投递到期定时消息的间隔（秒）
+    #[serde(default = "default_scheduler_interval_secs")]
+    pub scheduler_interval_secs: u64,
+}
+
+fn default_recall_window_secs() -> i64 {
+    120
+}
+
+fn default_delete_window_secs() -> i64 {
+    86400
+}
+
+fn default_expire_interval_secs() -> u64 {
+    10
+}
+
+fn default_scheduler_interval_secs() -> u64 {
+    5
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self {
+            recall_window_secs: default_recall_window_secs(),
+            delete_window_secs: default_delete_window_secs(),
+            expire_interval_secs: default_expire_interval_secs(),
+            scheduler_interval_secs: default_scheduler_interval_secs(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct LocalStorageConfig {
     pub storage_dir: String,
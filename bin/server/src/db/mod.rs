@@ -12,10 +12,10 @@ use async_trait::async_trait;
 use crate::db::error::DBError;
 
 use uchat_model::{
-    FullPrivateMessage, GroupDetailedInfo, GroupSimpleInfo, IdMessagePair, ManagerUserSimpleInfo,
-    MessageType, PreviewPrivateMessage, RoleType, SessionMessage, UpdateTimestamps,
+    Attachment, Conversation, ConversationSettings, ConversationSummary, ForwardedFrom, FullGroupMessage, FullPrivateMessage, GroupDetailedInfo, GroupSimpleInfo, IdMessagePair, ManagerUserSimpleInfo,
+    MessageEdit, MessageId, MessageType, PinnedMessage, PreviewGroupMessage, PreviewPrivateMessage, RoleType, ScheduledMessage, SessionMessage, UnreadCount, UnreadMention, UpdateTimestamps,
     UserDetailedInfo, UserSimpleInfo,
-    request::{PatchUserRequest, UpdateUserRequest},
+    request::{PatchUserRequest, ScheduleMessageRequest, SearchMessagesQuery, UpdateUserRequest},
 };
 
 #[async_trait]
@@ -91,44 +91,148 @@ pub trait GroupDB: Send + Sync {
     async fn join_group(&self, user_id: u32, group_id: u32) -> Result<(), DBError>;
     /// 退出群聊
     async fn leave_group(&self, user_id: u32, group_id: u32) -> Result<(), DBError>;
+    /// 获取群主（创建者）ID，群组不存在时返回 None
+    async fn get_group_creator(&self, group_id: u32) -> Result<Option<u32>, DBError>;
+}
+
+/// 撤回、编辑消息前用于校验的消息元信息
+#[derive(Debug, sqlx::FromRow)]
+pub struct MessageMeta {
+    pub sender_id: u32,
+    pub conversation_id: u32, // 私聊为接收者ID，群聊为群号
+    pub message_type: MessageType,
+    pub timestamp: i64,
+    pub recalled_at: Option<i64>,
+}
+
+/// 已到期并被清除的阅后即焚消息
+#[derive(Debug, sqlx::FromRow)]
+pub struct ExpiredMessage {
+    pub message_id: u64,
+    pub sender_id: u32,
+    pub conversation_id: u32, // 私聊为接收者ID，群聊为群号
+}
+
+/// 待导入的一条历史消息，保留原时间戳；reply_to 为源消息ID，写入时按导入记录换算
+#[derive(Debug)]
+pub struct ImportMessage {
+    pub source_message_id: MessageId,
+    pub conversation: Conversation, // 私聊为 Private(接收者ID)，群聊为 Group(群号)
+    pub sender_id: u32,
+    pub message_type: MessageType,
+    pub message: String,
+    pub attachment: Option<Attachment>,
+    pub timestamp: i64,
+    pub edited_at: Option<i64>,
+    pub reply_to: Option<MessageId>,
+}
+
+/// 待写入目标会话的一条转发消息
+#[derive(Debug)]
+pub struct ForwardMessage {
+    pub message_type: MessageType,
+    pub message: String,
+    pub attachment: Option<Attachment>,
+    pub forwarded_from: ForwardedFrom,
+}
+
+/// 按消息ID分页的游标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageCursor {
+    /// 最新的一页
+    Latest,
+    /// 早于该消息的一页（向前翻历史）
+    Before(MessageId),
+    /// 晚于该消息的一页（向后追新消息）
+    After(MessageId),
 }
 
 #[async_trait]
 pub trait MessageDB: Send + Sync {
     /// 添加私聊信息聊天记录，返回消息的timestamp和message_id
+    /// 会话设置了消息有效期时同时记录到期时间（添加群聊消息同理）
+    /// forwarded_from 为转发来源，非转发消息为 None
+    #[allow(clippy::too_many_arguments)]
     async fn add_message(
         &self,
         sender: u32,
         receiver: u32,
         message_type: MessageType,
         message: &str,
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
     ) -> Result<(i64, u64), DBError>;
     /// 添加群聊信息聊天记录，返回消息的timestamp和message_id
+    /// mentions 为被 @ 的成员，与消息在同一事务中写入
+    #[allow(clippy::too_many_arguments)]
     async fn add_group_message(
         &self,
         group_id: u32,
         sender: u32,
+        message_type: MessageType,
         message: &str,
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
+        mentions: &[u32],
     ) -> Result<(i64, u64), DBError>;
-    /// 获取私聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
+    /// 在一个事务中以 sender 的身份将多条转发消息写入 target 会话，全部写入或全部不写入
+    /// 返回每条消息的 timestamp 和 message_id，顺序与 messages 一致
+    async fn add_forwarded_messages(
+        &self,
+        sender: u32,
+        target: Conversation,
+        messages: &[ForwardMessage],
+    ) -> Result<Vec<(i64, u64)>, DBError>;
+    /// 获取私聊聊天记录（以 sender 的视角过滤已删除与被其隐藏的消息）
+    /// 按游标取至多 limit 条消息，结果按消息ID递增
     async fn get_messages(
         &self,
         sender: u32,
         receiver: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
     ) -> Result<Vec<SessionMessage>, DBError>;
-    /// 获取群聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
+    /// 获取群聊聊天记录（以 user_id 的视角过滤已删除与被其隐藏的消息，下同）
+    /// 按游标取至多 limit 条消息，结果按消息ID递增
     async fn get_group_messages(
         &self,
+        user_id: u32,
         group_id: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
+    ) -> Result<Vec<SessionMessage>, DBError>;
+    /// 获取群聊中的单条消息，不存在时返回 None
+    async fn get_group_message(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        message_id: MessageId,
+    ) -> Result<Option<SessionMessage>, DBError>;
+    /// 按消息ID批量获取会话中 user_id 可见且未撤回的消息（消息ID递增，不含表情回应）
+    /// 不属于该会话或对其不可见的消息会被忽略；群聊不检查成员身份，由调用方负责
+    async fn get_messages_by_ids(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_ids: &[MessageId],
     ) -> Result<Vec<SessionMessage>, DBError>;
-    /// 获取某群聊最新一条消息时间戳
-    async fn get_latest_timestamp_of_group(&self, group_id: u32) -> Result<Option<i64>, DBError>;
+    /// 获取群聊中直接回复某条消息的消息（消息ID递增），分页方式同 get_group_messages
+    async fn get_group_thread_replies(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        root_id: MessageId,
+        cursor: MessageCursor,
+        limit: u32,
+    ) -> Result<Vec<SessionMessage>, DBError>;
+    /// 获取某群聊最新一条消息时间戳（以 user_id 的视角过滤）
+    async fn get_latest_timestamp_of_group(
+        &self,
+        user_id: u32,
+        group_id: u32,
+    ) -> Result<Option<i64>, DBError>;
     /// 用户加入群聊的所有的群消息最后的时间戳
     async fn get_latest_timestamps_of_all_groups(
         &self,
@@ -142,6 +246,7 @@ pub trait MessageDB: Send + Sync {
     /// 某个群某时间之后的消息
     async fn get_group_messages_after_timestamp(
         &self,
+        user_id: u32,
         group_id: u32,
         after: i64,
     ) -> Result<Vec<SessionMessage>, DBError>;
@@ -151,7 +256,7 @@ pub trait MessageDB: Send + Sync {
         user_id: u32,
         after: i64,
     ) -> Result<Vec<IdMessagePair>, DBError>;
-    /// 获取与某个用户的最后一条私聊消息时间戳
+    /// 获取与某个用户的最后一条私聊消息时间戳（以 user1_id 的视角过滤）
     async fn get_latest_timestamp_with_user(
         &self,
         user1_id: u32,
@@ -167,7 +272,7 @@ pub trait MessageDB: Send + Sync {
         &self,
         user_id: u32,
     ) -> Result<Option<i64>, DBError>;
-    /// 获取与某个用户某时间之后的聊天记录（时间递增）
+    /// 获取与某个用户某时间之后的聊天记录（时间递增，以 user1_id 的视角过滤）
     async fn get_private_messages_after_timestamp(
         &self,
         user1_id: u32,
@@ -180,6 +285,190 @@ pub trait MessageDB: Send + Sync {
         user_id: u32,
         after: i64,
     ) -> Result<Vec<IdMessagePair>, DBError>;
+    /// 将用户在某会话中的已读游标推进到 up_to（不会后退，也不会超过会话中最新的消息），
+    /// 返回推进后的游标；会话中没有任何消息时返回 None
+    async fn update_read_cursor(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        up_to: MessageId,
+    ) -> Result<Option<MessageId>, DBError>;
+    /// 根据已读游标统计当前用户各会话的未读数（只统计他人发送的消息，只返回有未读的会话）
+    async fn get_unread_counts(&self, user_id: u32) -> Result<Vec<UnreadCount>, DBError>;
+    /// 标记私聊消息已送达 receiver，只有首次标记生效
+    /// 返回 (发送者ID, 送达时间戳)；消息不存在、接收者不符或此前已送达时返回 None
+    async fn mark_delivered(
+        &self,
+        message_id: MessageId,
+        receiver_id: u32,
+    ) -> Result<Option<(u32, i64)>, DBError>;
+    /// 获取单条消息的元信息，消息不存在或已被发送者删除时返回 None
+    async fn get_message_meta(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<MessageMeta>, DBError>;
+    /// 撤回消息：清空内容、附件与编辑历史并记录撤回时间，返回撤回时间戳
+    /// 消息已被撤回或删除时不做修改，返回 None
+    async fn recall_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError>;
+    /// 为所有人删除消息：清空内容与附件，删除编辑历史、表情回应等关联记录并记录删除时间，返回删除时间戳
+    /// 删除后的消息不再出现在任何人的聊天记录中；消息已被删除时不做修改，返回 None
+    async fn delete_message_for_everyone(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError>;
+    /// 仅为 user 隐藏消息，重复隐藏会被忽略
+    async fn hide_message(
+        &self,
+        user_id: u32,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<(), DBError>;
+    /// 编辑消息：编辑前的内容写入 message_edits，返回编辑时间戳
+    /// 消息不存在或已被撤回、删除时返回 DBError::NotFound
+    async fn edit_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        editor_id: u32,
+        message: &str,
+    ) -> Result<i64, DBError>;
+    /// 获取消息的编辑历史（时间递增）
+    async fn get_message_edits(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageEdit>, DBError>;
+    /// 添加表情回应，返回是否为新增（重复添加返回 false）
+    async fn add_reaction(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        user_id: u32,
+        emoji: &str,
+    ) -> Result<bool, DBError>;
+    /// 取消表情回应，返回是否确实删除了记录
+    async fn remove_reaction(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        user_id: u32,
+        emoji: &str,
+    ) -> Result<bool, DBError>;
+    /// 获取某条消息上某个表情的回应人数
+    async fn get_reaction_count(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        emoji: &str,
+    ) -> Result<u32, DBError>;
+    /// 获取所在各群中 @ 了当前用户且位于已读游标之后、未撤回的消息（消息ID递减）
+    async fn get_unread_mentions(&self, user_id: u32) -> Result<Vec<UnreadMention>, DBError>;
+    /// 获取用户的全部会话：有过消息往来的私聊与好友、所在的群
+    /// 每项带有最后一条消息的预览、未读数与个人设置，返回顺序不做保证
+    async fn get_conversations(&self, user_id: u32) -> Result<Vec<ConversationSummary>, DBError>;
+    /// 修改会话的个人设置，None 表示保持不变，返回修改后的设置
+    async fn update_conversation_settings(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        pinned: Option<bool>,
+        muted: Option<bool>,
+    ) -> Result<ConversationSettings, DBError>;
+    /// 设置会话的消息有效期（私聊为 user 与对方共享的设置），None 表示关闭
+    async fn set_message_ttl(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        ttl_secs: Option<u32>,
+    ) -> Result<(), DBError>;
+    /// 置顶会话中的消息（私聊为双方共享），消息已置顶时返回 false
+    async fn pin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        pinned_at: i64,
+    ) -> Result<bool, DBError>;
+    /// 取消置顶会话中的消息，消息未置顶时返回 false
+    async fn unpin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<bool, DBError>;
+    /// 获取会话的置顶消息（置顶时间递减），不含已撤回、已删除或已到期的消息
+    async fn get_pinned_messages(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+    ) -> Result<Vec<PinnedMessage>, DBError>;
+    /// 清除 now 之前到期的消息及其编辑历史、表情回应等关联记录，每张消息表至多清除 limit 条
+    /// 返回 (私聊消息, 群聊消息)
+    async fn purge_expired_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<(Vec<ExpiredMessage>, Vec<ExpiredMessage>), DBError>;
+    /// 全文搜索 user 可见的消息（本人参与的私聊与仍在的群，不含已撤回的消息）
+    /// 以空格分隔的关键词需全部命中，结果按时间递减，跳过 query.offset 条后取至多 limit 条
+    async fn search_messages(
+        &self,
+        user_id: u32,
+        query: &SearchMessagesQuery,
+        limit: u32,
+    ) -> Result<Vec<(Conversation, SessionMessage)>, DBError>;
+}
+
+#[async_trait]
+pub trait ScheduledMessageDB: Send + Sync {
+    /// 添加定时消息，返回定时消息ID
+    async fn add_scheduled_message(
+        &self,
+        sender_id: u32,
+        request: &ScheduleMessageRequest,
+    ) -> Result<u64, DBError>;
+    /// 获取用户的全部定时消息（按计划发送时间递增）
+    async fn get_scheduled_messages(&self, sender_id: u32) -> Result<Vec<ScheduledMessage>, DBError>;
+    /// 获取用户的一条定时消息，不存在或不属于该用户时返回 None
+    async fn get_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+    ) -> Result<Option<ScheduledMessage>, DBError>;
+    /// 修改用户尚未发送的定时消息，None 表示保持不变
+    /// 返回修改后的定时消息；不存在、不属于该用户或已不是待发送状态时返回 None
+    async fn update_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+        message: Option<&str>,
+        send_at: Option<i64>,
+    ) -> Result<Option<ScheduledMessage>, DBError>;
+    /// 删除用户尚未发送的定时消息，返回是否确实删除了记录
+    async fn cancel_scheduled_message(&self, sender_id: u32, id: u64) -> Result<bool, DBError>;
+    /// 领取计划发送时间不晚于 now 且仍待发送的定时消息（按计划发送时间递增），至多 limit 条
+    /// 领取的消息状态改为 sending 并记录领取时间，之后不能再修改或取消，也不会被重复领取
+    async fn claim_due_scheduled_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<Vec<ScheduledMessage>, DBError>;
+    /// 将领取时间早于 claimed_before 仍在投递中的定时消息标记为发送失败（投递途中服务中断），返回条数
+    async fn fail_stale_scheduled_messages(
+        &self,
+        claimed_before: i64,
+        reason: &str,
+    ) -> Result<u64, DBError>;
+    /// 标记定时消息已发送，并记录发送后的消息ID
+    async fn mark_scheduled_sent(&self, id: u64, message_id: MessageId) -> Result<(), DBError>;
+    /// 标记定时消息发送失败，并记录失败原因
+    async fn mark_scheduled_failed(&self, id: u64, reason: &str) -> Result<(), DBError>;
 }
 
 #[async_trait]
@@ -207,10 +496,47 @@ pub trait ManagerDB: Send + Sync {
     async fn delete_private_message(&self, message_id: u64) -> Result<u64, DBError>;
     /// 获取一个私聊聊天记录
     async fn get_private_message(&self, message_id: u64) -> Result<FullPrivateMessage, DBError>;
+    /// 获取全服务器近N条群聊天记录
+    async fn get_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError>;
+    /// 获取某群近N条聊天记录
+    async fn get_recent_messages_of_group(
+        &self,
+        count: u32,
+        offset: u32,
+        group_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError>;
+    /// 获取某用户在各群中发送的近N条聊天记录
+    async fn get_user_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+        user_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError>;
+    /// 删除某条群聊天记录及其编辑历史、表情回应等关联记录
+    async fn delete_group_message(&self, message_id: u64) -> Result<u64, DBError>;
+    /// 获取一个群聊天记录
+    async fn get_full_group_message(&self, message_id: u64) -> Result<FullGroupMessage, DBError>;
+    /// 按用户名查找用户ID（用户名可能重复）
+    async fn get_user_ids_by_username(&self, username: &str) -> Result<Vec<u32>, DBError>;
+    /// 按群名查找群号（群名可能重复）
+    async fn get_group_ids_by_name(&self, name: &str) -> Result<Vec<u32>, DBError>;
+    /// 在一个事务中导入一批历史消息，同一来源中已导入过的源消息跳过，返回实际写入的条数
+    async fn import_messages(&self, source: &str, messages: &[ImportMessage])
+    -> Result<u32, DBError>;
 }
 
 // 综合 trait，将所有子 trait 组合起来
 #[async_trait]
-pub trait DB: InitDB + UserDB + FriendDB + GroupDB + MessageDB + ManagerDB {}
+pub trait DB:
+    InitDB + UserDB + FriendDB + GroupDB + MessageDB + ScheduledMessageDB + ManagerDB
+{
+}
 
-impl<T> DB for T where T: InitDB + UserDB + FriendDB + GroupDB + MessageDB + ManagerDB {}
+impl<T> DB for T where
+    T: InitDB + UserDB + FriendDB + GroupDB + MessageDB + ScheduledMessageDB + ManagerDB
+{
+}
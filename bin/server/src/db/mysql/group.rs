@@ -20,6 +20,7 @@ impl GroupDB for MysqlDB {
         Ok(row.map(|r| GroupDetailedInfo {
             group_id: r.group_id,
             title: r.title,
+            pinned: Vec::new(),
         }))
     }
 
@@ -168,4 +169,12 @@ impl GroupDB for MysqlDB {
 
         Ok(())
     }
+
+    /// 获取群主（创建者）ID
+    async fn get_group_creator(&self, group_id: u32) -> Result<Option<u32>, DBError> {
+        let creator_id = sqlx::query_scalar!("SELECT creator_id FROM ugroups WHERE id = ?", group_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(creator_id)
+    }
 }
@@ -1,8 +1,33 @@
 use super::MysqlDB;
-use crate::db::{ManagerDB, error::DBError};
+use crate::db::{ImportMessage, ManagerDB, error::DBError};
 use anyhow::Result;
 use async_trait::async_trait;
-use uchat_model::{FullPrivateMessage, ManagerUserSimpleInfo, PreviewPrivateMessage, RoleType};
+use sqlx::{MySql, Transaction, types::Json};
+use uchat_model::{
+    Conversation, FullGroupMessage, FullPrivateMessage, ManagerUserSimpleInfo, MessageId,
+    MessageType, PreviewGroupMessage, PreviewPrivateMessage, RoleType,
+};
+
+/// 查询某来源的源消息导入后在本实例中的消息ID
+async fn imported_message_id(
+    tx: &mut Transaction<'_, MySql>,
+    source: &str,
+    conversation_type: &str,
+    source_message_id: MessageId,
+) -> Result<Option<MessageId>, DBError> {
+    let message_id = sqlx::query_scalar!(
+        r#"
+    SELECT message_id FROM imported_messages
+    WHERE source = ? AND conversation_type = ? AND source_message_id = ?
+    "#,
+        source,
+        conversation_type,
+        source_message_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(message_id)
+}
 
 #[async_trait]
 impl ManagerDB for MysqlDB {
@@ -157,4 +182,233 @@ impl ManagerDB for MysqlDB {
             None => Err(DBError::NotFound),
         }
     }
+    /// 获取服务器前N条群消息
+    async fn get_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let messages = sqlx::query_as!(
+            PreviewGroupMessage,
+            r#"
+            SELECT id, group_id, group_name, sender_id, sender_username,
+                message_type AS `message_type: MessageType`, message_preview AS `message_preview!`, timestamp
+            FROM recent_group_messages_view
+            ORDER BY timestamp DESC
+            LIMIT ? OFFSET ?
+            "#,
+            count,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+    /// 获取某群前N条消息
+    async fn get_recent_messages_of_group(
+        &self,
+        count: u32,
+        offset: u32,
+        group_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let messages = sqlx::query_as!(
+            PreviewGroupMessage,
+            r#"
+            SELECT id, group_id, group_name, sender_id, sender_username,
+                message_type AS `message_type: MessageType`, message_preview AS `message_preview!`, timestamp
+            FROM recent_group_messages_view
+            WHERE group_id = ?
+            ORDER BY timestamp DESC
+            LIMIT ? OFFSET ?
+            "#,
+            group_id,
+            count,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+    /// 获取某用户在各群中发送的前N条消息
+    async fn get_user_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+        user_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let messages = sqlx::query_as!(
+            PreviewGroupMessage,
+            r#"
+            SELECT id, group_id, group_name, sender_id, sender_username,
+                message_type AS `message_type: MessageType`, message_preview AS `message_preview!`, timestamp
+            FROM recent_group_messages_view
+            WHERE sender_id = ?
+            ORDER BY timestamp DESC
+            LIMIT ? OFFSET ?
+            "#,
+            user_id,
+            count,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+    /// 根据message_id删除某条群聊天记录
+    /// 群消息的 @ 记录随外键级联删除，其余关联记录需手动清除
+    async fn delete_group_message(&self, message_id: u64) -> Result<u64, DBError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = 'group' AND message_id = ?",
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = 'group' AND message_id = ?",
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM pinned_messages WHERE conversation_type = 'group' AND message_id = ?",
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM hidden_messages WHERE conversation_type = 'group' AND message_id = ?",
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let result = sqlx::query!("DELETE FROM ugroup_messages WHERE id = ?", message_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+    /// 获取一条群聊消息
+    async fn get_full_group_message(&self, message_id: u64) -> Result<FullGroupMessage, DBError> {
+        let message = sqlx::query_as!(
+            FullGroupMessage,
+            r#"
+            SELECT
+                m.id,
+                m.group_id,
+                g.name AS group_name,
+                m.sender_id,
+                s.username AS sender_username,
+                m.message_type AS `message_type: MessageType`,
+                m.message,
+                m.timestamp
+            FROM ugroup_messages m
+            JOIN ugroups g ON m.group_id = g.id
+            JOIN users s ON m.sender_id = s.id
+            WHERE m.id = ?
+            "#,
+            message_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        message.ok_or(DBError::NotFound)
+    }
+    /// 按用户名查找用户ID
+    async fn get_user_ids_by_username(&self, username: &str) -> Result<Vec<u32>, DBError> {
+        let ids = sqlx::query_scalar!("SELECT id FROM users WHERE username = ?", username)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids)
+    }
+    /// 按群名查找群号
+    async fn get_group_ids_by_name(&self, name: &str) -> Result<Vec<u32>, DBError> {
+        let ids = sqlx::query_scalar!("SELECT id FROM ugroups WHERE name = ?", name)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids)
+    }
+    /// 在一个事务中导入一批历史消息
+    /// 导入的私聊消息视为已送达，不会再作为离线消息推送
+    async fn import_messages(
+        &self,
+        source: &str,
+        messages: &[ImportMessage],
+    ) -> Result<u32, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let mut imported = 0;
+        for message in messages {
+            let conversation_type = message.conversation.kind_str();
+            if imported_message_id(&mut tx, source, conversation_type, message.source_message_id)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+            // 被回复的消息未导入时不保留引用
+            let reply_to = match message.reply_to {
+                Some(id) => imported_message_id(&mut tx, source, conversation_type, id).await?,
+                None => None,
+            };
+            let result = match message.conversation {
+                Conversation::Private(receiver_id) => {
+                    sqlx::query!(
+                        r#"
+                    INSERT INTO messages (sender_id, receiver_id, message_type, message, attachment, timestamp, delivered_at, edited_at, reply_to)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                        message.sender_id,
+                        receiver_id,
+                        message.message_type,
+                        message.message,
+                        message.attachment.as_ref().map(Json),
+                        message.timestamp,
+                        message.timestamp,
+                        message.edited_at,
+                        reply_to
+                    )
+                    .execute(&mut *tx)
+                    .await?
+                }
+                Conversation::Group(group_id) => {
+                    sqlx::query!(
+                        r#"
+                    INSERT INTO ugroup_messages (group_id, sender_id, message_type, message, attachment, timestamp, edited_at, reply_to)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                        group_id,
+                        message.sender_id,
+                        message.message_type,
+                        message.message,
+                        message.attachment.as_ref().map(Json),
+                        message.timestamp,
+                        message.edited_at,
+                        reply_to
+                    )
+                    .execute(&mut *tx)
+                    .await?
+                }
+            };
+            sqlx::query!(
+                r#"
+            INSERT INTO imported_messages (source, conversation_type, source_message_id, message_id)
+            VALUES (?, ?, ?, ?)
+            "#,
+                source,
+                conversation_type,
+                message.source_message_id,
+                result.last_insert_id()
+            )
+            .execute(&mut *tx)
+            .await?;
+            imported += 1;
+        }
+        tx.commit().await?;
+
+        Ok(imported)
+    }
 }
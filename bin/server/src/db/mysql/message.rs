@@ -1,164 +1,814 @@
 use super::MysqlDB;
-use crate::db::{MessageDB, error::DBError};
+use crate::db::{
+    ExpiredMessage, ForwardMessage, MessageCursor, MessageDB, MessageMeta, error::DBError,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::Utc;
+use sqlx::{Executor, MySql, QueryBuilder, Transaction, types::Json};
 use std::collections::HashMap;
-use uchat_model::{GroupSessionMessage, IdMessagePair, MessageType, SessionMessage};
+use uchat_model::{
+    Attachment, Conversation, ConversationSettings, ConversationSummary, ForwardedFrom, IdMessagePair,
+    MessageEdit, MessageId, MessagePreview, MessageType, PinnedMessage, ReactionCount,
+    SessionMessage, UnreadCount, UnreadMention, request::SearchMessagesQuery,
+};
+
+/// SessionMessage 对应的查询列，供 QueryBuilder 动态拼接的查询使用
+const SESSION_MESSAGE_COLUMNS: &str = "id AS message_id, sender_id, `timestamp`, message_type, message, attachment, edited_at, recalled_at, reply_to, expires_at, forwarded_from";
+
+/// 私聊与群聊消息分表存储，消息 ID 各自独立
+fn message_table(is_group: bool) -> &'static str {
+    if is_group { "ugroup_messages" } else { "messages" }
+}
+
+/// message_edits 等表中 conversation_type 列的取值
+fn conversation_type(is_group: bool) -> &'static str {
+    if is_group { "group" } else { "private" }
+}
+
+/// 游标对应的翻页方向与消息ID边界：向后翻页为 (true, id)，其余为 (false, id)
+/// 取最新一页等同于早于 u64::MAX 的一页；倒序查询的结果需由调用方翻转为递增
+fn cursor_bound(cursor: MessageCursor) -> (bool, MessageId) {
+    match cursor {
+        MessageCursor::Latest => (false, MessageId::MAX),
+        MessageCursor::Before(id) => (false, id),
+        MessageCursor::After(id) => (true, id),
+    }
+}
+
+/// 将查询宏返回的消息行转换为 SessionMessage，表情回应另行填充
+macro_rules! session_message {
+    ($row:expr) => {{
+        let row = $row;
+        SessionMessage {
+            message_id: row.message_id,
+            sender_id: row.sender_id,
+            timestamp: row.timestamp,
+            message_type: row.message_type,
+            message: row.message,
+            attachment: row.attachment.map(|a| a.0),
+            edited_at: row.edited_at,
+            recalled_at: row.recalled_at,
+            reply_to: row.reply_to,
+            expires_at: row.expires_at,
+            forwarded_from: row.forwarded_from.map(|f| f.0),
+            reactions: Vec::new(),
+        }
+    }};
+}
+
+/// 只保留 user 可见的消息：未被发送者删除、未到期，且未被 user 隐藏（user 以 ? 占位）
+/// table 为消息表名或其别名；静态查询中内联了相同的条件，修改时需一并调整
+fn visible_condition(is_group: bool, table: &str) -> String {
+    format!(
+        "{table}.deleted_at IS NULL \
+         AND ({table}.expires_at IS NULL OR {table}.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000) \
+         AND NOT EXISTS (SELECT 1 FROM hidden_messages h \
+         WHERE h.user_id = ? AND h.conversation_type = '{}' AND h.message_id = {table}.id)",
+        conversation_type(is_group)
+    )
+}
+
+/// message_ttls、pinned_messages 中会话的键：群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+fn ttl_key(user_id: u32, conversation: Conversation) -> (u32, u32) {
+    match conversation {
+        Conversation::Private(peer_id) => (user_id.min(peer_id), user_id.max(peer_id)),
+        Conversation::Group(group_id) => (group_id, 0),
+    }
+}
+
+/// 在 QueryBuilder 中追加可见条件，并绑定 user_id
+fn push_visible_condition(
+    builder: &mut QueryBuilder<'_, MySql>,
+    is_group: bool,
+    table: &str,
+    user_id: u32,
+) {
+    let condition = visible_condition(is_group, table);
+    let (head, tail) = condition.split_once('?').unwrap_or((&condition, ""));
+    builder.push(head).push_bind(user_id).push(tail);
+}
+
+/// 写入一条消息，返回 message_id；会话未设置有效期时子查询为 NULL，expires_at 随之为 NULL
+#[allow(clippy::too_many_arguments)]
+async fn insert_message<'e, E: Executor<'e, Database = MySql>>(
+    executor: E,
+    sender: u32,
+    conversation: Conversation,
+    message_type: MessageType,
+    message: &str,
+    attachment: Option<&Attachment>,
+    reply_to: Option<MessageId>,
+    forwarded_from: Option<&ForwardedFrom>,
+    timestamp: i64,
+) -> Result<u64, DBError> {
+    let attachment = attachment.map(Json);
+    let forwarded_from = forwarded_from.map(Json);
+    let result = match conversation {
+        Conversation::Private(receiver) => {
+            let (ttl_id, ttl_peer_id) = ttl_key(sender, conversation);
+            sqlx::query!(
+                r#"
+        INSERT INTO messages (sender_id, receiver_id, message_type, message, attachment, timestamp, reply_to, forwarded_from, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ? + (
+            SELECT ttl_secs * 1000 FROM message_ttls
+            WHERE conversation_type = 'private' AND conversation_id = ? AND peer_id = ?
+        ))
+        "#,
+                sender,
+                receiver,
+                message_type,
+                message,
+                attachment,
+                timestamp,
+                reply_to,
+                forwarded_from,
+                timestamp,
+                ttl_id,
+                ttl_peer_id
+            )
+            .execute(executor)
+            .await?
+        }
+        Conversation::Group(group_id) => {
+            sqlx::query!(
+                r#"
+        INSERT INTO ugroup_messages (group_id, sender_id, message_type, message, attachment, timestamp, reply_to, forwarded_from, expires_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ? + (
+            SELECT ttl_secs * 1000 FROM message_ttls
+            WHERE conversation_type = 'group' AND conversation_id = ? AND peer_id = 0
+        ))
+        "#,
+                group_id,
+                sender,
+                message_type,
+                message,
+                attachment,
+                timestamp,
+                reply_to,
+                forwarded_from,
+                timestamp,
+                group_id
+            )
+            .execute(executor)
+            .await?
+        }
+    };
+    Ok(result.last_insert_id())
+}
+
+/// 记录群消息中被 @ 的成员，重复记录会被忽略
+async fn insert_mentions<'e, E: Executor<'e, Database = MySql>>(
+    executor: E,
+    group_id: u32,
+    message_id: MessageId,
+    user_ids: &[u32],
+) -> Result<(), DBError> {
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+    let mut builder: QueryBuilder<MySql> =
+        QueryBuilder::new("INSERT IGNORE INTO message_mentions (message_id, group_id, user_id) ");
+    builder.push_values(user_ids, |mut b, user_id| {
+        b.push_bind(message_id).push_bind(group_id).push_bind(*user_id);
+    });
+    builder.build().execute(executor).await?;
+    Ok(())
+}
+
+/// 在事务中清除一张消息表中 now 之前到期的至多 limit 条消息及其关联记录
+async fn purge_expired_in(
+    tx: &mut Transaction<'_, MySql>,
+    is_group: bool,
+    now: i64,
+    limit: u32,
+) -> Result<Vec<ExpiredMessage>, DBError> {
+    let expired = if is_group {
+        sqlx::query_as!(
+            ExpiredMessage,
+            r#"
+    SELECT id AS message_id, sender_id, group_id AS conversation_id
+    FROM ugroup_messages
+    WHERE expires_at <= ?
+    ORDER BY expires_at
+    LIMIT ?
+    FOR UPDATE
+    "#,
+            now,
+            limit
+        )
+        .fetch_all(&mut **tx)
+        .await?
+    } else {
+        sqlx::query_as!(
+            ExpiredMessage,
+            r#"
+    SELECT id AS message_id, sender_id, receiver_id AS conversation_id
+    FROM messages
+    WHERE expires_at <= ?
+    ORDER BY expires_at
+    LIMIT ?
+    FOR UPDATE
+    "#,
+            now,
+            limit
+        )
+        .fetch_all(&mut **tx)
+        .await?
+    };
+    if expired.is_empty() {
+        return Ok(expired);
+    }
+    // 群消息的 @ 记录随外键级联删除，其余关联记录需手动清除
+    for related in ["message_edits", "message_reactions", "pinned_messages", "hidden_messages"] {
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new(format!("DELETE FROM {related} WHERE conversation_type = "));
+        builder
+            .push_bind(conversation_type(is_group))
+            .push(" AND message_id IN (");
+        let mut ids = builder.separated(", ");
+        for message in &expired {
+            ids.push_bind(message.message_id);
+        }
+        builder.push(")").build().execute(&mut **tx).await?;
+    }
+    let mut builder: QueryBuilder<MySql> = QueryBuilder::new(format!(
+        "DELETE FROM {} WHERE id IN (",
+        message_table(is_group)
+    ));
+    let mut ids = builder.separated(", ");
+    for message in &expired {
+        ids.push_bind(message.message_id);
+    }
+    builder.push(")").build().execute(&mut **tx).await?;
+    Ok(expired)
+}
+
+/// 搜索命中的消息行，conversation_id 为调用者视角下的对方用户ID或群号
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+    conversation_type: String,
+    conversation_id: u32,
+    #[sqlx(flatten)]
+    message: SessionMessage,
+}
+
+/// 会话列表的一行：对方用户或群的信息、最后一条消息、未读数与个人设置
+struct ConversationRow {
+    conversation_id: u32,
+    title: String,
+    avatar_url: Option<String>,
+    message_id: Option<u64>,
+    sender_id: Option<u32>,
+    message_type: Option<MessageType>,
+    preview: Option<String>,
+    timestamp: Option<i64>,
+    recalled_at: Option<i64>,
+    unread: i64,
+    pinned: Option<bool>,
+    muted: Option<bool>,
+    ttl_secs: Option<u32>,
+}
+
+impl ConversationRow {
+    fn into_summary(self, conversation: Conversation) -> ConversationSummary {
+        let last_message = match (self.message_id, self.sender_id, self.message_type) {
+            (Some(message_id), Some(sender_id), Some(message_type)) => Some(MessagePreview {
+                message_id,
+                sender_id,
+                message_type,
+                message: self.preview.unwrap_or_default(),
+                recalled: self.recalled_at.is_some(),
+            }),
+            _ => None,
+        };
+        ConversationSummary {
+            conversation,
+            title: self.title,
+            avatar_url: self.avatar_url,
+            last_message,
+            last_timestamp: self.timestamp,
+            unread: self.unread as u32,
+            pinned: self.pinned.unwrap_or(false),
+            muted: self.muted.unwrap_or(false),
+            message_ttl_secs: self.ttl_secs,
+        }
+    }
+}
+
+/// 单条消息上某个表情的回应人数
+#[derive(sqlx::FromRow)]
+struct ReactionRow {
+    message_id: u64,
+    emoji: String,
+    count: i64,
+}
+
+/// 每次查询表情回应时 IN 列表的最大长度
+const REACTION_BATCH_SIZE: usize = 500;
+
+/// 未读 @ 列表最多返回的消息条数
+const UNREAD_MENTION_LIMIT: u32 = 100;
+
+/// 会话列表中消息预览的最大字符数
+const PREVIEW_LEN: u32 = 50;
+
+/// 全文索引使用 ngram 分词器（兼容中文），与 MySQL 的 ngram_token_size 保持一致
+/// 短于该长度的关键词不会被索引，退化为 LIKE 匹配
+const NGRAM_TOKEN_SIZE: usize = 2;
+
+/// 转义 LIKE 模式中的通配符
+fn escape_like(term: &str) -> String {
+    term.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// 追加搜索关键词与过滤条件（不含会话范围）
+fn push_search_conditions(builder: &mut QueryBuilder<'_, MySql>, query: &SearchMessagesQuery) {
+    let mut against = String::new();
+    for term in query.q.split_whitespace() {
+        if term.chars().count() < NGRAM_TOKEN_SIZE {
+            builder
+                .push(" AND message LIKE ")
+                .push_bind(format!("%{}%", escape_like(term)));
+            continue;
+        }
+        // 每个关键词都以必须命中的短语检索，避免用户输入中的布尔运算符生效
+        let term = term.replace('"', "");
+        if !term.is_empty() {
+            against.push_str(&format!("+\"{}\" ", term));
+        }
+    }
+    if !against.is_empty() {
+        builder
+            .push(" AND MATCH(message) AGAINST (")
+            .push_bind(against)
+            .push(" IN BOOLEAN MODE)");
+    }
+    if let Some(sender_id) = query.sender_id {
+        builder.push(" AND sender_id = ").push_bind(sender_id);
+    }
+    if let Some(start) = query.start {
+        builder.push(" AND `timestamp` >= ").push_bind(start);
+    }
+    if let Some(end) = query.end {
+        builder.push(" AND `timestamp` < ").push_bind(end);
+    }
+    if let Some(message_type) = query.message_type {
+        builder.push(" AND message_type = ").push_bind(message_type);
+    }
+}
+
+impl MysqlDB {
+    /// 为一批消息填充表情回应统计
+    async fn fill_reactions<'a>(
+        &self,
+        is_group: bool,
+        messages: impl Iterator<Item = &'a mut SessionMessage>,
+    ) -> Result<(), DBError> {
+        let mut messages: Vec<&mut SessionMessage> = messages.collect();
+        for batch in messages.chunks_mut(REACTION_BATCH_SIZE) {
+            let mut builder = QueryBuilder::<MySql>::new(
+                "SELECT message_id, emoji, COUNT(*) AS count FROM message_reactions WHERE conversation_type = ",
+            );
+            builder.push_bind(conversation_type(is_group));
+            builder.push(" AND message_id IN (");
+            let mut ids = builder.separated(", ");
+            for message in batch.iter() {
+                ids.push_bind(message.message_id);
+            }
+            builder.push(") GROUP BY message_id, emoji ORDER BY MIN(created_at) ASC");
+            let rows = builder
+                .build_query_as::<ReactionRow>()
+                .fetch_all(&self.pool)
+                .await?;
+
+            let mut by_message: HashMap<u64, Vec<ReactionCount>> = HashMap::new();
+            for row in rows {
+                by_message.entry(row.message_id).or_default().push(ReactionCount {
+                    emoji: row.emoji,
+                    count: row.count as u32,
+                });
+            }
+            for message in batch.iter_mut() {
+                if let Some(reactions) = by_message.remove(&message.message_id) {
+                    message.reactions = reactions;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 未读数统计行，conversation_id 为对方用户 ID 或群号
+struct UnreadRow {
+    conversation_id: u32,
+    last_read_message_id: u64,
+    unread: i64,
+}
 
 #[async_trait]
 impl MessageDB for MysqlDB {
     /// 添加私聊信息聊天记录，返回消息的时间戳
     /// 注意：这里的时间戳是秒级别的，返回值是 u64 类型
     /// 发送者和接收者的 ID 都是 u32 类型
-    /// 消息类型是 MessageType 枚举，消息内容是字符串
+    /// 消息类型是 MessageType 枚举，消息内容是字符串，附件以 JSON 保存
     /// 该函数会将消息插入到 messages 表中，并返回当前的时间和message_id
+    #[allow(clippy::too_many_arguments)]
     async fn add_message(
         &self,
         sender: u32,
         receiver: u32,
         message_type: MessageType,
         message: &str,
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
     ) -> Result<(i64, u64), DBError> {
         let now_ts = Utc::now().timestamp_millis(); // 毫秒级时间戳
-
-        let result = sqlx::query!(
-            r#"
-        INSERT INTO messages (sender_id, receiver_id, message_type, message, timestamp)
-        VALUES (?, ?, ?, ?, ?)
-        "#,
+        let message_id = insert_message(
+            &self.pool,
             sender,
-            receiver,
-            message_type as MessageType,
+            Conversation::Private(receiver),
+            message_type,
             message,
-            now_ts
+            attachment,
+            reply_to,
+            forwarded_from,
+            now_ts,
         )
-        .execute(&self.pool)
         .await?;
 
-        Ok((now_ts, result.last_insert_id()))
+        Ok((now_ts, message_id))
     }
 
     /// 添加群聊消息记录，返回消息的时间戳
     /// 注意：这里的时间戳是秒级别的，返回值是 u64 类型
-    /// 群聊 ID 是 u32 类型，发送者 ID 是 u32 类型，消息内容是字符串
+    /// 群聊 ID 是 u32 类型，发送者 ID 是 u32 类型，消息内容是字符串，附件以 JSON 保存
+    /// 被 @ 的成员与消息在同一事务中写入
+    #[allow(clippy::too_many_arguments)]
     async fn add_group_message(
         &self,
         group_id: u32,
         sender: u32,
+        message_type: MessageType,
         message: &str,
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
+        mentions: &[u32],
     ) -> Result<(i64, u64), DBError> {
         let timestamp = Utc::now().timestamp_millis(); // 毫秒级时间戳
-
-        let result = sqlx::query!(
-            r#"
-        INSERT INTO ugroup_messages (group_id, sender_id, message, timestamp)
-        VALUES (?, ?, ?, ?)
-        "#,
-            group_id,
+        let mut tx = self.pool.begin().await?;
+        let message_id = insert_message(
+            &mut *tx,
             sender,
+            Conversation::Group(group_id),
+            message_type,
             message,
-            timestamp
+            attachment,
+            reply_to,
+            forwarded_from,
+            timestamp,
         )
-        .execute(&self.pool)
         .await?;
+        insert_mentions(&mut *tx, group_id, message_id, mentions).await?;
+        tx.commit().await?;
 
-        Ok((timestamp, result.last_insert_id()))
+        Ok((timestamp, message_id))
+    }
+
+    async fn add_forwarded_messages(
+        &self,
+        sender: u32,
+        target: Conversation,
+        messages: &[ForwardMessage],
+    ) -> Result<Vec<(i64, u64)>, DBError> {
+        let timestamp = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let mut stored = Vec::with_capacity(messages.len());
+        for forward in messages {
+            let message_id = insert_message(
+                &mut *tx,
+                sender,
+                target,
+                forward.message_type,
+                &forward.message,
+                forward.attachment.as_ref(),
+                None,
+                Some(&forward.forwarded_from),
+                timestamp,
+            )
+            .await?;
+            stored.push((timestamp, message_id));
+        }
+        tx.commit().await?;
+        Ok(stored)
     }
 
     /// 获取私聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
     async fn get_messages(
         &self,
         sender: u32,
         receiver: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        // 每页显示的消息数
-        let limit = 30;
-        // 计算要偏移的数量
-        let offset_rows = offset * limit;
-
-        // 不再使用 DATE_FORMAT，而是直接查询原始 timestamp 列
-        let messages = sqlx::query_as!(
-            SessionMessage,
-            r#"
-            SELECT
-                id as `message_id!`,
-                sender_id AS `sender_id!`,
-                `timestamp`,
-                message_type as `message_type: MessageType`,
-                message AS `message!`
+        let (forward, bound) = cursor_bound(cursor);
+        let mut messages: Vec<SessionMessage> = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
             FROM messages
-            WHERE
-                (sender_id = ? AND receiver_id = ?)
-                OR
-                (sender_id = ? AND receiver_id = ?)
-            ORDER BY `timestamp` ASC
+            WHERE ((sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                AND id > ?
+            ORDER BY id ASC
             LIMIT ?
-            OFFSET ?
             "#,
-            sender,
-            receiver,
-            receiver,
-            sender,
-            limit,
-            offset_rows
-        )
-        .fetch_all(&self.pool)
-        .await?;
+                sender,
+                receiver,
+                receiver,
+                sender,
+                sender,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM messages
+            WHERE ((sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                AND id < ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+                sender,
+                receiver,
+                receiver,
+                sender,
+                sender,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
+        self.fill_reactions(false, messages.iter_mut()).await?;
 
         Ok(messages)
     }
 
     /// 获取群聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
     async fn get_group_messages(
         &self,
+        user_id: u32,
         group_id: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        let limit = 30;
-        let offset_rows = offset * limit;
-
-        // 使用 query_as! 时，需要把表里的 `timestamp` 原样返回
-        let messages = sqlx::query_as!(
-            SessionMessage,
-            r#"
-            SELECT
-                id as `message_id!`,
-                sender_id as `sender_id!`,
-                `timestamp`,
-                message_type as `message_type: MessageType`,
-                message     as `message!`
+        let (forward, bound) = cursor_bound(cursor);
+        let mut messages: Vec<SessionMessage> = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
             FROM ugroup_messages
             WHERE group_id = ?
-            ORDER BY `timestamp` ASC
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id > ?
+            ORDER BY id ASC
             LIMIT ?
-            OFFSET ?
+            "#,
+                group_id,
+                user_id,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE group_id = ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id < ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+                group_id,
+                user_id,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
+        self.fill_reactions(true, messages.iter_mut()).await?;
+        Ok(messages)
+    }
+    async fn get_group_message(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        message_id: MessageId,
+    ) -> Result<Option<SessionMessage>, DBError> {
+        let message = sqlx::query!(
+            r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE group_id = ? AND id = ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             "#,
             group_id,
-            limit,
-            offset_rows
+            message_id,
+            user_id
         )
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
-        // 这里 messages 就已经是 Vec<Message>，无需再手动解析
+        let mut message = message.map(|r| session_message!(r));
+        self.fill_reactions(true, message.iter_mut()).await?;
+        Ok(message)
+    }
+
+    async fn get_messages_by_ids(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<SessionMessage>, DBError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let is_group = conversation.is_group();
+        let table = message_table(is_group);
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new(format!("SELECT {SESSION_MESSAGE_COLUMNS} FROM {table} WHERE "));
+        match conversation {
+            Conversation::Private(peer_id) => {
+                builder
+                    .push("((sender_id = ")
+                    .push_bind(user_id)
+                    .push(" AND receiver_id = ")
+                    .push_bind(peer_id)
+                    .push(") OR (sender_id = ")
+                    .push_bind(peer_id)
+                    .push(" AND receiver_id = ")
+                    .push_bind(user_id)
+                    .push("))");
+            }
+            Conversation::Group(group_id) => {
+                builder.push("group_id = ").push_bind(group_id);
+            }
+        }
+        builder.push(" AND recalled_at IS NULL AND ");
+        push_visible_condition(&mut builder, is_group, table, user_id);
+        builder.push(" AND id IN (");
+        let mut ids = builder.separated(", ");
+        for message_id in message_ids {
+            ids.push_bind(*message_id);
+        }
+        builder.push(") ORDER BY id");
+        let messages = builder
+            .build_query_as::<SessionMessage>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(messages)
+    }
+
+    async fn get_group_thread_replies(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        root_id: MessageId,
+        cursor: MessageCursor,
+        limit: u32,
+    ) -> Result<Vec<SessionMessage>, DBError> {
+        let (forward, bound) = cursor_bound(cursor);
+        let mut messages: Vec<SessionMessage> = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE group_id = ? AND reply_to = ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id > ?
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+                group_id,
+                root_id,
+                user_id,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE group_id = ? AND reply_to = ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id < ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+                group_id,
+                root_id,
+                user_id,
+                bound,
+                limit
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
+        self.fill_reactions(true, messages.iter_mut()).await?;
         Ok(messages)
     }
-    /// 获取某群聊最新一条消息时间戳
-    async fn get_latest_timestamp_of_group(&self, group_id: u32) -> Result<Option<i64>, DBError> {
-        let ts: Option<i64> = sqlx::query_scalar!(
+    /// 获取某群聊最新一条消息时间戳（以 user_id 的视角过滤）
+    async fn get_latest_timestamp_of_group(
+        &self,
+        user_id: u32,
+        group_id: u32,
+    ) -> Result<Option<i64>, DBError> {
+        let ts = sqlx::query_scalar!(
             r#"
             SELECT `timestamp`
             FROM ugroup_messages
             WHERE group_id = ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             ORDER BY `timestamp` DESC
             LIMIT 1
             "#,
-            group_id
+            group_id,
+            user_id
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -175,12 +825,17 @@ impl MessageDB for MysqlDB {
             r#"
             SELECT
                 m.group_id,
-                MAX(m.`timestamp`) as `timestamp`
+                MAX(m.`timestamp`) as `timestamp?`
             FROM ugroup_messages m
             JOIN group_members um ON um.group_id = m.group_id
             WHERE um.user_id = ?
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = m.id)
             GROUP BY m.group_id
             "#,
+            user_id,
             user_id
         )
         .fetch_all(&self.pool)
@@ -188,7 +843,7 @@ impl MessageDB for MysqlDB {
 
         Ok(result
             .into_iter()
-            .filter_map(|row| row.timestamp.map(|ts| (row.group_id, ts)))
+            .filter_map(|r| r.timestamp.map(|ts| (r.group_id, ts)))
             .collect())
     }
     /// 当前用户所有群聊中最新的一条消息的时间戳（全局最大）
@@ -198,44 +853,54 @@ impl MessageDB for MysqlDB {
     ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-            SELECT MAX(m.`timestamp`)
+            SELECT MAX(m.`timestamp`) AS `timestamp?`
             FROM ugroup_messages m
             JOIN group_members gm ON m.group_id = gm.group_id
             WHERE gm.user_id = ?
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = m.id)
             "#,
+            user_id,
             user_id
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(ts)
     }
     /// 某个群某时间之后的消息
     async fn get_group_messages_after_timestamp(
         &self,
+        user_id: u32,
         group_id: u32,
         after: i64,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        let msgs = sqlx::query_as!(
-            SessionMessage,
+        let mut msgs: Vec<SessionMessage> = sqlx::query!(
             r#"
-            SELECT
-                id as "message_id!",
-                sender_id as "sender_id!",
-                `timestamp`,
-                message_type as "message_type: MessageType",
-                message as "message!"
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
             FROM ugroup_messages
             WHERE group_id = ?
-            AND `timestamp` > ?
+                AND `timestamp` > ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             ORDER BY `timestamp` ASC
             "#,
             group_id,
-            after
+            after,
+            user_id
         )
         .fetch_all(&self.pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(|r| session_message!(r))
+        .collect();
+        self.fill_reactions(true, msgs.iter_mut()).await?;
 
         Ok(msgs)
     }
@@ -245,43 +910,38 @@ impl MessageDB for MysqlDB {
         user_id: u32,
         after: i64,
     ) -> Result<Vec<IdMessagePair>, DBError> {
-        let rows = sqlx::query_as!(
-            GroupSessionMessage,
+        let mut rows: Vec<IdMessagePair> = sqlx::query!(
             r#"
-            SELECT
-                m.id as `message_id!`,
-                m.group_id as `group_id!`,
-                m.sender_id as `sender_id!`,
-                m.`timestamp`,
-                m.message_type as `message_type: MessageType`,
-                m.message as `message!`
-            FROM ugroup_messages m
-            JOIN group_members um ON m.group_id = um.group_id
-            WHERE um.user_id = ?
-            AND m.`timestamp` > ?
-            ORDER BY m.`timestamp` ASC
+            SELECT group_id, id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE group_id IN (SELECT group_id FROM group_members WHERE user_id = ?)
+                AND `timestamp` > ?
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+            ORDER BY `timestamp` ASC
             "#,
             user_id,
-            after
+            after,
+            user_id
         )
         .fetch_all(&self.pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(|r| IdMessagePair {
+            id: r.group_id,
+            message: session_message!(r),
+        })
+        .collect();
+        self.fill_reactions(true, rows.iter_mut().map(|r| &mut r.message))
+            .await?;
 
-        Ok(rows
-            .into_iter()
-            .map(|r| IdMessagePair {
-                id: r.group_id,
-                message: SessionMessage {
-                    message_id: r.message_id,
-                    sender_id: r.sender_id,
-                    timestamp: r.timestamp,
-                    message_type: r.message_type,
-                    message: r.message,
-                },
-            })
-            .collect())
+        Ok(rows)
     }
-    /// 获取与某个用户的最后一条私聊消息时间戳
+    /// 获取与某个用户的最后一条私聊消息时间戳（以 user1_id 的视角过滤）
     async fn get_latest_timestamp_with_user(
         &self,
         user1_id: u32,
@@ -289,19 +949,22 @@ impl MessageDB for MysqlDB {
     ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-        SELECT MAX(`timestamp`)
-        FROM messages
-        WHERE (sender_id = ? AND receiver_id = ?)
-           OR (sender_id = ? AND receiver_id = ?)
-        "#,
+            SELECT MAX(`timestamp`) AS `timestamp?`
+            FROM messages
+            WHERE ((sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            "#,
             user1_id,
             user2_id,
             user2_id,
+            user1_id,
             user1_id
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(ts)
     }
@@ -312,16 +975,21 @@ impl MessageDB for MysqlDB {
     ) -> Result<HashMap<u32, i64>, DBError> {
         let rows = sqlx::query!(
             r#"
-        SELECT
-            CASE
-                WHEN sender_id = ? THEN receiver_id
-                ELSE sender_id
-            END as peer_id,
-            MAX(`timestamp`) as `timestamp`
-        FROM messages
-        WHERE sender_id = ? OR receiver_id = ?
-        GROUP BY peer_id
-        "#,
+            SELECT
+                CASE
+                    WHEN sender_id = ? THEN receiver_id
+                    ELSE sender_id
+                END as peer_id,
+                MAX(`timestamp`) as `timestamp?`
+            FROM messages
+            WHERE (sender_id = ? OR receiver_id = ?)
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            GROUP BY peer_id
+            "#,
+            user_id,
             user_id,
             user_id,
             user_id
@@ -331,7 +999,7 @@ impl MessageDB for MysqlDB {
 
         Ok(rows
             .into_iter()
-            .filter_map(|row| row.timestamp.map(|ts| (row.peer_id, ts)))
+            .filter_map(|r| r.timestamp.map(|ts| (r.peer_id, ts)))
             .collect())
     }
     /// 获取当前用户所有私聊中最新的一条消息时间戳（全局最大）
@@ -341,16 +1009,20 @@ impl MessageDB for MysqlDB {
     ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-        SELECT MAX(`timestamp`) as `timestamp`
-        FROM messages
-        WHERE sender_id = ? OR receiver_id = ?
-        "#,
+            SELECT MAX(`timestamp`) AS `timestamp?`
+            FROM messages
+            WHERE (sender_id = ? OR receiver_id = ?)
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            "#,
+            user_id,
             user_id,
             user_id
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(ts)
     }
@@ -361,28 +1033,33 @@ impl MessageDB for MysqlDB {
         user2_id: u32,
         after: i64,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        let rows = sqlx::query_as!(
-            SessionMessage,
+        let mut rows: Vec<SessionMessage> = sqlx::query!(
             r#"
-        SELECT
-            id as "message_id!",
-            sender_id as "sender_id!",
-            `timestamp`,
-            message_type as "message_type: MessageType",
-            message as "message!"
-        FROM messages
-        WHERE ((sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?))
-          AND `timestamp` > ?
-        ORDER BY `timestamp` ASC
-        "#,
+            SELECT id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM messages
+            WHERE ((sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?))
+                AND `timestamp` > ?
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            ORDER BY `timestamp` ASC
+            "#,
             user1_id,
             user2_id,
             user2_id,
             user1_id,
-            after
+            after,
+            user1_id
         )
         .fetch_all(&self.pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(|r| session_message!(r))
+        .collect();
+        self.fill_reactions(false, rows.iter_mut()).await?;
 
         Ok(rows)
     }
@@ -392,41 +1069,1015 @@ impl MessageDB for MysqlDB {
         user_id: u32,
         after: i64,
     ) -> Result<Vec<IdMessagePair>, DBError> {
-        let rows = sqlx::query!(
+        let mut rows: Vec<IdMessagePair> = sqlx::query!(
+            r#"
+            SELECT CASE
+                    WHEN sender_id = ? THEN receiver_id
+                    ELSE sender_id
+                END AS peer_id,
+                id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM messages
+            WHERE (sender_id = ? OR receiver_id = ?)
+                AND `timestamp` > ?
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            ORDER BY `timestamp` ASC
+            "#,
+            user_id,
+            user_id,
+            user_id,
+            after,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| IdMessagePair {
+            id: r.peer_id,
+            message: session_message!(r),
+        })
+        .collect();
+        self.fill_reactions(false, rows.iter_mut().map(|r| &mut r.message))
+            .await?;
+
+        Ok(rows)
+    }
+
+    async fn update_read_cursor(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        up_to: MessageId,
+    ) -> Result<Option<MessageId>, DBError> {
+        // 游标不能越过会话中最新的消息，避免提前把之后的消息标记为已读
+        let latest = match conversation {
+            Conversation::Private(peer_id) => {
+                sqlx::query_scalar!(
+                    r#"
+                SELECT MAX(id) AS latest FROM messages
+                WHERE (sender_id = ? AND receiver_id = ?) OR (sender_id = ? AND receiver_id = ?)
+                "#,
+                    user_id,
+                    peer_id,
+                    peer_id,
+                    user_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            Conversation::Group(group_id) => {
+                sqlx::query_scalar!(
+                    "SELECT MAX(id) AS latest FROM ugroup_messages WHERE group_id = ?",
+                    group_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+        };
+        let Some(latest) = latest else {
+            return Ok(None);
+        };
+
+        let now = Utc::now().timestamp_millis();
+        sqlx::query!(
+            r#"
+        INSERT INTO read_cursors (user_id, conversation_type, conversation_id, last_read_message_id, updated_at)
+        VALUES (?, ?, ?, ?, ?)
+        ON DUPLICATE KEY UPDATE
+            last_read_message_id = GREATEST(last_read_message_id, VALUES(last_read_message_id)),
+            updated_at = VALUES(updated_at)
+        "#,
+            user_id,
+            conversation.kind_str(),
+            conversation.id(),
+            up_to.min(latest),
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let cursor = sqlx::query_scalar!(
+            r#"
+        SELECT last_read_message_id FROM read_cursors
+        WHERE user_id = ? AND conversation_type = ? AND conversation_id = ?
+        "#,
+            user_id,
+            conversation.kind_str(),
+            conversation.id()
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(Some(cursor))
+    }
+
+    async fn get_unread_counts(&self, user_id: u32) -> Result<Vec<UnreadCount>, DBError> {
+        let private_rows = sqlx::query_as!(
+            UnreadRow,
             r#"
         SELECT
-            CASE
-                WHEN sender_id = ? THEN receiver_id
-                ELSE sender_id
-            END as peer_id,
-            id as "message_id!",
-            sender_id as "sender_id!",
-            `timestamp`,
-            message_type as "message_type: MessageType",
-            message as "message!"
+            m.sender_id AS conversation_id,
+            CAST(COALESCE(rc.last_read_message_id, 0) AS UNSIGNED) AS `last_read_message_id!`,
+            COUNT(*) AS unread
+        FROM messages m
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = m.receiver_id
+            AND rc.conversation_type = 'private'
+            AND rc.conversation_id = m.sender_id
+        WHERE m.receiver_id = ? AND m.id > COALESCE(rc.last_read_message_id, 0)
+            AND m.deleted_at IS NULL
+            AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+            AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = m.id)
+        GROUP BY m.sender_id, rc.last_read_message_id
+        "#,
+            user_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let group_rows = sqlx::query_as!(
+            UnreadRow,
+            r#"
+        SELECT
+            gm.group_id AS conversation_id,
+            CAST(COALESCE(rc.last_read_message_id, 0) AS UNSIGNED) AS `last_read_message_id!`,
+            COUNT(*) AS unread
+        FROM group_members gm
+        JOIN ugroup_messages m ON m.group_id = gm.group_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = gm.user_id
+            AND rc.conversation_type = 'group'
+            AND rc.conversation_id = gm.group_id
+        WHERE gm.user_id = ?
+            AND m.sender_id <> gm.user_id
+            AND m.id > COALESCE(rc.last_read_message_id, 0)
+            AND m.deleted_at IS NULL
+            AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+            AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = m.id)
+        GROUP BY gm.group_id, rc.last_read_message_id
+        "#,
+            user_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let private = private_rows.into_iter().map(|r| (Conversation::Private(r.conversation_id), r));
+        let group = group_rows.into_iter().map(|r| (Conversation::Group(r.conversation_id), r));
+        Ok(private
+            .chain(group)
+            .map(|(conversation, r)| UnreadCount {
+                conversation,
+                last_read_message_id: r.last_read_message_id,
+                unread: r.unread as u32,
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(
+        &self,
+        message_id: MessageId,
+        receiver_id: u32,
+    ) -> Result<Option<(u32, i64)>, DBError> {
+        let now = Utc::now().timestamp_millis();
+        let result = sqlx::query!(
+            r#"
+        UPDATE messages SET delivered_at = ?
+        WHERE id = ? AND receiver_id = ? AND delivered_at IS NULL
+        "#,
+            now,
+            message_id,
+            receiver_id
+        )
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let sender_id = sqlx::query_scalar!("SELECT sender_id FROM messages WHERE id = ?", message_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(Some((sender_id, now)))
+    }
+
+    async fn get_message_meta(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<MessageMeta>, DBError> {
+        let meta = if is_group {
+            sqlx::query_as!(
+                MessageMeta,
+                r#"
+        SELECT sender_id, group_id AS conversation_id, message_type AS `message_type: MessageType`, `timestamp`, recalled_at
+        FROM ugroup_messages
+        WHERE id = ? AND deleted_at IS NULL
+        "#,
+                message_id
+            )
+            .fetch_optional(&self.pool)
+            .await?
+        } else {
+            sqlx::query_as!(
+                MessageMeta,
+                r#"
+        SELECT sender_id, receiver_id AS conversation_id, message_type AS `message_type: MessageType`, `timestamp`, recalled_at
         FROM messages
-        WHERE (sender_id = ? OR receiver_id = ?) AND `timestamp` > ?
-        ORDER BY `timestamp` ASC
+        WHERE id = ? AND deleted_at IS NULL
+        "#,
+                message_id
+            )
+            .fetch_optional(&self.pool)
+            .await?
+        };
+        Ok(meta)
+    }
+
+    async fn recall_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let result = if is_group {
+            sqlx::query!(
+                r#"
+        UPDATE ugroup_messages SET message = '', attachment = NULL, recalled_at = ?
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query!(
+                r#"
+        UPDATE messages SET message = '', attachment = NULL, recalled_at = ?
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            // 已被撤回或删除（如并发的重复请求），不再重复处理
+            return Ok(None);
+        }
+        // 撤回后编辑历史中的旧内容与表情回应也一并清除
+        let conversation_type = conversation_type(is_group);
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(now))
+    }
+
+    async fn delete_message_for_everyone(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let result = if is_group {
+            sqlx::query!(
+                r#"
+        UPDATE ugroup_messages SET message = '', attachment = NULL, deleted_at = ?
+        WHERE id = ? AND deleted_at IS NULL
+        "#,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query!(
+                r#"
+        UPDATE messages SET message = '', attachment = NULL, deleted_at = ?
+        WHERE id = ? AND deleted_at IS NULL
+        "#,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            // 已被删除（如并发的重复请求），不再重复处理
+            return Ok(None);
+        }
+        // 消息已对所有人不可见，关联的编辑历史、表情回应、置顶与隐藏记录一并清除
+        let conversation_type = conversation_type(is_group);
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM pinned_messages WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM hidden_messages WHERE conversation_type = ? AND message_id = ?",
+            conversation_type,
+            message_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        if is_group {
+            sqlx::query!("DELETE FROM message_mentions WHERE message_id = ?", message_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(Some(now))
+    }
+
+    async fn hide_message(
+        &self,
+        user_id: u32,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<(), DBError> {
+        sqlx::query!(
+            r#"
+        INSERT IGNORE INTO hidden_messages (user_id, conversation_type, message_id, hidden_at)
+        VALUES (?, ?, ?, ?)
+        "#,
+            user_id,
+            conversation_type(is_group),
+            message_id,
+            Utc::now().timestamp_millis()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn edit_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        editor_id: u32,
+        message: &str,
+    ) -> Result<i64, DBError> {
+        let now = Utc::now().timestamp_millis();
+        let mut tx = self.pool.begin().await?;
+        let result = if is_group {
+            sqlx::query!(
+                r#"
+        INSERT INTO message_edits (conversation_type, message_id, editor_id, old_message, edited_at)
+        SELECT ?, id, ?, message, ? FROM ugroup_messages
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                conversation_type(is_group),
+                editor_id,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+        UPDATE ugroup_messages SET message = ?, edited_at = ?
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                message,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        } else {
+            sqlx::query!(
+                r#"
+        INSERT INTO message_edits (conversation_type, message_id, editor_id, old_message, edited_at)
+        SELECT ?, id, ?, message, ? FROM messages
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                conversation_type(is_group),
+                editor_id,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+        UPDATE messages SET message = ?, edited_at = ?
+        WHERE id = ? AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                message,
+                now,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?
+        };
+        if result.rows_affected() == 0 {
+            // 消息在校验之后被撤回或删除，丢弃已写入的编辑历史
+            return Err(DBError::NotFound);
+        }
+        tx.commit().await?;
+        Ok(now)
+    }
+
+    async fn get_message_edits(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageEdit>, DBError> {
+        let edits = sqlx::query_as!(
+            MessageEdit,
+            r#"
+        SELECT editor_id, old_message, edited_at AS `edited_at!`
+        FROM message_edits
+        WHERE conversation_type = ? AND message_id = ?
+        ORDER BY edited_at ASC, id ASC
+        "#,
+            conversation_type(is_group),
+            message_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(edits)
+    }
+
+    async fn add_reaction(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        user_id: u32,
+        emoji: &str,
+    ) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            r#"
+        INSERT IGNORE INTO message_reactions (conversation_type, message_id, user_id, emoji, created_at)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+            conversation_type(is_group),
+            message_id,
+            user_id,
+            emoji,
+            Utc::now().timestamp_millis()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn remove_reaction(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        user_id: u32,
+        emoji: &str,
+    ) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            r#"
+        DELETE FROM message_reactions
+        WHERE conversation_type = ? AND message_id = ? AND user_id = ? AND emoji = ?
         "#,
+            conversation_type(is_group),
+            message_id,
             user_id,
+            emoji
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_reaction_count(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        emoji: &str,
+    ) -> Result<u32, DBError> {
+        let count = sqlx::query_scalar!(
+            r#"
+        SELECT COUNT(*) AS count FROM message_reactions
+        WHERE conversation_type = ? AND message_id = ? AND emoji = ?
+        "#,
+            conversation_type(is_group),
+            message_id,
+            emoji
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u32)
+    }
+
+    async fn get_unread_mentions(&self, user_id: u32) -> Result<Vec<UnreadMention>, DBError> {
+        let mut rows: Vec<UnreadMention> = sqlx::query!(
+            r#"
+            SELECT group_id, id AS message_id, sender_id, `timestamp`, message_type AS `message_type: MessageType`,
+                message, attachment AS `attachment: Json<Attachment>`, edited_at, recalled_at,
+                reply_to, expires_at, forwarded_from AS `forwarded_from: Json<ForwardedFrom>`
+            FROM ugroup_messages
+            WHERE recalled_at IS NULL
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id IN (
+                    SELECT mm.message_id
+                    FROM message_mentions mm
+                    JOIN group_members gm
+                        ON gm.group_id = mm.group_id AND gm.user_id = mm.user_id
+                    LEFT JOIN read_cursors rc
+                        ON rc.user_id = mm.user_id
+                        AND rc.conversation_type = 'group'
+                        AND rc.conversation_id = mm.group_id
+                    WHERE mm.user_id = ? AND mm.message_id > COALESCE(rc.last_read_message_id, 0)
+                )
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
             user_id,
             user_id,
-            after
+            UNREAD_MENTION_LIMIT
         )
         .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| UnreadMention {
+            group_id: r.group_id,
+            message: session_message!(r),
+        })
+        .collect();
+        self.fill_reactions(true, rows.iter_mut().map(|r| &mut r.message))
+            .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_conversations(&self, user_id: u32) -> Result<Vec<ConversationSummary>, DBError> {
+        // 私聊：有过消息往来的用户与好友，各取与之最新一条可见消息的 ID
+        let private_rows = sqlx::query_as!(
+            ConversationRow,
+            r#"
+        SELECT
+            p.peer_id AS conversation_id,
+            u.username AS title,
+            u.avatar_url,
+            m.id AS `message_id?`,
+            m.sender_id AS `sender_id?`,
+            m.message_type AS `message_type?: MessageType`,
+            IF(m.message_type = 'text', LEFT(m.message, ?), '') AS preview,
+            m.`timestamp` AS `timestamp?`,
+            m.recalled_at,
+            (
+                SELECT COUNT(*) FROM messages um
+                WHERE um.sender_id = p.peer_id AND um.receiver_id = ?
+                    AND um.id > COALESCE(rc.last_read_message_id, 0)
+                    AND um.deleted_at IS NULL
+                    AND (um.expires_at IS NULL OR um.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                    AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                        WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = um.id)
+            ) AS `unread!`,
+            cs.pinned AS `pinned?: bool`,
+            cs.muted AS `muted?: bool`,
+            mt.ttl_secs AS `ttl_secs?`
+        FROM (
+            SELECT peer_id, MAX(last_id) AS last_id
+            FROM (
+                SELECT receiver_id AS peer_id, MAX(id) AS last_id
+                FROM messages
+                WHERE sender_id = ?
+                    AND messages.deleted_at IS NULL
+                    AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                    AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                        WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                GROUP BY receiver_id
+                UNION ALL
+                SELECT sender_id AS peer_id, MAX(id) AS last_id
+                FROM messages
+                WHERE receiver_id = ?
+                    AND messages.deleted_at IS NULL
+                    AND (messages.expires_at IS NULL OR messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                    AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                        WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                GROUP BY sender_id
+                UNION ALL
+                SELECT friend_id AS peer_id, NULL AS last_id
+                FROM friendships WHERE user_id = ?
+            ) peers
+            GROUP BY peer_id
+        ) p
+        JOIN users u ON u.id = p.peer_id
+        LEFT JOIN messages m ON m.id = p.last_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = ? AND rc.conversation_type = 'private' AND rc.conversation_id = p.peer_id
+        LEFT JOIN conversation_settings cs
+            ON cs.user_id = ? AND cs.conversation_type = 'private' AND cs.conversation_id = p.peer_id
+        LEFT JOIN message_ttls mt
+            ON mt.conversation_type = 'private'
+            AND mt.conversation_id = LEAST(?, p.peer_id) AND mt.peer_id = GREATEST(?, p.peer_id)
+        "#,
+            PREVIEW_LEN,
+            user_id,
+            user_id, // 未读数中的可见条件
+            user_id,
+            user_id, // 发出消息中的可见条件
+            user_id,
+            user_id, // 收到消息中的可见条件
+            user_id,
+            user_id,
+            user_id,
+            user_id,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // 群聊：所在的全部群，各取群内最新一条可见消息的 ID
+        let group_rows = sqlx::query_as!(
+            ConversationRow,
+            r#"
+        SELECT
+            g.id AS conversation_id,
+            g.name AS title,
+            g.avatar_url,
+            m.id AS `message_id?`,
+            m.sender_id AS `sender_id?`,
+            m.message_type AS `message_type?: MessageType`,
+            IF(m.message_type = 'text', LEFT(m.message, ?), '') AS preview,
+            m.`timestamp` AS `timestamp?`,
+            m.recalled_at,
+            (
+                SELECT COUNT(*) FROM ugroup_messages um
+                WHERE um.group_id = g.id AND um.sender_id <> gm.user_id
+                    AND um.id > COALESCE(rc.last_read_message_id, 0)
+                    AND um.deleted_at IS NULL
+                    AND (um.expires_at IS NULL OR um.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                    AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                        WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = um.id)
+            ) AS `unread!`,
+            cs.pinned AS `pinned?: bool`,
+            cs.muted AS `muted?: bool`,
+            mt.ttl_secs AS `ttl_secs?`
+        FROM group_members gm
+        JOIN ugroups g ON g.id = gm.group_id
+        LEFT JOIN (
+            SELECT group_id, MAX(id) AS last_id
+            FROM ugroup_messages
+            WHERE group_id IN (SELECT group_id FROM group_members WHERE user_id = ?)
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+            GROUP BY group_id
+        ) lm ON lm.group_id = gm.group_id
+        LEFT JOIN ugroup_messages m ON m.id = lm.last_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = gm.user_id AND rc.conversation_type = 'group' AND rc.conversation_id = gm.group_id
+        LEFT JOIN conversation_settings cs
+            ON cs.user_id = gm.user_id AND cs.conversation_type = 'group' AND cs.conversation_id = gm.group_id
+        LEFT JOIN message_ttls mt
+            ON mt.conversation_type = 'group' AND mt.conversation_id = gm.group_id AND mt.peer_id = 0
+        WHERE gm.user_id = ?
+        "#,
+            PREVIEW_LEN,
+            user_id, // 未读数中的可见条件
+            user_id,
+            user_id, // 最新消息中的可见条件
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let private = private_rows.into_iter().map(|r| {
+            let conversation = Conversation::Private(r.conversation_id);
+            r.into_summary(conversation)
+        });
+        let group = group_rows.into_iter().map(|r| {
+            let conversation = Conversation::Group(r.conversation_id);
+            r.into_summary(conversation)
+        });
+        Ok(private.chain(group).collect())
+    }
+
+    async fn update_conversation_settings(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        pinned: Option<bool>,
+        muted: Option<bool>,
+    ) -> Result<ConversationSettings, DBError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+        INSERT INTO conversation_settings
+            (user_id, conversation_type, conversation_id, pinned, muted, updated_at)
+        VALUES (?, ?, ?, COALESCE(?, FALSE), COALESCE(?, FALSE), ?)
+        ON DUPLICATE KEY UPDATE
+            pinned = COALESCE(?, pinned),
+            muted = COALESCE(?, muted),
+            updated_at = VALUES(updated_at)
+        "#,
+            user_id,
+            conversation.kind_str(),
+            conversation.id(),
+            pinned,
+            muted,
+            Utc::now().timestamp_millis(),
+            pinned,
+            muted
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let settings = sqlx::query_as!(
+            ConversationSettings,
+            r#"
+        SELECT pinned AS `pinned: bool`, muted AS `muted: bool` FROM conversation_settings
+        WHERE user_id = ? AND conversation_type = ? AND conversation_id = ?
+        "#,
+            user_id,
+            conversation.kind_str(),
+            conversation.id()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(settings)
+    }
+
+    async fn set_message_ttl(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        ttl_secs: Option<u32>,
+    ) -> Result<(), DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        match ttl_secs {
+            Some(ttl_secs) => {
+                sqlx::query!(
+                    r#"
+                INSERT INTO message_ttls (conversation_type, conversation_id, peer_id, ttl_secs, updated_by, updated_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+                ON DUPLICATE KEY UPDATE
+                    ttl_secs = VALUES(ttl_secs),
+                    updated_by = VALUES(updated_by),
+                    updated_at = VALUES(updated_at)
+                "#,
+                    conversation.kind_str(),
+                    conversation_id,
+                    peer_id,
+                    ttl_secs,
+                    user_id,
+                    Utc::now().timestamp_millis()
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM message_ttls WHERE conversation_type = ? AND conversation_id = ? AND peer_id = ?",
+                    conversation.kind_str(),
+                    conversation_id,
+                    peer_id
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn pin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        pinned_at: i64,
+    ) -> Result<bool, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let result = sqlx::query!(
+            r#"
+        INSERT IGNORE INTO pinned_messages (conversation_type, message_id, conversation_id, peer_id, pinned_by, pinned_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+            conversation.kind_str(),
+            message_id,
+            conversation_id,
+            peer_id,
+            user_id,
+            pinned_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unpin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<bool, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let result = sqlx::query!(
+            r#"
+        DELETE FROM pinned_messages
+        WHERE conversation_type = ? AND message_id = ? AND conversation_id = ? AND peer_id = ?
+        "#,
+            conversation.kind_str(),
+            message_id,
+            conversation_id,
+            peer_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_pinned_messages(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+    ) -> Result<Vec<PinnedMessage>, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let pinned = if conversation.is_group() {
+            sqlx::query_as!(
+                PinnedMessage,
+                r#"
+        SELECT m.id AS message_id, m.sender_id, m.message_type AS `message_type: MessageType`, m.message, m.`timestamp`, p.pinned_by, p.pinned_at
+        FROM pinned_messages p
+        JOIN ugroup_messages m ON m.id = p.message_id
+        WHERE p.conversation_type = 'group' AND p.conversation_id = ? AND p.peer_id = ?
+            AND m.recalled_at IS NULL
+            AND m.deleted_at IS NULL
+            AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+            AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                WHERE h.user_id = ? AND h.conversation_type = 'group' AND h.message_id = m.id)
+        ORDER BY p.pinned_at DESC
+        "#,
+                conversation_id,
+                peer_id,
+                user_id
+            )
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            sqlx::query_as!(
+                PinnedMessage,
+                r#"
+        SELECT m.id AS message_id, m.sender_id, m.message_type AS `message_type: MessageType`, m.message, m.`timestamp`, p.pinned_by, p.pinned_at
+        FROM pinned_messages p
+        JOIN messages m ON m.id = p.message_id
+        WHERE p.conversation_type = 'private' AND p.conversation_id = ? AND p.peer_id = ?
+            AND m.recalled_at IS NULL
+            AND m.deleted_at IS NULL
+            AND (m.expires_at IS NULL OR m.expires_at > UNIX_TIMESTAMP(NOW(3)) * 1000)
+            AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                WHERE h.user_id = ? AND h.conversation_type = 'private' AND h.message_id = m.id)
+        ORDER BY p.pinned_at DESC
+        "#,
+                conversation_id,
+                peer_id,
+                user_id
+            )
+            .fetch_all(&self.pool)
+            .await?
+        };
+        Ok(pinned)
+    }
+
+    async fn purge_expired_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<(Vec<ExpiredMessage>, Vec<ExpiredMessage>), DBError> {
+        let mut tx = self.pool.begin().await?;
+        let private = purge_expired_in(&mut tx, false, now, limit).await?;
+        let group = purge_expired_in(&mut tx, true, now, limit).await?;
+        tx.commit().await?;
+        Ok((private, group))
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: u32,
+        query: &SearchMessagesQuery,
+        limit: u32,
+    ) -> Result<Vec<(Conversation, SessionMessage)>, DBError> {
+        let mut builder: QueryBuilder<MySql> = QueryBuilder::new("");
+        if query.group_id.is_none() {
+            builder
+                .push("(SELECT 'private' AS conversation_type, IF(sender_id = ")
+                .push_bind(user_id)
+                .push(", receiver_id, sender_id) AS conversation_id, ")
+                .push(SESSION_MESSAGE_COLUMNS)
+                .push(" FROM messages WHERE recalled_at IS NULL AND ");
+            push_visible_condition(&mut builder, false, "messages", user_id);
+            builder.push(" AND ");
+            match query.peer_id {
+                Some(peer_id) => builder
+                    .push("((sender_id = ")
+                    .push_bind(user_id)
+                    .push(" AND receiver_id = ")
+                    .push_bind(peer_id)
+                    .push(") OR (sender_id = ")
+                    .push_bind(peer_id)
+                    .push(" AND receiver_id = ")
+                    .push_bind(user_id)
+                    .push("))"),
+                None => builder
+                    .push("(sender_id = ")
+                    .push_bind(user_id)
+                    .push(" OR receiver_id = ")
+                    .push_bind(user_id)
+                    .push(")"),
+            };
+            push_search_conditions(&mut builder, query);
+            builder.push(")");
+        }
+        if query.peer_id.is_none() {
+            if query.group_id.is_none() {
+                builder.push(" UNION ALL ");
+            }
+            builder
+                .push("(SELECT 'group' AS conversation_type, group_id AS conversation_id, ")
+                .push(SESSION_MESSAGE_COLUMNS)
+                .push(" FROM ugroup_messages WHERE recalled_at IS NULL AND ");
+            push_visible_condition(&mut builder, true, "ugroup_messages", user_id);
+            builder
+                .push(" AND group_id IN (SELECT group_id FROM group_members WHERE user_id = ")
+                .push_bind(user_id)
+                .push(")");
+            if let Some(group_id) = query.group_id {
+                builder.push(" AND group_id = ").push_bind(group_id);
+            }
+            push_search_conditions(&mut builder, query);
+            builder.push(")");
+        }
+        builder
+            .push(" ORDER BY `timestamp` DESC, message_id DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(query.offset);
+
+        let mut rows = builder
+            .build_query_as::<SearchRow>()
+            .fetch_all(&self.pool)
+            .await?;
+        self.fill_reactions(
+            false,
+            rows.iter_mut()
+                .filter(|r| r.conversation_type == "private")
+                .map(|r| &mut r.message),
+        )
+        .await?;
+        self.fill_reactions(
+            true,
+            rows.iter_mut()
+                .filter(|r| r.conversation_type == "group")
+                .map(|r| &mut r.message),
+        )
         .await?;
 
         Ok(rows
             .into_iter()
-            .map(|r| IdMessagePair {
-                id: r.peer_id,
-                message: SessionMessage {
-                    message_id: r.message_id,
-                    sender_id: r.sender_id,
-                    timestamp: r.timestamp,
-                    message_type: r.message_type,
-                    message: r.message,
-                },
+            .filter_map(|r| {
+                Conversation::from_parts(&r.conversation_type, r.conversation_id)
+                    .map(|conversation| (conversation, r.message))
             })
             .collect())
     }
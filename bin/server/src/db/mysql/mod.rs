@@ -3,6 +3,7 @@ mod friend;
 mod group;
 mod manager;
 mod message;
+mod scheduled;
 mod user;
 
 use crate::db::InitDB;
@@ -0,0 +1,276 @@
+use super::MysqlDB;
+use crate::db::{ScheduledMessageDB, error::DBError};
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{MySql, QueryBuilder, types::Json};
+use uchat_model::{
+    Attachment, Conversation, MessageId, MessageType, ScheduledMessage, ScheduledStatus,
+    request::ScheduleMessageRequest,
+};
+
+/// 定时消息行，conversation_type 与 conversation_id 组成发送者视角下的会话
+struct ScheduledRow {
+    id: u64,
+    sender_id: u32,
+    conversation_type: String,
+    conversation_id: u32,
+    message_type: MessageType,
+    message: String,
+    attachment: Option<Json<Attachment>>,
+    reply_to: Option<u64>,
+    mentions: Option<Json<Vec<u32>>>,
+    mention_all: bool,
+    send_at: i64,
+    created_at: i64,
+    status: ScheduledStatus,
+    message_id: Option<u64>,
+    failure_reason: Option<String>,
+}
+
+impl ScheduledRow {
+    fn into_scheduled(self) -> Option<ScheduledMessage> {
+        Some(ScheduledMessage {
+            id: self.id,
+            sender_id: self.sender_id,
+            conversation: Conversation::from_parts(&self.conversation_type, self.conversation_id)?,
+            message_type: self.message_type,
+            message: self.message,
+            attachment: self.attachment.map(|a| a.0),
+            reply_to: self.reply_to,
+            mentions: self.mentions.map(|m| m.0).unwrap_or_default(),
+            mention_all: self.mention_all,
+            send_at: self.send_at,
+            created_at: self.created_at,
+            status: self.status,
+            message_id: self.message_id,
+            failure_reason: self.failure_reason,
+        })
+    }
+}
+
+#[async_trait]
+impl ScheduledMessageDB for MysqlDB {
+    async fn add_scheduled_message(
+        &self,
+        sender_id: u32,
+        request: &ScheduleMessageRequest,
+    ) -> Result<u64, DBError> {
+        let result = sqlx::query!(
+            r#"
+        INSERT INTO scheduled_messages
+            (sender_id, conversation_type, conversation_id, message_type, message, attachment,
+             reply_to, mentions, mention_all, send_at, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+            sender_id,
+            request.conversation.kind_str(),
+            request.conversation.id(),
+            request.message_type,
+            request.message,
+            request.attachment.as_ref().map(Json),
+            request.reply_to,
+            Json(&request.mentions),
+            request.mention_all,
+            request.send_at,
+            Utc::now().timestamp_millis()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_id())
+    }
+
+    async fn get_scheduled_messages(&self, sender_id: u32) -> Result<Vec<ScheduledMessage>, DBError> {
+        let rows = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+        SELECT id, sender_id, conversation_type, conversation_id, message_type AS `message_type: MessageType`,
+            message, attachment AS `attachment: Json<Attachment>`, reply_to,
+            mentions AS `mentions: Json<Vec<u32>>`, mention_all AS `mention_all: bool`, send_at, created_at,
+            status AS `status: ScheduledStatus`, message_id AS `message_id?`, failure_reason
+        FROM scheduled_messages
+        WHERE sender_id = ?
+        ORDER BY send_at, id
+        "#,
+            sender_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().filter_map(ScheduledRow::into_scheduled).collect())
+    }
+
+    async fn get_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+    ) -> Result<Option<ScheduledMessage>, DBError> {
+        let row = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+        SELECT id, sender_id, conversation_type, conversation_id, message_type AS `message_type: MessageType`,
+            message, attachment AS `attachment: Json<Attachment>`, reply_to,
+            mentions AS `mentions: Json<Vec<u32>>`, mention_all AS `mention_all: bool`, send_at, created_at,
+            status AS `status: ScheduledStatus`, message_id AS `message_id?`, failure_reason
+        FROM scheduled_messages
+        WHERE id = ? AND sender_id = ?
+        "#,
+            id,
+            sender_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.and_then(ScheduledRow::into_scheduled))
+    }
+
+    async fn update_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+        message: Option<&str>,
+        send_at: Option<i64>,
+    ) -> Result<Option<ScheduledMessage>, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let Some(row) = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+        SELECT id, sender_id, conversation_type, conversation_id, message_type AS `message_type: MessageType`,
+            message, attachment AS `attachment: Json<Attachment>`, reply_to,
+            mentions AS `mentions: Json<Vec<u32>>`, mention_all AS `mention_all: bool`, send_at, created_at,
+            status AS `status: ScheduledStatus`, message_id AS `message_id?`, failure_reason
+        FROM scheduled_messages
+        WHERE id = ? AND sender_id = ? AND status = 'pending'
+        FOR UPDATE
+        "#,
+            id,
+            sender_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+        sqlx::query!(
+            r#"
+        UPDATE scheduled_messages
+        SET message = COALESCE(?, message), send_at = COALESCE(?, send_at)
+        WHERE id = ?
+        "#,
+            message,
+            send_at,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(row.into_scheduled().map(|mut scheduled| {
+            if let Some(message) = message {
+                scheduled.message = message.to_string();
+            }
+            if let Some(send_at) = send_at {
+                scheduled.send_at = send_at;
+            }
+            scheduled
+        }))
+    }
+
+    async fn cancel_scheduled_message(&self, sender_id: u32, id: u64) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            "DELETE FROM scheduled_messages WHERE id = ? AND sender_id = ? AND status = 'pending'",
+            id,
+            sender_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn claim_due_scheduled_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<Vec<ScheduledMessage>, DBError> {
+        let mut tx = self.pool.begin().await?;
+        // SKIP LOCKED：多个实例同时领取时互不等待，也不会领到同一条
+        let rows = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+        SELECT id, sender_id, conversation_type, conversation_id, message_type AS `message_type: MessageType`,
+            message, attachment AS `attachment: Json<Attachment>`, reply_to,
+            mentions AS `mentions: Json<Vec<u32>>`, mention_all AS `mention_all: bool`, send_at, created_at,
+            status AS `status: ScheduledStatus`, message_id AS `message_id?`, failure_reason
+        FROM scheduled_messages
+        WHERE status = 'pending' AND send_at <= ?
+        ORDER BY send_at, id
+        LIMIT ?
+        FOR UPDATE SKIP LOCKED
+        "#,
+            now,
+            limit
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder: QueryBuilder<MySql> =
+            QueryBuilder::new("UPDATE scheduled_messages SET status = 'sending', claimed_at = ");
+        builder.push_bind(now).push(" WHERE id IN (");
+        let mut ids = builder.separated(", ");
+        for row in &rows {
+            ids.push_bind(row.id);
+        }
+        builder.push(")").build().execute(&mut *tx).await?;
+        tx.commit().await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(ScheduledRow::into_scheduled)
+            .map(|mut scheduled| {
+                scheduled.status = ScheduledStatus::Sending;
+                scheduled
+            })
+            .collect())
+    }
+
+    async fn fail_stale_scheduled_messages(
+        &self,
+        claimed_before: i64,
+        reason: &str,
+    ) -> Result<u64, DBError> {
+        let result = sqlx::query!(
+            r#"
+        UPDATE scheduled_messages SET status = 'failed', failure_reason = ?
+        WHERE status = 'sending' AND claimed_at < ?
+        "#,
+            reason,
+            claimed_before
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn mark_scheduled_sent(&self, id: u64, message_id: MessageId) -> Result<(), DBError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = 'sent', message_id = ? WHERE id = ?",
+            message_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_scheduled_failed(&self, id: u64, reason: &str) -> Result<(), DBError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = 'failed', failure_reason = ? WHERE id = ?",
+            reason,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
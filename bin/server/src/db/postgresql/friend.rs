@@ -1,7 +1,7 @@
 use crate::db::FriendDB;
 use crate::db::error::DBError;
 use crate::db::postgresql::PgSqlDB;
-use crate::protocol::UserSimpleInfo;
+use uchat_model::UserSimpleInfo;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -14,7 +14,8 @@ impl FriendDB for PgSqlDB {
             "
             SELECT 
                 f.friend_id, 
-                u.username 
+                u.username,
+                u.avatar_url
             FROM 
                 friendships f
             JOIN 
@@ -35,6 +36,7 @@ impl FriendDB for PgSqlDB {
             .map(|r| UserSimpleInfo {
                 user_id: r.friend_id as u32,
                 username: r.username,
+                avatar_url: r.avatar_url.unwrap_or_default(), // 如果avatar_url为NULL，使用默认值
             })
             .collect())
     }
@@ -61,6 +63,31 @@ impl FriendDB for PgSqlDB {
         .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+        Ok(())
+    }
+    /// 直接删除好友
+    async fn delete_friendship(&self, user_id: u32, friend_id: u32) -> Result<(), DBError> {
+        let mut tx = self.pool.begin().await?;
+
+        // 删除 (user_id, friend_id) 关系
+        sqlx::query!(
+            "DELETE FROM friendships WHERE user_id = $1 AND friend_id = $2",
+            user_id as i32,
+            friend_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // 删除 (friend_id, user_id) 关系（双向删除）
+        sqlx::query!(
+            "DELETE FROM friendships WHERE user_id = $1 AND friend_id = $2",
+            friend_id as i32,
+            user_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+
         tx.commit().await?;
         Ok(())
     }
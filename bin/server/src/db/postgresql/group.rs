@@ -1,7 +1,7 @@
 use crate::db::GroupDB;
 use crate::db::error::DBError;
 use crate::db::postgresql::PgSqlDB;
-use crate::protocol::{GroupDetailedInfo, GroupSimpleInfo, UserSimpleInfo};
+use uchat_model::{GroupDetailedInfo, GroupSimpleInfo, UserSimpleInfo};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -20,6 +20,7 @@ impl GroupDB for PgSqlDB {
         Ok(row.map(|r| GroupDetailedInfo {
             group_id: r.group_id as u32,
             title: r.title,
+            pinned: Vec::new(),
         }))
     }
 
@@ -60,7 +61,8 @@ impl GroupDB for PgSqlDB {
             "
             SELECT 
                 gm.user_id, 
-                u.username 
+                u.username,
+                u.avatar_url 
             FROM 
                 group_members gm
             JOIN 
@@ -81,6 +83,7 @@ impl GroupDB for PgSqlDB {
             .map(|r| UserSimpleInfo {
                 user_id: r.user_id as u32,
                 username: r.username,
+                avatar_url: r.avatar_url.unwrap_or_default(), // 如果avatar_url可能为NULL，使用unwrap_or_default()
             })
             .collect())
     }
@@ -154,4 +157,13 @@ impl GroupDB for PgSqlDB {
 
         Ok(())
     }
+
+    /// 获取群主（创建者）ID
+    async fn get_group_creator(&self, group_id: u32) -> Result<Option<u32>, DBError> {
+        let creator_id =
+            sqlx::query_scalar!("SELECT creator_id FROM ugroups WHERE id = $1", group_id as i32)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(creator_id.map(|id| id as u32))
+    }
 }
@@ -1,22 +1,71 @@
-use std::collections::HashMap;
-
-use super::MysqlDB;
-use crate::{
-    db::{ManagerDB, MessageDB, error::DBError},
-    protocol::{GroupSessionMessage, MessageType, RoleType, SessionMessage},
-};
+use super::PgSqlDB;
+use crate::db::{ImportMessage, ManagerDB, error::DBError};
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::{Postgres, Transaction, types::Json};
+use uchat_model::{
+    Conversation, FullGroupMessage, FullPrivateMessage, ManagerUserSimpleInfo, MessageId,
+    MessageType, PreviewGroupMessage, PreviewPrivateMessage, RoleType,
+};
+
+/// 毫秒时间戳转为 TIMESTAMP 列的取值
+fn from_millis(ts: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_millis(ts).map(|t| t.naive_utc())
+}
+
+/// 查询某来源的源消息导入后在本实例中的消息ID
+async fn imported_message_id(
+    tx: &mut Transaction<'_, Postgres>,
+    source: &str,
+    conversation_type: &str,
+    source_message_id: MessageId,
+) -> Result<Option<MessageId>, DBError> {
+    let message_id = sqlx::query_scalar!(
+        r#"
+    SELECT message_id FROM imported_messages
+    WHERE source = $1 AND conversation_type = $2::TEXT::conversation_type AND source_message_id = $3
+    "#,
+        source,
+        conversation_type,
+        source_message_id as i64
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(message_id.map(|id| id as u64))
+}
 
 #[async_trait]
-impl ManagerDB for MysqlDB {
+impl ManagerDB for PgSqlDB {
+    /// 用户总数量
     async fn get_user_count(&self) -> Result<u32, DBError> {
-        let row = sqlx::query!("SELECT COUNT(id) as count FROM users")
+        let row = sqlx::query!(r#"SELECT COUNT(id) AS "count!" FROM users"#)
             .fetch_one(&self.pool)
             .await?;
         Ok(row.count as u32)
     }
+    /// 获取全部用户
+    async fn get_all_user(&self) -> Result<Vec<ManagerUserSimpleInfo>, DBError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS user_id, username, role AS "role: RoleType"
+            FROM users
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| ManagerUserSimpleInfo {
+                user_id: r.user_id as u32,
+                username: r.username,
+                role: r.role,
+            })
+            .collect())
+    }
+
+    /// 改变身份
     async fn change_user_role(&self, userid: u32, role: RoleType) -> Result<(), DBError> {
         sqlx::query!(
             r#"
@@ -24,8 +73,8 @@ impl ManagerDB for MysqlDB {
         SET role = $1
         WHERE id = $2
         "#,
-            role.to_string(),
-            user_id as i32
+            role as RoleType,
+            userid as i32
         )
         .execute(&self.pool)
         .await?;
@@ -36,65 +85,89 @@ impl ManagerDB for MysqlDB {
         &self,
         count: u32,
         offset: u32,
-    ) -> Result<Vec<RecentPrivateMessage>, DBError> {
-        let messages = sqlx::query_as::<_, RecentPrivateMessage>(
+    ) -> Result<Vec<PreviewPrivateMessage>, DBError> {
+        let rows = sqlx::query!(
             r#"
-            SELECT 
-                id,
-                sender_id,
-                sender_username,
-                receiver_id,
-                receiver_username,
-                message_type,
-                message_preview,
-                timestamp
+            SELECT
+                id AS "id!",
+                sender_id AS "sender_id!",
+                sender_username AS "sender_username!",
+                receiver_id AS "receiver_id!",
+                receiver_username AS "receiver_username!",
+                message_type AS "message_type!: MessageType",
+                message_preview AS "message_preview!",
+                (EXTRACT(EPOCH FROM "timestamp") * 1000)::BIGINT AS "timestamp!"
             FROM recent_private_messages_view
-            ORDER BY timestamp DESC
+            ORDER BY "timestamp" DESC
             LIMIT $1 OFFSET $2
             "#,
+            count as i64,
+            offset as i64
         )
-        .bind(count as i64)
-        .bind(offset as i64)
-        .fetch_all(&self.pool) // 假设你在 impl 中有 `self.pool: PgPool`
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(messages)
+        Ok(rows
+            .into_iter()
+            .map(|r| PreviewPrivateMessage {
+                id: r.id as u64,
+                sender_id: r.sender_id as u32,
+                sender_username: r.sender_username,
+                receiver_id: r.receiver_id as u32,
+                receiver_username: r.receiver_username,
+                message_type: r.message_type,
+                message_preview: r.message_preview,
+                timestamp: r.timestamp,
+            })
+            .collect())
     }
-    /// 获取某用户前N条消息
+    /// 获取某用户前N条消息(包括收发的)
     async fn get_user_recent_messages(
         &self,
         count: u32,
         offset: u32,
         user_id: u32,
-    ) -> Result<Vec<RecentPrivateMessage>, DBError> {
-        let messages = sqlx::query_as::<_, RecentPrivateMessage>(
+    ) -> Result<Vec<PreviewPrivateMessage>, DBError> {
+        let rows = sqlx::query!(
             r#"
-            SELECT 
-                id,
-                sender_id,
-                sender_username,
-                receiver_id,
-                receiver_username,
-                message_type,
-                message_preview,
-                timestamp
+            SELECT
+                id AS "id!",
+                sender_id AS "sender_id!",
+                sender_username AS "sender_username!",
+                receiver_id AS "receiver_id!",
+                receiver_username AS "receiver_username!",
+                message_type AS "message_type!: MessageType",
+                message_preview AS "message_preview!",
+                (EXTRACT(EPOCH FROM "timestamp") * 1000)::BIGINT AS "timestamp!"
             FROM recent_private_messages_view
             WHERE sender_id = $1 OR receiver_id = $1
-            ORDER BY timestamp DESC
+            ORDER BY "timestamp" DESC
             LIMIT $2 OFFSET $3
             "#,
+            user_id as i32,
+            count as i64,
+            offset as i64
         )
-        .bind(user_id as i32)
-        .bind(count as i64)
-        .bind(offset as i64)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(messages)
+        Ok(rows
+            .into_iter()
+            .map(|r| PreviewPrivateMessage {
+                id: r.id as u64,
+                sender_id: r.sender_id as u32,
+                sender_username: r.sender_username,
+                receiver_id: r.receiver_id as u32,
+                receiver_username: r.receiver_username,
+                message_type: r.message_type,
+                message_preview: r.message_preview,
+                timestamp: r.timestamp,
+            })
+            .collect())
     }
     /// 根据message_id删除某条聊天记录
-    async fn delete_private_message(&self, message_id: i32) -> Result<u64, DBError> {
-        let result = sqlx::query!("DELETE FROM messages WHERE id = $1", message_id)
+    async fn delete_private_message(&self, message_id: u64) -> Result<u64, DBError> {
+        let result = sqlx::query!("DELETE FROM messages WHERE id = $1", message_id as i32)
             .execute(&self.pool)
             .await?;
 
@@ -102,18 +175,17 @@ impl ManagerDB for MysqlDB {
     }
     /// 获取一条私聊消息
     async fn get_private_message(&self, message_id: u64) -> Result<FullPrivateMessage, DBError> {
-        let message = sqlx::query_as!(
-            FullPrivateMessage,
+        let row = sqlx::query!(
             r#"
-            SELECT 
+            SELECT
                 m.id,
                 m.sender_id,
                 s.username AS sender_username,
                 m.receiver_id,
                 r.username AS receiver_username,
-                m.message_type,
+                m.message_type AS "message_type: MessageType",
                 m.message,
-                m.timestamp
+                (EXTRACT(EPOCH FROM m."timestamp") * 1000)::BIGINT AS "timestamp!"
             FROM messages m
             JOIN users s ON m.sender_id = s.id
             JOIN users r ON m.receiver_id = r.id
@@ -124,9 +196,296 @@ impl ManagerDB for MysqlDB {
         .fetch_optional(&self.pool)
         .await?;
 
-        match message {
-            Some(m) => Ok(m),
+        match row {
+            Some(row) => Ok(FullPrivateMessage {
+                id: row.id as u64,
+                sender_id: row.sender_id as u32,
+                sender_username: row.sender_username,
+                receiver_id: row.receiver_id as u32,
+                receiver_username: row.receiver_username,
+                message_type: row.message_type,
+                message: row.message,
+                timestamp: row.timestamp,
+            }),
             None => Err(DBError::NotFound),
         }
     }
+    /// 获取服务器前N条群消息
+    async fn get_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!", group_id AS "group_id!", group_name AS "group_name!",
+                sender_id AS "sender_id!", sender_username AS "sender_username!",
+                message_type AS "message_type!: MessageType", message_preview AS "message_preview!",
+                (EXTRACT(EPOCH FROM "timestamp") * 1000)::BIGINT AS "timestamp!"
+            FROM recent_group_messages_view
+            ORDER BY "timestamp" DESC
+            LIMIT $1 OFFSET $2
+            "#,
+            count as i64,
+            offset as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| PreviewGroupMessage {
+                id: r.id as u64,
+                group_id: r.group_id as u32,
+                group_name: r.group_name,
+                sender_id: r.sender_id as u32,
+                sender_username: r.sender_username,
+                message_type: r.message_type,
+                message_preview: r.message_preview,
+                timestamp: r.timestamp,
+            })
+            .collect())
+    }
+    /// 获取某群前N条消息
+    async fn get_recent_messages_of_group(
+        &self,
+        count: u32,
+        offset: u32,
+        group_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!", group_id AS "group_id!", group_name AS "group_name!",
+                sender_id AS "sender_id!", sender_username AS "sender_username!",
+                message_type AS "message_type!: MessageType", message_preview AS "message_preview!",
+                (EXTRACT(EPOCH FROM "timestamp") * 1000)::BIGINT AS "timestamp!"
+            FROM recent_group_messages_view
+            WHERE group_id = $1
+            ORDER BY "timestamp" DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            group_id as i32,
+            count as i64,
+            offset as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| PreviewGroupMessage {
+                id: r.id as u64,
+                group_id: r.group_id as u32,
+                group_name: r.group_name,
+                sender_id: r.sender_id as u32,
+                sender_username: r.sender_username,
+                message_type: r.message_type,
+                message_preview: r.message_preview,
+                timestamp: r.timestamp,
+            })
+            .collect())
+    }
+    /// 获取某用户在各群中发送的前N条消息
+    async fn get_user_recent_group_messages(
+        &self,
+        count: u32,
+        offset: u32,
+        user_id: u32,
+    ) -> Result<Vec<PreviewGroupMessage>, DBError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!", group_id AS "group_id!", group_name AS "group_name!",
+                sender_id AS "sender_id!", sender_username AS "sender_username!",
+                message_type AS "message_type!: MessageType", message_preview AS "message_preview!",
+                (EXTRACT(EPOCH FROM "timestamp") * 1000)::BIGINT AS "timestamp!"
+            FROM recent_group_messages_view
+            WHERE sender_id = $1
+            ORDER BY "timestamp" DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            user_id as i32,
+            count as i64,
+            offset as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| PreviewGroupMessage {
+                id: r.id as u64,
+                group_id: r.group_id as u32,
+                group_name: r.group_name,
+                sender_id: r.sender_id as u32,
+                sender_username: r.sender_username,
+                message_type: r.message_type,
+                message_preview: r.message_preview,
+                timestamp: r.timestamp,
+            })
+            .collect())
+    }
+    /// 根据message_id删除某条群聊天记录
+    /// 群消息的 @ 记录随外键级联删除，其余关联记录需手动清除
+    async fn delete_group_message(&self, message_id: u64) -> Result<u64, DBError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = 'group' AND message_id = $1",
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = 'group' AND message_id = $1",
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM pinned_messages WHERE conversation_type = 'group' AND message_id = $1",
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM hidden_messages WHERE conversation_type = 'group' AND message_id = $1",
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        let result = sqlx::query!("DELETE FROM ugroup_messages WHERE id = $1", message_id as i32)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+    /// 获取一条群聊消息
+    async fn get_full_group_message(&self, message_id: u64) -> Result<FullGroupMessage, DBError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                m.id,
+                m.group_id,
+                g.name AS group_name,
+                m.sender_id,
+                s.username AS sender_username,
+                m.message_type AS "message_type: MessageType",
+                m.message,
+                (EXTRACT(EPOCH FROM m."timestamp") * 1000)::BIGINT AS "timestamp!"
+            FROM ugroup_messages m
+            JOIN ugroups g ON m.group_id = g.id
+            JOIN users s ON m.sender_id = s.id
+            WHERE m.id = $1
+            "#,
+            message_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = row.ok_or(DBError::NotFound)?;
+        Ok(FullGroupMessage {
+            id: row.id as u64,
+            group_id: row.group_id as u32,
+            group_name: row.group_name,
+            sender_id: row.sender_id as u32,
+            sender_username: row.sender_username,
+            message_type: row.message_type,
+            message: row.message,
+            timestamp: row.timestamp,
+        })
+    }
+    /// 按用户名查找用户ID
+    async fn get_user_ids_by_username(&self, username: &str) -> Result<Vec<u32>, DBError> {
+        let ids = sqlx::query_scalar!("SELECT id FROM users WHERE username = $1", username)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids.into_iter().map(|id| id as u32).collect())
+    }
+    /// 按群名查找群号
+    async fn get_group_ids_by_name(&self, name: &str) -> Result<Vec<u32>, DBError> {
+        let ids = sqlx::query_scalar!("SELECT id FROM ugroups WHERE name = $1", name)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids.into_iter().map(|id| id as u32).collect())
+    }
+    /// 在一个事务中导入一批历史消息
+    /// 导入的私聊消息视为已送达，不会再作为离线消息推送
+    async fn import_messages(
+        &self,
+        source: &str,
+        messages: &[ImportMessage],
+    ) -> Result<u32, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let mut imported = 0;
+        for message in messages {
+            let conversation_type = message.conversation.kind_str();
+            if imported_message_id(&mut tx, source, conversation_type, message.source_message_id)
+                .await?
+                .is_some()
+            {
+                continue;
+            }
+            // 被回复的消息未导入时不保留引用
+            let reply_to = match message.reply_to {
+                Some(id) => imported_message_id(&mut tx, source, conversation_type, id).await?,
+                None => None,
+            };
+            let message_id = match message.conversation {
+                Conversation::Private(receiver_id) => {
+                    sqlx::query_scalar!(
+                        r#"
+                    INSERT INTO messages (sender_id, receiver_id, message_type, message, attachment, "timestamp", delivered_at, edited_at, reply_to)
+                    VALUES ($1, $2, $3, $4, $5, $6, $6, $7, $8)
+                    RETURNING id
+                    "#,
+                        message.sender_id as i32,
+                        receiver_id as i32,
+                        message.message_type as MessageType,
+                        message.message,
+                        message.attachment.as_ref().map(Json) as _,
+                        from_millis(message.timestamp),
+                        message.edited_at.and_then(from_millis),
+                        reply_to.map(|v| v as i32)
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?
+                }
+                Conversation::Group(group_id) => {
+                    sqlx::query_scalar!(
+                        r#"
+                    INSERT INTO ugroup_messages (group_id, sender_id, message_type, message, attachment, "timestamp", edited_at, reply_to)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    RETURNING id
+                    "#,
+                        group_id as i32,
+                        message.sender_id as i32,
+                        message.message_type as MessageType,
+                        message.message,
+                        message.attachment.as_ref().map(Json) as _,
+                        from_millis(message.timestamp),
+                        message.edited_at.and_then(from_millis),
+                        reply_to.map(|v| v as i32)
+                    )
+                    .fetch_one(&mut *tx)
+                    .await?
+                }
+            };
+            sqlx::query!(
+                r#"
+            INSERT INTO imported_messages (source, conversation_type, source_message_id, message_id)
+            VALUES ($1, $2::TEXT::conversation_type, $3, $4)
+            "#,
+                source,
+                conversation_type,
+                message.source_message_id as i64,
+                message_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            imported += 1;
+        }
+        tx.commit().await?;
+
+        Ok(imported)
+    }
 }
@@ -1,216 +1,824 @@
 use std::collections::HashMap;
 
 use super::PgSqlDB;
-use crate::{
-    db::{MessageDB, error::DBError},
-    protocol::{MessageType, SessionMessage},
+use crate::db::{
+    ExpiredMessage, ForwardMessage, MessageCursor, MessageDB, MessageMeta, error::DBError,
 };
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::{Executor, Postgres, QueryBuilder, Row, Transaction, postgres::PgRow, types::Json};
+use uchat_model::{
+    Attachment, Conversation, ConversationSettings, ConversationSummary, ForwardedFrom,
+    IdMessagePair, MessageEdit, MessageId, MessagePreview, MessageType, PinnedMessage,
+    SessionMessage, UnreadCount, UnreadMention, request::SearchMessagesQuery,
+};
+
+/// SessionMessage 对应的查询列，供 QueryBuilder 动态拼接的查询使用
+const SESSION_MESSAGE_COLUMNS: &str =
+    r#"id, sender_id, "timestamp", message_type, message, attachment, edited_at, recalled_at, reply_to, expires_at, forwarded_from"#;
+
+/// 未读 @ 列表最多返回的消息条数
+const UNREAD_MENTION_LIMIT: i64 = 100;
+
+/// message_edits 等表中 conversation_type 列的取值
+fn conversation_type(is_group: bool) -> &'static str {
+    if is_group { "group" } else { "private" }
+}
+
+/// 游标对应的翻页方向与消息ID边界：向后翻页为 (true, id)，其余为 (false, id)
+/// 取最新一页等同于早于 i32::MAX 的一页；倒序查询的结果需由调用方翻转为递增
+fn cursor_bound(cursor: MessageCursor) -> (bool, i32) {
+    match cursor {
+        MessageCursor::Latest => (false, i32::MAX),
+        MessageCursor::Before(id) => (false, id.min(i32::MAX as u64) as i32),
+        MessageCursor::After(id) => (true, id.min(i32::MAX as u64) as i32),
+    }
+}
+
+/// 只保留 user 可见的消息：未被发送者删除、未到期，且未被 user 隐藏
+/// table 为消息表名或其别名，user_param 为 user 的占位符（如 $1）
+/// 静态查询中内联了相同的条件，修改时需一并调整
+fn visible_condition(is_group: bool, table: &str, user_param: &str) -> String {
+    format!(
+        "{table}.deleted_at IS NULL \
+         AND ({table}.expires_at IS NULL OR {table}.expires_at > CURRENT_TIMESTAMP) \
+         AND NOT EXISTS (SELECT 1 FROM hidden_messages h \
+         WHERE h.user_id = {user_param} AND h.conversation_type = '{}' AND h.message_id = {table}.id)",
+        conversation_type(is_group)
+    )
+}
+
+/// message_ttls、pinned_messages 中会话的键：群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+fn ttl_key(user_id: u32, conversation: Conversation) -> (u32, u32) {
+    match conversation {
+        Conversation::Private(peer_id) => (user_id.min(peer_id), user_id.max(peer_id)),
+        Conversation::Group(group_id) => (group_id, 0),
+    }
+}
+
+fn millis(t: NaiveDateTime) -> i64 {
+    t.and_utc().timestamp_millis()
+}
+
+/// 毫秒时间戳转为 TIMESTAMP 列的取值
+fn from_millis(ms: i64) -> NaiveDateTime {
+    DateTime::from_timestamp_millis(ms)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+/// 将查询宏返回的消息行转换为 SessionMessage，表情回应另行填充
+macro_rules! session_message {
+    ($row:expr) => {{
+        let row = $row;
+        SessionMessage {
+            message_id: row.id as u64,
+            sender_id: row.sender_id as u32,
+            timestamp: millis(row.timestamp),
+            message_type: row.message_type,
+            message: row.message,
+            attachment: row.attachment.map(|a| a.0),
+            edited_at: row.edited_at.map(millis),
+            recalled_at: row.recalled_at.map(millis),
+            reply_to: row.reply_to.map(|v| v as u64),
+            expires_at: row.expires_at.map(millis),
+            forwarded_from: row.forwarded_from.map(|f| f.0),
+            reactions: Vec::new(),
+        }
+    }};
+}
+
+/// 写入一条消息，返回消息的 timestamp 和 message_id
+/// 会话未设置有效期时子查询为 NULL，expires_at 随之为 NULL
+#[allow(clippy::too_many_arguments)]
+async fn insert_message<'e, E: Executor<'e, Database = Postgres>>(
+    executor: E,
+    sender: u32,
+    conversation: Conversation,
+    message_type: MessageType,
+    message: &str,
+    attachment: Option<&Attachment>,
+    reply_to: Option<MessageId>,
+    forwarded_from: Option<&ForwardedFrom>,
+) -> Result<(i64, u64), DBError> {
+    let attachment = attachment.map(Json);
+    let forwarded_from = forwarded_from.map(Json);
+    let reply_to = reply_to.map(|v| v as i32);
+    let (timestamp, id) = match conversation {
+        Conversation::Private(receiver) => {
+            let (ttl_id, ttl_peer_id) = ttl_key(sender, conversation);
+            let rec = sqlx::query!(
+                r#"
+        INSERT INTO messages (sender_id, receiver_id, message_type, message, attachment, reply_to, forwarded_from, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP + (
+            SELECT ttl_secs * INTERVAL '1 second' FROM message_ttls
+            WHERE conversation_type = 'private' AND conversation_id = $8 AND peer_id = $9
+        ))
+        RETURNING id, "timestamp" AS "timestamp!"
+        "#,
+                sender as i32,
+                receiver as i32,
+                message_type as MessageType,
+                message,
+                attachment as _,
+                reply_to,
+                forwarded_from as _,
+                ttl_id as i32,
+                ttl_peer_id as i32
+            )
+            .fetch_one(executor)
+            .await?;
+            (rec.timestamp, rec.id)
+        }
+        Conversation::Group(group_id) => {
+            let rec = sqlx::query!(
+                r#"
+        INSERT INTO ugroup_messages (group_id, sender_id, message_type, message, attachment, reply_to, forwarded_from, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, CURRENT_TIMESTAMP + (
+            SELECT ttl_secs * INTERVAL '1 second' FROM message_ttls
+            WHERE conversation_type = 'group' AND conversation_id = $1 AND peer_id = 0
+        ))
+        RETURNING id, "timestamp" AS "timestamp!"
+        "#,
+                group_id as i32,
+                sender as i32,
+                message_type as MessageType,
+                message,
+                attachment as _,
+                reply_to,
+                forwarded_from as _
+            )
+            .fetch_one(executor)
+            .await?;
+            (rec.timestamp, rec.id)
+        }
+    };
+    Ok((millis(timestamp), id as u64))
+}
+
+/// 在事务中记录群消息中被 @ 的成员，重复记录会被忽略
+async fn insert_mentions(
+    tx: &mut Transaction<'_, Postgres>,
+    group_id: u32,
+    message_id: u64,
+    user_ids: &[u32],
+) -> Result<(), DBError> {
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+    let user_ids: Vec<i32> = user_ids.iter().map(|&id| id as i32).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO message_mentions (message_id, group_id, user_id)
+        SELECT $1, $2, UNNEST($3::INTEGER[])
+        ON CONFLICT DO NOTHING
+        "#,
+        message_id as i32,
+        group_id as i32,
+        &user_ids
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// 在事务中清除一张消息表中 now 之前到期的至多 limit 条消息及其关联记录
+async fn purge_expired_in(
+    tx: &mut Transaction<'_, Postgres>,
+    is_group: bool,
+    now: NaiveDateTime,
+    limit: u32,
+) -> Result<Vec<ExpiredMessage>, DBError> {
+    let expired: Vec<ExpiredMessage> = if is_group {
+        sqlx::query!(
+            r#"
+    DELETE FROM ugroup_messages
+    WHERE id IN (
+        SELECT id FROM ugroup_messages
+        WHERE expires_at <= $1
+        ORDER BY expires_at
+        LIMIT $2
+        FOR UPDATE
+    )
+    RETURNING id, sender_id, group_id AS conversation_id
+    "#,
+            now,
+            limit as i64
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|r| ExpiredMessage {
+            message_id: r.id as u64,
+            sender_id: r.sender_id as u32,
+            conversation_id: r.conversation_id as u32,
+        })
+        .collect()
+    } else {
+        sqlx::query!(
+            r#"
+    DELETE FROM messages
+    WHERE id IN (
+        SELECT id FROM messages
+        WHERE expires_at <= $1
+        ORDER BY expires_at
+        LIMIT $2
+        FOR UPDATE
+    )
+    RETURNING id, sender_id, receiver_id AS conversation_id
+    "#,
+            now,
+            limit as i64
+        )
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|r| ExpiredMessage {
+            message_id: r.id as u64,
+            sender_id: r.sender_id as u32,
+            conversation_id: r.conversation_id as u32,
+        })
+        .collect()
+    };
+    if expired.is_empty() {
+        return Ok(expired);
+    }
+    // 群消息的 @ 记录随外键级联删除，其余关联记录需手动清除
+    let conversation_type = conversation_type(is_group);
+    let ids: Vec<i32> = expired.iter().map(|m| m.message_id as i32).collect();
+    sqlx::query!(
+        "DELETE FROM message_edits WHERE conversation_type = $1::TEXT::conversation_type AND message_id = ANY($2)",
+        conversation_type,
+        &ids
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM message_reactions WHERE conversation_type = $1::TEXT::conversation_type AND message_id = ANY($2)",
+        conversation_type,
+        &ids
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM pinned_messages WHERE conversation_type = $1::TEXT::conversation_type AND message_id = ANY($2)",
+        conversation_type,
+        &ids
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM hidden_messages WHERE conversation_type = $1::TEXT::conversation_type AND message_id = ANY($2)",
+        conversation_type,
+        &ids
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(expired)
+}
+
+/// 在 QueryBuilder 中追加可见条件，并绑定 user_id
+fn push_visible_condition(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    is_group: bool,
+    table: &str,
+    user_id: i32,
+) {
+    let condition = visible_condition(is_group, table, "?");
+    let (head, tail) = condition.split_once('?').unwrap_or((&condition, ""));
+    builder.push(head).push_bind(user_id).push(tail);
+}
+
+/// 会话列表中消息预览的最大字符数
+const PREVIEW_LEN: i32 = 50;
+
+/// 中文全文检索配置（基于 zhparser 分词），由 tools/init_postgresql.py 创建
+const SEARCH_CONFIG: &str = "chinese_zh";
+
+/// 追加搜索关键词与过滤条件（不含会话范围），时间参数为毫秒时间戳
+fn push_search_conditions(builder: &mut QueryBuilder<'_, Postgres>, query: &SearchMessagesQuery) {
+    builder
+        .push(format!(" AND to_tsvector('{SEARCH_CONFIG}', message) @@ plainto_tsquery('{SEARCH_CONFIG}', "))
+        .push_bind(query.q.clone())
+        .push(")");
+    if let Some(sender_id) = query.sender_id {
+        builder.push(" AND sender_id = ").push_bind(sender_id as i32);
+    }
+    if let Some(start) = query.start {
+        builder
+            .push(r#" AND "timestamp" >= to_timestamp("#)
+            .push_bind(start as f64 / 1000.0)
+            .push(")");
+    }
+    if let Some(end) = query.end {
+        builder
+            .push(r#" AND "timestamp" < to_timestamp("#)
+            .push_bind(end as f64 / 1000.0)
+            .push(")");
+    }
+    if let Some(message_type) = query.message_type {
+        builder.push(" AND message_type = ").push_bind(message_type);
+    }
+}
+
+/// 由 SESSION_MESSAGE_COLUMNS 查询出的行构造 SessionMessage（表情回应另行填充）
+fn session_message_from_row(r: &PgRow) -> SessionMessage {
+    SessionMessage {
+        message_id: r.get::<i32, _>("id") as u64,
+        message_type: r.get("message_type"),
+        sender_id: r.get::<i32, _>("sender_id") as u32,
+        message: r.get("message"),
+        timestamp: millis(r.get("timestamp")),
+        attachment: r.get::<Option<Json<Attachment>>, _>("attachment").map(|a| a.0),
+        edited_at: r.get::<Option<NaiveDateTime>, _>("edited_at").map(millis),
+        recalled_at: r.get::<Option<NaiveDateTime>, _>("recalled_at").map(millis),
+        reply_to: r.get::<Option<i32>, _>("reply_to").map(|v| v as u64),
+        expires_at: r.get::<Option<NaiveDateTime>, _>("expires_at").map(millis),
+        forwarded_from: r
+            .get::<Option<Json<ForwardedFrom>>, _>("forwarded_from")
+            .map(|f| f.0),
+        reactions: Vec::new(),
+    }
+}
+
+/// 会话列表的一行：对方用户或群的信息、最后一条消息、未读数与个人设置
+struct ConversationRow {
+    conversation_id: i32,
+    title: String,
+    avatar_url: Option<String>,
+    message_id: Option<i32>,
+    sender_id: Option<i32>,
+    message_type: Option<MessageType>,
+    preview: Option<String>,
+    timestamp: Option<NaiveDateTime>,
+    recalled_at: Option<NaiveDateTime>,
+    unread: i64,
+    pinned: Option<bool>,
+    muted: Option<bool>,
+    ttl_secs: Option<i32>,
+}
+
+impl ConversationRow {
+    fn into_summary(self, conversation: Conversation) -> ConversationSummary {
+        let last_message = match (self.message_id, self.sender_id, self.message_type) {
+            (Some(message_id), Some(sender_id), Some(message_type)) => Some(MessagePreview {
+                message_id: message_id as u64,
+                sender_id: sender_id as u32,
+                message_type,
+                message: self.preview.unwrap_or_default(),
+                recalled: self.recalled_at.is_some(),
+            }),
+            _ => None,
+        };
+        ConversationSummary {
+            conversation,
+            title: self.title,
+            avatar_url: self.avatar_url,
+            last_message,
+            last_timestamp: self.timestamp.map(millis),
+            unread: self.unread as u32,
+            pinned: self.pinned.unwrap_or(false),
+            muted: self.muted.unwrap_or(false),
+            message_ttl_secs: self.ttl_secs.map(|v| v as u32),
+        }
+    }
+}
+
+/// 未读数统计行，conversation_id 为对方用户 ID 或群号
+struct UnreadRow {
+    conversation_id: i32,
+    last_read_message_id: i32,
+    unread: i64,
+}
+
+
+/// 置顶消息查询行
+struct PinnedRow {
+    id: i32,
+    sender_id: i32,
+    message_type: MessageType,
+    message: String,
+    timestamp: NaiveDateTime,
+    pinned_by: i32,
+    pinned_at: NaiveDateTime,
+}
 
 #[async_trait]
 impl MessageDB for PgSqlDB {
-    /// 添加私聊信息聊天记录，返回消息的自增 ID
+    /// 添加私聊信息聊天记录，返回消息的时间戳（毫秒）和 message_id
+    #[allow(clippy::too_many_arguments)]
     async fn add_message(
         &self,
         sender: u32,
         receiver: u32,
         message_type: MessageType,
         message: &str,
-    ) -> Result<u64, DBError> {
-        let rec = sqlx::query!(
-            r#"
-            INSERT INTO messages (sender_id, receiver_id, message_type, message)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id
-            "#,
-            sender as i32,
-            receiver as i32,
-            message_type.to_string(), // 传字符串
-            message
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
+    ) -> Result<(i64, u64), DBError> {
+        insert_message(
+            &self.pool,
+            sender,
+            Conversation::Private(receiver),
+            message_type,
+            message,
+            attachment,
+            reply_to,
+            forwarded_from,
         )
-        .fetch_one(&self.pool)
-        .await?;
-
-        Ok(rec.id as u64)
+        .await
     }
 
-    /// 添加离线消息记录
-    async fn add_offline_message(
-        &self,
-        receiver_id: u32,
-        is_group: bool,
-        message_id: Option<u64>,
-        group_message_id: Option<u64>,
-    ) -> Result<(), DBError> {
-        sqlx::query!(
-            "INSERT INTO offline_messages (receiver_id, is_group, message_id, group_message_id)
-            VALUES ($1, $2, $3, $4)",
-            receiver_id as i32,
-            is_group,
-            message_id.map(|v| v as i32),
-            group_message_id.map(|v| v as i32)
-        )
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    /// 添加群聊信息聊天记录
+    /// 添加群聊信息聊天记录，被 @ 的成员与消息在同一事务中写入
+    #[allow(clippy::too_many_arguments)]
     async fn add_group_message(
         &self,
         group_id: u32,
         sender: u32,
+        message_type: MessageType,
         message: &str,
-    ) -> Result<u64, DBError> {
-        let rec = sqlx::query!(
-            r#"
-            INSERT INTO ugroup_messages (group_id, sender_id, message)
-            VALUES ($1, $2, $3)
-            RETURNING id
-            "#,
-            group_id as i32,
-            sender as i32,
-            message
+        attachment: Option<&Attachment>,
+        reply_to: Option<MessageId>,
+        forwarded_from: Option<&ForwardedFrom>,
+        mentions: &[u32],
+    ) -> Result<(i64, u64), DBError> {
+        let mut tx = self.pool.begin().await?;
+        let (timestamp, message_id) = insert_message(
+            &mut *tx,
+            sender,
+            Conversation::Group(group_id),
+            message_type,
+            message,
+            attachment,
+            reply_to,
+            forwarded_from,
         )
-        .fetch_one(&self.pool)
         .await?;
+        insert_mentions(&mut tx, group_id, message_id, mentions).await?;
+        tx.commit().await?;
 
-        Ok(rec.id as u64)
+        Ok((timestamp, message_id))
+    }
+
+    /// 在一个事务中写入全部转发消息
+    async fn add_forwarded_messages(
+        &self,
+        sender: u32,
+        target: Conversation,
+        messages: &[ForwardMessage],
+    ) -> Result<Vec<(i64, u64)>, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let mut stored = Vec::with_capacity(messages.len());
+        for forward in messages {
+            let row = insert_message(
+                &mut *tx,
+                sender,
+                target,
+                forward.message_type,
+                &forward.message,
+                forward.attachment.as_ref(),
+                None,
+                Some(&forward.forwarded_from),
+            )
+            .await?;
+            stored.push(row);
+        }
+        tx.commit().await?;
+        Ok(stored)
     }
 
     /// 获取私聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
     async fn get_messages(
         &self,
         sender: u32,
         receiver: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        // 每页显示的消息数
-        let limit = 30;
-        // 计算要偏移的数量
-        let offset_rows = offset * limit;
-
-        // 不再使用 DATE_FORMAT，而是直接查询原始 timestamp 列
-        let messages = sqlx::query!(
-            r#"
-            SELECT 
-                sender_id,
-                timestamp,
-                message
+        let (forward, bound) = cursor_bound(cursor);
+        let messages = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
             FROM messages
-            WHERE 
-                (sender_id = $1 AND receiver_id = $2)
-                OR 
-                (sender_id = $3 AND receiver_id = $4)
-            ORDER BY timestamp ASC
-            LIMIT $5
-            OFFSET $6
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                AND id > $3
+            ORDER BY id ASC
+            LIMIT $4
             "#,
-            sender as i32,
-            receiver as i32,
-            receiver as i32,
-            sender as i32,
-            limit as i32,
-            offset_rows as i32
-        )
-        .fetch_all(&self.pool)
-        .await?
-        .into_iter()
-        .filter_map(|row| {
-            row.timestamp.map(|ts| SessionMessage {
-                sender_id: row.sender_id as u32,
-                timestamp: ts,
-                message: row.message,
-            })
-        })
-        .collect();
-
+                sender as i32,
+                receiver as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM messages
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                AND id < $3
+            ORDER BY id DESC
+            LIMIT $4
+            "#,
+                sender as i32,
+                receiver as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
         Ok(messages)
     }
 
     /// 获取群聊聊天记录
-    /// 返回值为元组，元组的第一个元素是发送者的id，第二个元素是timestap，第三个元素是消息内容
-    /// offset是消息分组，一组消息30条，0代表最近的30条，1代表30-60条，以此类推
     async fn get_group_messages(
         &self,
+        user_id: u32,
         group_id: u32,
-        offset: u32,
+        cursor: MessageCursor,
+        limit: u32,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        let limit = 30;
-        let offset_rows = offset * limit;
-
-        // 使用 query_as! 时，需要把表里的 `timestamp` 原样返回
-        let rows = sqlx::query!(
-            r#"
-            SELECT
-                sender_id,
-                timestamp,
-                message
+        let (forward, bound) = cursor_bound(cursor);
+        let messages = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
             FROM ugroup_messages
             WHERE group_id = $1
-            ORDER BY timestamp ASC
-            LIMIT $2
-            OFFSET $3
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $2 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id > $3
+            ORDER BY id ASC
+            LIMIT $4
+            "#,
+                group_id as i32,
+                user_id as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id = $1
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $2 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id < $3
+            ORDER BY id DESC
+            LIMIT $4
+            "#,
+                group_id as i32,
+                user_id as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
+        Ok(messages)
+    }
+    async fn get_group_message(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        message_id: MessageId,
+    ) -> Result<Option<SessionMessage>, DBError> {
+        let message = sqlx::query!(
+            r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id = $1 AND id = $2
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             "#,
             group_id as i32,
-            limit as i64,
-            offset_rows as i64
+            message_id as i32,
+            user_id as i32
         )
-        .fetch_all(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
+        Ok(message.map(|r| session_message!(r)))
+    }
 
-        let messages = rows
+    async fn get_messages_by_ids(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_ids: &[MessageId],
+    ) -> Result<Vec<SessionMessage>, DBError> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<i32> = message_ids.iter().map(|id| *id as i32).collect();
+        let messages = match conversation {
+            Conversation::Private(peer_id) => sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM messages
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND recalled_at IS NULL
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                AND id = ANY($3)
+            ORDER BY id
+            "#,
+                user_id as i32,
+                peer_id as i32,
+                &ids
+            )
+            .fetch_all(&self.pool)
+            .await?
             .into_iter()
-            .filter_map(|row| {
-                row.timestamp.map(|ts| SessionMessage {
-                    sender_id: row.sender_id as u32,
-                    timestamp: ts,
-                    message: row.message,
-                })
-            })
-            .collect();
+            .map(|r| session_message!(r))
+            .collect(),
+            Conversation::Group(group_id) => sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id = $2
+                AND recalled_at IS NULL
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id = ANY($3)
+            ORDER BY id
+            "#,
+                user_id as i32,
+                group_id as i32,
+                &ids
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect(),
+        };
+        Ok(messages)
+    }
 
+    async fn get_group_thread_replies(
+        &self,
+        user_id: u32,
+        group_id: u32,
+        root_id: MessageId,
+        cursor: MessageCursor,
+        limit: u32,
+    ) -> Result<Vec<SessionMessage>, DBError> {
+        let (forward, bound) = cursor_bound(cursor);
+        let messages = if forward {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id = $1 AND reply_to = $2
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id > $4
+            ORDER BY id ASC
+            LIMIT $5
+            "#,
+                group_id as i32,
+                root_id as i32,
+                user_id as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| session_message!(r))
+            .collect()
+        } else {
+            sqlx::query!(
+                r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id = $1 AND reply_to = $2
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+                AND id < $4
+            ORDER BY id DESC
+            LIMIT $5
+            "#,
+                group_id as i32,
+                root_id as i32,
+                user_id as i32,
+                bound,
+                limit as i64
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|r| session_message!(r))
+            .collect()
+        };
         Ok(messages)
     }
-    /// 获取某群聊最新一条消息时间戳
+
+    /// 获取某群聊最新一条消息时间戳（以 user_id 的视角过滤）
     async fn get_latest_timestamp_of_group(
         &self,
+        user_id: u32,
         group_id: u32,
-    ) -> Result<Option<NaiveDateTime>, DBError> {
-        let ts: Option<NaiveDateTime> = sqlx::query_scalar!(
+    ) -> Result<Option<i64>, DBError> {
+        let ts = sqlx::query_scalar!(
             r#"
-            SELECT "timestamp" as "timestamp: NaiveDateTime"
+            SELECT MAX("timestamp") AS "timestamp?"
             FROM ugroup_messages
             WHERE group_id = $1
-            ORDER BY "timestamp" DESC
-            LIMIT 1
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $2 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             "#,
-            group_id as i32
+            group_id as i32,
+            user_id as i32
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
-        Ok(ts)
+        Ok(ts.map(millis))
     }
     /// 用户加入群聊的所有的群消息最后的时间戳
     async fn get_latest_timestamps_of_all_groups(
         &self,
         user_id: u32,
-    ) -> Result<HashMap<u32, NaiveDateTime>, DBError> {
-        let result = sqlx::query!(
+    ) -> Result<HashMap<u32, i64>, DBError> {
+        let rows = sqlx::query!(
             r#"
             SELECT
                 m.group_id,
-                MAX(m."timestamp") as "timestamp: NaiveDateTime"
+                MAX(m."timestamp") AS "timestamp?"
             FROM ugroup_messages m
             JOIN group_members um ON um.group_id = m.group_id
             WHERE um.user_id = $1
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = m.id)
             GROUP BY m.group_id
             "#,
             user_id as i32
@@ -218,151 +826,146 @@ impl MessageDB for PgSqlDB {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(result
+        Ok(rows
             .into_iter()
-            .filter_map(|row| row.timestamp.map(|ts| (row.group_id as u32, ts)))
+            .filter_map(|r| r.timestamp.map(|ts| (r.group_id as u32, millis(ts))))
             .collect())
     }
     /// 当前用户所有群聊中最新的一条消息的时间戳（全局最大）
     async fn get_latest_timestamp_of_all_group_messages(
         &self,
         user_id: u32,
-    ) -> Result<Option<NaiveDateTime>, DBError> {
+    ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-            SELECT MAX(m."timestamp") as "timestamp: NaiveDateTime"
+            SELECT MAX(m."timestamp") AS "timestamp?"
             FROM ugroup_messages m
             JOIN group_members gm ON m.group_id = gm.group_id
             WHERE gm.user_id = $1
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = m.id)
             "#,
             user_id as i32
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
-        Ok(ts)
+        Ok(ts.map(millis))
     }
     /// 某个群某时间之后的消息
     async fn get_group_messages_after_timestamp(
         &self,
+        user_id: u32,
         group_id: u32,
-        after: NaiveDateTime,
+        after: i64,
     ) -> Result<Vec<SessionMessage>, DBError> {
-        let rows = sqlx::query!(
+        let messages = sqlx::query!(
             r#"
-            SELECT
-                sender_id,
-                "timestamp" as "timestamp: NaiveDateTime",
-                message
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
             FROM ugroup_messages
             WHERE group_id = $1
-            AND "timestamp" > $2
+                AND "timestamp" > $2
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
             ORDER BY "timestamp" ASC
             "#,
             group_id as i32,
-            after
+            from_millis(after),
+            user_id as i32
         )
         .fetch_all(&self.pool)
-        .await?;
-
-        let msgs = rows
-            .into_iter()
-            .filter_map(|row| {
-                row.timestamp.map(|ts| SessionMessage {
-                    sender_id: row.sender_id as u32,
-                    timestamp: ts,
-                    message: row.message,
-                })
-            })
-            .collect();
+        .await?
+        .into_iter()
+        .map(|r| session_message!(r))
+        .collect();
 
-        Ok(msgs)
+        Ok(messages)
     }
     // 当前用户所有群某时间之后的消息
     async fn get_all_group_messages_after_timestamp(
         &self,
         user_id: u32,
-        after: NaiveDateTime,
-    ) -> Result<Vec<(u32, SessionMessage)>, DBError> {
+        after: i64,
+    ) -> Result<Vec<IdMessagePair>, DBError> {
         let rows = sqlx::query!(
             r#"
-            SELECT
-                m.group_id,
-                m.sender_id,
-                m."timestamp" as "timestamp: NaiveDateTime",
-                m.message
-            FROM ugroup_messages m
-            JOIN group_members um ON m.group_id = um.group_id
-            WHERE um.user_id = $1
-            AND m."timestamp" > $2
-            ORDER BY m."timestamp" ASC
+            SELECT group_id, id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM ugroup_messages
+            WHERE group_id IN (SELECT group_id FROM group_members WHERE user_id = $1)
+                AND "timestamp" > $2
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+            ORDER BY "timestamp" ASC
             "#,
             user_id as i32,
-            after
+            from_millis(after)
         )
         .fetch_all(&self.pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(|r| IdMessagePair {
+            id: r.group_id as u32,
+            message: session_message!(r),
+        })
+        .collect();
 
-        Ok(rows
-            .into_iter()
-            .filter_map(|row| {
-                row.timestamp.map(|ts| {
-                    (
-                        row.group_id as u32,
-                        SessionMessage {
-                            sender_id: row.sender_id as u32,
-                            timestamp: ts,
-                            message: row.message,
-                        },
-                    )
-                })
-            })
-            .collect())
+        Ok(rows)
     }
-    /// 获取与某个用户的最后一条私聊消息时间戳
+    /// 获取与某个用户的最后一条私聊消息时间戳（以 user1_id 的视角过滤）
     async fn get_latest_timestamp_with_user(
         &self,
         user1_id: u32,
         user2_id: u32,
-    ) -> Result<Option<NaiveDateTime>, DBError> {
+    ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-        SELECT MAX("timestamp") as "timestamp: NaiveDateTime"
-        FROM messages
-        WHERE (sender_id = $1 AND receiver_id = $2)
-           OR (sender_id = $3 AND receiver_id = $4)
-        "#,
+            SELECT MAX("timestamp") AS "timestamp?"
+            FROM messages
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            "#,
             user1_id as i32,
-            user2_id as i32,
-            user2_id as i32,
-            user1_id as i32
+            user2_id as i32
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
-        Ok(ts)
+        Ok(ts.map(millis))
     }
     /// 获取当前用户所有私聊会话的最后时间戳（按对方用户 ID 映射）
     async fn get_latest_timestamps_of_all_private_chats(
         &self,
         user_id: u32,
-    ) -> Result<HashMap<u32, NaiveDateTime>, DBError> {
+    ) -> Result<HashMap<u32, i64>, DBError> {
         let rows = sqlx::query!(
             r#"
-        SELECT
-            CASE
-                WHEN sender_id = $1 THEN receiver_id
-                ELSE sender_id
-            END as peer_id,
-            MAX("timestamp") as "timestamp: NaiveDateTime"
-        FROM messages
-        WHERE sender_id = $2 OR receiver_id = $3
-        GROUP BY peer_id
-        "#,
-            user_id as i32,
-            user_id as i32,
+            SELECT
+                CASE
+                    WHEN sender_id = $1 THEN receiver_id
+                    ELSE sender_id
+                END AS "peer_id!",
+                MAX("timestamp") AS "timestamp?"
+            FROM messages
+            WHERE (sender_id = $1 OR receiver_id = $1)
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            GROUP BY 1
+            "#,
             user_id as i32
         )
         .fetch_all(&self.pool)
@@ -370,113 +973,1033 @@ impl MessageDB for PgSqlDB {
 
         Ok(rows
             .into_iter()
-            .filter_map(|row| match (row.peer_id, row.timestamp) {
-                (Some(peer_id), Some(ts)) => Some((peer_id as u32, ts)),
-                _ => None,
-            })
+            .filter_map(|r| r.timestamp.map(|ts| (r.peer_id as u32, millis(ts))))
             .collect())
     }
     /// 获取当前用户所有私聊中最新的一条消息时间戳（全局最大）
     async fn get_latest_timestamp_of_all_private_messages(
         &self,
         user_id: u32,
-    ) -> Result<Option<NaiveDateTime>, DBError> {
+    ) -> Result<Option<i64>, DBError> {
         let ts = sqlx::query_scalar!(
             r#"
-        SELECT MAX("timestamp") as "timestamp: NaiveDateTime"
-        FROM messages
-        WHERE sender_id = $1 OR receiver_id = $2
-        "#,
-            user_id as i32,
+            SELECT MAX("timestamp") AS "timestamp?"
+            FROM messages
+            WHERE (sender_id = $1 OR receiver_id = $1)
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            "#,
             user_id as i32
         )
-        .fetch_optional(&self.pool)
-        .await?
-        .flatten();
+        .fetch_one(&self.pool)
+        .await?;
 
-        Ok(ts)
+        Ok(ts.map(millis))
     }
     /// 获取与某个用户某时间之后的聊天记录（时间递增）
     async fn get_private_messages_after_timestamp(
         &self,
         user1_id: u32,
         user2_id: u32,
-        after: NaiveDateTime,
+        after: i64,
     ) -> Result<Vec<SessionMessage>, DBError> {
+        let messages = sqlx::query!(
+            r#"
+            SELECT id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM messages
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND "timestamp" > $3
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            ORDER BY "timestamp" ASC
+            "#,
+            user1_id as i32,
+            user2_id as i32,
+            from_millis(after)
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| session_message!(r))
+        .collect();
+
+        Ok(messages)
+    }
+    /// 获取所有私聊消息中某时间之后的所有聊天记录（带对方 ID）
+    async fn get_all_private_messages_after_timestamp(
+        &self,
+        user_id: u32,
+        after: i64,
+    ) -> Result<Vec<IdMessagePair>, DBError> {
         let rows = sqlx::query!(
             r#"
+            SELECT CASE
+                    WHEN sender_id = $1 THEN receiver_id
+                    ELSE sender_id
+                END AS "peer_id!",
+                id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+            FROM messages
+            WHERE (sender_id = $1 OR receiver_id = $1)
+                AND "timestamp" > $2
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+            ORDER BY "timestamp" ASC
+            "#,
+            user_id as i32,
+            from_millis(after)
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|r| IdMessagePair {
+            id: r.peer_id as u32,
+            message: session_message!(r),
+        })
+        .collect();
+
+        Ok(rows)
+    }
+
+    async fn update_read_cursor(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        up_to: MessageId,
+    ) -> Result<Option<MessageId>, DBError> {
+        // 游标不能越过会话中最新的消息，避免提前把之后的消息标记为已读
+        let latest = match conversation {
+            Conversation::Private(peer_id) => {
+                sqlx::query_scalar!(
+                    r#"
+                SELECT MAX(id) FROM messages
+                WHERE (sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1)
+                "#,
+                    user_id as i32,
+                    peer_id as i32
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            Conversation::Group(group_id) => {
+                sqlx::query_scalar!(
+                    "SELECT MAX(id) FROM ugroup_messages WHERE group_id = $1",
+                    group_id as i32
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+        };
+        let Some(latest) = latest else {
+            return Ok(None);
+        };
+
+        let last_read_message_id = sqlx::query_scalar!(
+            r#"
+        INSERT INTO read_cursors (user_id, conversation_type, conversation_id, last_read_message_id, updated_at)
+        VALUES ($1, $2::TEXT::conversation_type, $3, $4, CURRENT_TIMESTAMP)
+        ON CONFLICT (user_id, conversation_type, conversation_id) DO UPDATE SET
+            last_read_message_id = GREATEST(read_cursors.last_read_message_id, EXCLUDED.last_read_message_id),
+            updated_at = EXCLUDED.updated_at
+        RETURNING last_read_message_id
+        "#,
+            user_id as i32,
+            conversation.kind_str(),
+            conversation.id() as i32,
+            (up_to as i64).min(latest as i64) as i32
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Some(last_read_message_id as u64))
+    }
+
+    async fn get_unread_counts(&self, user_id: u32) -> Result<Vec<UnreadCount>, DBError> {
+        let private = sqlx::query_as!(
+            UnreadRow,
+            r#"
         SELECT
-            sender_id,
-            "timestamp" as "timestamp: NaiveDateTime",
-            message
+            m.sender_id AS conversation_id,
+            COALESCE(rc.last_read_message_id, 0) AS "last_read_message_id!",
+            COUNT(*) AS "unread!"
+        FROM messages m
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = m.receiver_id
+            AND rc.conversation_type = 'private'
+            AND rc.conversation_id = m.sender_id
+        WHERE m.receiver_id = $1 AND m.id > COALESCE(rc.last_read_message_id, 0)
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = m.id)
+        GROUP BY m.sender_id, rc.last_read_message_id
+        "#,
+            user_id as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let group = sqlx::query_as!(
+            UnreadRow,
+            r#"
+        SELECT
+            gm.group_id AS conversation_id,
+            COALESCE(rc.last_read_message_id, 0) AS "last_read_message_id!",
+            COUNT(*) AS "unread!"
+        FROM group_members gm
+        JOIN ugroup_messages m ON m.group_id = gm.group_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = gm.user_id
+            AND rc.conversation_type = 'group'
+            AND rc.conversation_id = gm.group_id
+        WHERE gm.user_id = $1
+            AND m.sender_id <> gm.user_id
+            AND m.id > COALESCE(rc.last_read_message_id, 0)
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = m.id)
+        GROUP BY gm.group_id, rc.last_read_message_id
+        "#,
+            user_id as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let private = private
+            .into_iter()
+            .map(|r| (Conversation::Private(r.conversation_id as u32), r));
+        let group = group
+            .into_iter()
+            .map(|r| (Conversation::Group(r.conversation_id as u32), r));
+        Ok(private
+            .chain(group)
+            .map(|(conversation, r)| UnreadCount {
+                conversation,
+                last_read_message_id: r.last_read_message_id as u64,
+                unread: r.unread as u32,
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(
+        &self,
+        message_id: MessageId,
+        receiver_id: u32,
+    ) -> Result<Option<(u32, i64)>, DBError> {
+        let rec = sqlx::query!(
+            r#"
+        UPDATE messages SET delivered_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND receiver_id = $2 AND delivered_at IS NULL
+        RETURNING sender_id, delivered_at AS "delivered_at!"
+        "#,
+            message_id as i32,
+            receiver_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(rec.map(|r| (r.sender_id as u32, millis(r.delivered_at))))
+    }
+
+    async fn get_message_meta(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<MessageMeta>, DBError> {
+        let meta = if is_group {
+            sqlx::query!(
+                r#"
+        SELECT sender_id, group_id AS conversation_id, message_type AS "message_type: MessageType",
+            "timestamp" AS "timestamp!", recalled_at
+        FROM ugroup_messages
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+                message_id as i32
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|r| MessageMeta {
+                sender_id: r.sender_id as u32,
+                conversation_id: r.conversation_id as u32,
+                message_type: r.message_type,
+                timestamp: millis(r.timestamp),
+                recalled_at: r.recalled_at.map(millis),
+            })
+        } else {
+            sqlx::query!(
+                r#"
+        SELECT sender_id, receiver_id AS conversation_id, message_type AS "message_type: MessageType",
+            "timestamp" AS "timestamp!", recalled_at
         FROM messages
-        WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $3 AND receiver_id = $4))
-          AND "timestamp" > $5
-        ORDER BY "timestamp" ASC
+        WHERE id = $1 AND deleted_at IS NULL
         "#,
-            user1_id as i32,
-            user2_id as i32,
-            user2_id as i32,
-            user1_id as i32,
-            after
+                message_id as i32
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|r| MessageMeta {
+                sender_id: r.sender_id as u32,
+                conversation_id: r.conversation_id as u32,
+                message_type: r.message_type,
+                timestamp: millis(r.timestamp),
+                recalled_at: r.recalled_at.map(millis),
+            })
+        };
+
+        Ok(meta)
+    }
+
+    async fn recall_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let recalled_at = if is_group {
+            sqlx::query_scalar!(
+                r#"
+        UPDATE ugroup_messages SET message = '', attachment = NULL, recalled_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND recalled_at IS NULL AND deleted_at IS NULL
+        RETURNING recalled_at AS "recalled_at!"
+        "#,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        } else {
+            sqlx::query_scalar!(
+                r#"
+        UPDATE messages SET message = '', attachment = NULL, recalled_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND recalled_at IS NULL AND deleted_at IS NULL
+        RETURNING recalled_at AS "recalled_at!"
+        "#,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        };
+        let Some(recalled_at) = recalled_at else {
+            // 已被撤回或删除（如并发的重复请求），不再重复处理
+            return Ok(None);
+        };
+        // 撤回后编辑历史中的旧内容与表情回应也一并清除
+        let conversation_type = conversation_type(is_group);
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(millis(recalled_at)))
+    }
+
+    async fn delete_message_for_everyone(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Option<i64>, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let deleted_at = if is_group {
+            sqlx::query_scalar!(
+                r#"
+        UPDATE ugroup_messages SET message = '', attachment = NULL, deleted_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING deleted_at AS "deleted_at!"
+        "#,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        } else {
+            sqlx::query_scalar!(
+                r#"
+        UPDATE messages SET message = '', attachment = NULL, deleted_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND deleted_at IS NULL
+        RETURNING deleted_at AS "deleted_at!"
+        "#,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        };
+        let Some(deleted_at) = deleted_at else {
+            // 已被删除（如并发的重复请求），不再重复处理
+            return Ok(None);
+        };
+        // 消息已对所有人不可见，关联的编辑历史、表情回应、置顶与隐藏记录一并清除
+        let conversation_type = conversation_type(is_group);
+        sqlx::query!(
+            "DELETE FROM message_edits WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM message_reactions WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM pinned_messages WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM hidden_messages WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2",
+            conversation_type,
+            message_id as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        if is_group {
+            sqlx::query!(
+                "DELETE FROM message_mentions WHERE message_id = $1",
+                message_id as i32
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(Some(millis(deleted_at)))
+    }
+
+    async fn hide_message(
+        &self,
+        user_id: u32,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<(), DBError> {
+        sqlx::query!(
+            r#"
+        INSERT INTO hidden_messages (user_id, conversation_type, message_id)
+        VALUES ($1, $2::TEXT::conversation_type, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+            user_id as i32,
+            conversation_type(is_group),
+            message_id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn edit_message(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        editor_id: u32,
+        message: &str,
+    ) -> Result<i64, DBError> {
+        let mut tx = self.pool.begin().await?;
+        let edited_at = if is_group {
+            sqlx::query!(
+                r#"
+        INSERT INTO message_edits (conversation_type, message_id, editor_id, old_message, edited_at)
+        SELECT 'group', id, $1, message, CURRENT_TIMESTAMP FROM ugroup_messages
+        WHERE id = $2 AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                editor_id as i32,
+                message_id as i32
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query_scalar!(
+                r#"
+        UPDATE ugroup_messages SET message = $1, edited_at = CURRENT_TIMESTAMP
+        WHERE id = $2 AND recalled_at IS NULL AND deleted_at IS NULL
+        RETURNING edited_at AS "edited_at!"
+        "#,
+                message,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        } else {
+            sqlx::query!(
+                r#"
+        INSERT INTO message_edits (conversation_type, message_id, editor_id, old_message, edited_at)
+        SELECT 'private', id, $1, message, CURRENT_TIMESTAMP FROM messages
+        WHERE id = $2 AND recalled_at IS NULL AND deleted_at IS NULL
+        "#,
+                editor_id as i32,
+                message_id as i32
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query_scalar!(
+                r#"
+        UPDATE messages SET message = $1, edited_at = CURRENT_TIMESTAMP
+        WHERE id = $2 AND recalled_at IS NULL AND deleted_at IS NULL
+        RETURNING edited_at AS "edited_at!"
+        "#,
+                message,
+                message_id as i32
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        };
+        // 消息在校验之后被撤回或删除，丢弃已写入的编辑历史
+        let edited_at = edited_at.ok_or(DBError::NotFound)?;
+        tx.commit().await?;
+        Ok(millis(edited_at))
+    }
+
+    async fn get_message_edits(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+    ) -> Result<Vec<MessageEdit>, DBError> {
+        let rows = sqlx::query!(
+            r#"
+        SELECT editor_id, old_message, edited_at AS "edited_at!"
+        FROM message_edits
+        WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2
+        ORDER BY edited_at ASC, id ASC
+        "#,
+            conversation_type(is_group),
+            message_id as i32
         )
         .fetch_all(&self.pool)
         .await?;
 
-        let messages = rows
+        Ok(rows
             .into_iter()
-            .filter_map(|row| {
-                row.timestamp.map(|ts| SessionMessage {
-                    sender_id: row.sender_id as u32,
-                    timestamp: ts,
-                    message: row.message,
-                })
+            .map(|r| MessageEdit {
+                editor_id: r.editor_id as u32,
+                old_message: r.old_message,
+                edited_at: millis(r.edited_at),
             })
-            .collect();
+            .collect())
+    }
 
-        Ok(messages)
+    async fn add_reaction(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        user_id: u32,
+        emoji: &str,
+    ) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            r#"
+        INSERT INTO message_reactions (conversation_type, message_id, user_id, emoji)
+        VALUES ($1::TEXT::conversation_type, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        "#,
+            conversation_type(is_group),
+            message_id as i32,
+            user_id as i32,
+            emoji
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
     }
-    /// 获取所有私聊消息中某时间之后的所有聊天记录（带对方 ID）
-    async fn get_all_private_messages_after_timestamp(
+
+    async fn remove_reaction(
         &self,
+        is_group: bool,
+        message_id: MessageId,
         user_id: u32,
-        after: NaiveDateTime,
-    ) -> Result<Vec<(u32, SessionMessage)>, DBError> {
+        emoji: &str,
+    ) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            r#"
+        DELETE FROM message_reactions
+        WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2 AND user_id = $3 AND emoji = $4
+        "#,
+            conversation_type(is_group),
+            message_id as i32,
+            user_id as i32,
+            emoji
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_reaction_count(
+        &self,
+        is_group: bool,
+        message_id: MessageId,
+        emoji: &str,
+    ) -> Result<u32, DBError> {
+        let count = sqlx::query_scalar!(
+            r#"
+        SELECT COUNT(*) AS "count!" FROM message_reactions
+        WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2 AND emoji = $3
+        "#,
+            conversation_type(is_group),
+            message_id as i32,
+            emoji
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u32)
+    }
+
+    async fn get_unread_mentions(&self, user_id: u32) -> Result<Vec<UnreadMention>, DBError> {
         let rows = sqlx::query!(
             r#"
-        SELECT
-            CASE
-                WHEN sender_id = $1 THEN receiver_id
-                ELSE sender_id
-            END as peer_id,
-            sender_id as "sender_id!",
-            "timestamp" as "timestamp!: NaiveDateTime",
-            message as "message!"
-        FROM messages
-        WHERE (sender_id = $2 OR receiver_id = $3) AND "timestamp" > $4
-        ORDER BY "timestamp" ASC
+            SELECT group_id, id, sender_id, "timestamp" AS "timestamp!", message_type AS "message_type: MessageType", message,
+                attachment AS "attachment: Json<Attachment>", edited_at, recalled_at, reply_to, expires_at,
+                forwarded_from AS "forwarded_from: Json<ForwardedFrom>"
+        FROM ugroup_messages
+        WHERE recalled_at IS NULL
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+            AND id IN (
+            SELECT mm.message_id
+            FROM message_mentions mm
+            JOIN group_members gm
+                ON gm.group_id = mm.group_id AND gm.user_id = mm.user_id
+            LEFT JOIN read_cursors rc
+                ON rc.user_id = mm.user_id
+                AND rc.conversation_type = 'group'
+                AND rc.conversation_id = mm.group_id
+            WHERE mm.user_id = $1 AND mm.message_id > COALESCE(rc.last_read_message_id, 0)
+        )
+        ORDER BY id DESC
+        LIMIT $2
         "#,
             user_id as i32,
+            UNREAD_MENTION_LIMIT
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| UnreadMention {
+                group_id: r.group_id as u32,
+                message: session_message!(r),
+            })
+            .collect())
+    }
+
+    async fn get_conversations(&self, user_id: u32) -> Result<Vec<ConversationSummary>, DBError> {
+        let private = sqlx::query_as!(
+            ConversationRow,
+            r#"
+        WITH peers AS (
+            SELECT peer_id, MAX(last_id) AS last_id
+            FROM (
+                SELECT receiver_id AS peer_id, MAX(id) AS last_id
+                FROM messages WHERE sender_id = $1
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                GROUP BY receiver_id
+                UNION ALL
+                SELECT sender_id AS peer_id, MAX(id) AS last_id
+                FROM messages WHERE receiver_id = $1
+                AND messages.deleted_at IS NULL
+                AND (messages.expires_at IS NULL OR messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = messages.id)
+                GROUP BY sender_id
+                UNION ALL
+                SELECT friend_id AS peer_id, NULL AS last_id
+                FROM friendships WHERE user_id = $1
+            ) t
+            GROUP BY peer_id
+        )
+        SELECT
+            p.peer_id AS "conversation_id!",
+            u.username AS title,
+            u.avatar_url,
+            m.id AS "message_id?",
+            m.sender_id AS "sender_id?",
+            m.message_type AS "message_type?: MessageType",
+            CASE WHEN m.message_type = 'text' THEN LEFT(m.message, $2) ELSE '' END AS preview,
+            m."timestamp" AS "timestamp?",
+            m.recalled_at AS "recalled_at?",
+            (
+                SELECT COUNT(*) FROM messages um
+                WHERE um.sender_id = p.peer_id AND um.receiver_id = $1
+                    AND um.id > COALESCE(rc.last_read_message_id, 0)
+                AND um.deleted_at IS NULL
+                AND (um.expires_at IS NULL OR um.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'private' AND h.message_id = um.id)
+            ) AS "unread!",
+            cs.pinned AS "pinned?",
+            cs.muted AS "muted?",
+            mt.ttl_secs AS "ttl_secs?"
+        FROM peers p
+        JOIN users u ON u.id = p.peer_id
+        LEFT JOIN messages m ON m.id = p.last_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = $1 AND rc.conversation_type = 'private' AND rc.conversation_id = p.peer_id
+        LEFT JOIN conversation_settings cs
+            ON cs.user_id = $1 AND cs.conversation_type = 'private' AND cs.conversation_id = p.peer_id
+        LEFT JOIN message_ttls mt
+            ON mt.conversation_type = 'private'
+            AND mt.conversation_id = LEAST($1, p.peer_id) AND mt.peer_id = GREATEST($1, p.peer_id)
+        "#,
             user_id as i32,
+            PREVIEW_LEN
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let group = sqlx::query_as!(
+            ConversationRow,
+            r#"
+        WITH last_group_messages AS (
+            SELECT group_id, MAX(id) AS last_id
+            FROM ugroup_messages
+            WHERE group_id IN (SELECT group_id FROM group_members WHERE user_id = $1)
+                AND ugroup_messages.deleted_at IS NULL
+                AND (ugroup_messages.expires_at IS NULL OR ugroup_messages.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = ugroup_messages.id)
+            GROUP BY group_id
+        )
+        SELECT
+            g.id AS "conversation_id!",
+            g.name AS title,
+            g.avatar_url,
+            m.id AS "message_id?",
+            m.sender_id AS "sender_id?",
+            m.message_type AS "message_type?: MessageType",
+            CASE WHEN m.message_type = 'text' THEN LEFT(m.message, $2) ELSE '' END AS preview,
+            m."timestamp" AS "timestamp?",
+            m.recalled_at AS "recalled_at?",
+            (
+                SELECT COUNT(*) FROM ugroup_messages um
+                WHERE um.group_id = g.id AND um.sender_id <> $1
+                    AND um.id > COALESCE(rc.last_read_message_id, 0)
+                AND um.deleted_at IS NULL
+                AND (um.expires_at IS NULL OR um.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $1 AND h.conversation_type = 'group' AND h.message_id = um.id)
+            ) AS "unread!",
+            cs.pinned AS "pinned?",
+            cs.muted AS "muted?",
+            mt.ttl_secs AS "ttl_secs?"
+        FROM group_members gm
+        JOIN ugroups g ON g.id = gm.group_id
+        LEFT JOIN last_group_messages lm ON lm.group_id = gm.group_id
+        LEFT JOIN ugroup_messages m ON m.id = lm.last_id
+        LEFT JOIN read_cursors rc
+            ON rc.user_id = $1 AND rc.conversation_type = 'group' AND rc.conversation_id = gm.group_id
+        LEFT JOIN conversation_settings cs
+            ON cs.user_id = $1 AND cs.conversation_type = 'group' AND cs.conversation_id = gm.group_id
+        LEFT JOIN message_ttls mt
+            ON mt.conversation_type = 'group' AND mt.conversation_id = gm.group_id AND mt.peer_id = 0
+        WHERE gm.user_id = $1
+        "#,
             user_id as i32,
-            after
+            PREVIEW_LEN
         )
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(rows
+        let private = private.into_iter().map(|r| {
+            let conversation = Conversation::Private(r.conversation_id as u32);
+            r.into_summary(conversation)
+        });
+        let group = group.into_iter().map(|r| {
+            let conversation = Conversation::Group(r.conversation_id as u32);
+            r.into_summary(conversation)
+        });
+        Ok(private.chain(group).collect())
+    }
+
+    async fn update_conversation_settings(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        pinned: Option<bool>,
+        muted: Option<bool>,
+    ) -> Result<ConversationSettings, DBError> {
+        let settings = sqlx::query_as!(
+            ConversationSettings,
+            r#"
+        INSERT INTO conversation_settings (user_id, conversation_type, conversation_id, pinned, muted)
+        VALUES ($1, $2::TEXT::conversation_type, $3, COALESCE($4, FALSE), COALESCE($5, FALSE))
+        ON CONFLICT (user_id, conversation_type, conversation_id) DO UPDATE SET
+            pinned = COALESCE($4, conversation_settings.pinned),
+            muted = COALESCE($5, conversation_settings.muted),
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING pinned AS "pinned!", muted AS "muted!"
+        "#,
+            user_id as i32,
+            conversation.kind_str(),
+            conversation.id() as i32,
+            pinned,
+            muted
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(settings)
+    }
+
+    async fn set_message_ttl(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        ttl_secs: Option<u32>,
+    ) -> Result<(), DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        match ttl_secs {
+            Some(ttl_secs) => {
+                sqlx::query!(
+                    r#"
+                INSERT INTO message_ttls (conversation_type, conversation_id, peer_id, ttl_secs, updated_by)
+                VALUES ($1::TEXT::conversation_type, $2, $3, $4, $5)
+                ON CONFLICT (conversation_type, conversation_id, peer_id) DO UPDATE SET
+                    ttl_secs = EXCLUDED.ttl_secs,
+                    updated_by = EXCLUDED.updated_by,
+                    updated_at = CURRENT_TIMESTAMP
+                "#,
+                    conversation.kind_str(),
+                    conversation_id as i32,
+                    peer_id as i32,
+                    ttl_secs as i32,
+                    user_id as i32
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "DELETE FROM message_ttls WHERE conversation_type = $1::TEXT::conversation_type AND conversation_id = $2 AND peer_id = $3",
+                    conversation.kind_str(),
+                    conversation_id as i32,
+                    peer_id as i32
+                )
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn pin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+        pinned_at: i64,
+    ) -> Result<bool, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO pinned_messages (conversation_type, message_id, conversation_id, peer_id, pinned_by, pinned_at)
+            VALUES ($1::TEXT::conversation_type, $2, $3, $4, $5, $6)
+            ON CONFLICT DO NOTHING
+            "#,
+            conversation.kind_str(),
+            message_id as i32,
+            conversation_id as i32,
+            peer_id as i32,
+            user_id as i32,
+            from_millis(pinned_at)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn unpin_message(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+        message_id: MessageId,
+    ) -> Result<bool, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM pinned_messages
+            WHERE conversation_type = $1::TEXT::conversation_type AND message_id = $2 AND conversation_id = $3 AND peer_id = $4
+            "#,
+            conversation.kind_str(),
+            message_id as i32,
+            conversation_id as i32,
+            peer_id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_pinned_messages(
+        &self,
+        user_id: u32,
+        conversation: Conversation,
+    ) -> Result<Vec<PinnedMessage>, DBError> {
+        let (conversation_id, peer_id) = ttl_key(user_id, conversation);
+        let pinned = if conversation.is_group() {
+            sqlx::query_as!(
+                PinnedRow,
+                r#"
+            SELECT m.id, m.sender_id, m.message_type AS "message_type: MessageType", m.message,
+                m."timestamp" AS "timestamp!", p.pinned_by, p.pinned_at AS "pinned_at!"
+            FROM pinned_messages p
+            JOIN ugroup_messages m ON m.id = p.message_id
+            WHERE p.conversation_type = 'group' AND p.conversation_id = $1 AND p.peer_id = $2
+                AND m.recalled_at IS NULL
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'group' AND h.message_id = m.id)
+            ORDER BY p.pinned_at DESC
+            "#,
+                conversation_id as i32,
+                peer_id as i32,
+                user_id as i32
+            )
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            sqlx::query_as!(
+                PinnedRow,
+                r#"
+            SELECT m.id, m.sender_id, m.message_type AS "message_type: MessageType", m.message,
+                m."timestamp" AS "timestamp!", p.pinned_by, p.pinned_at AS "pinned_at!"
+            FROM pinned_messages p
+            JOIN messages m ON m.id = p.message_id
+            WHERE p.conversation_type = 'private' AND p.conversation_id = $1 AND p.peer_id = $2
+                AND m.recalled_at IS NULL
+                AND m.deleted_at IS NULL
+                AND (m.expires_at IS NULL OR m.expires_at > CURRENT_TIMESTAMP)
+                AND NOT EXISTS (SELECT 1 FROM hidden_messages h
+                    WHERE h.user_id = $3 AND h.conversation_type = 'private' AND h.message_id = m.id)
+            ORDER BY p.pinned_at DESC
+            "#,
+                conversation_id as i32,
+                peer_id as i32,
+                user_id as i32
+            )
+            .fetch_all(&self.pool)
+            .await?
+        };
+        Ok(pinned
             .into_iter()
+            .map(|r| PinnedMessage {
+                message_id: r.id as u64,
+                sender_id: r.sender_id as u32,
+                message_type: r.message_type,
+                message: r.message,
+                timestamp: millis(r.timestamp),
+                pinned_by: r.pinned_by as u32,
+                pinned_at: millis(r.pinned_at),
+            })
+            .collect())
+    }
+
+    async fn purge_expired_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<(Vec<ExpiredMessage>, Vec<ExpiredMessage>), DBError> {
+        let now = from_millis(now);
+        let mut tx = self.pool.begin().await?;
+        let private = purge_expired_in(&mut tx, false, now, limit).await?;
+        let group = purge_expired_in(&mut tx, true, now, limit).await?;
+        tx.commit().await?;
+        Ok((private, group))
+    }
+
+    async fn search_messages(
+        &self,
+        user_id: u32,
+        query: &SearchMessagesQuery,
+        limit: u32,
+    ) -> Result<Vec<(Conversation, SessionMessage)>, DBError> {
+        let user_id = user_id as i32;
+        let mut builder: QueryBuilder<Postgres> = QueryBuilder::new("");
+        if query.group_id.is_none() {
+            builder
+                .push("(SELECT 'private' AS conversation_type, CASE WHEN sender_id = ")
+                .push_bind(user_id)
+                .push(" THEN receiver_id ELSE sender_id END AS conversation_id, ")
+                .push(SESSION_MESSAGE_COLUMNS)
+                .push(" FROM messages WHERE recalled_at IS NULL AND ");
+            push_visible_condition(&mut builder, false, "messages", user_id);
+            builder.push(" AND ");
+            match query.peer_id {
+                Some(peer_id) => builder
+                    .push("((sender_id = ")
+                    .push_bind(user_id)
+                    .push(" AND receiver_id = ")
+                    .push_bind(peer_id as i32)
+                    .push(") OR (sender_id = ")
+                    .push_bind(peer_id as i32)
+                    .push(" AND receiver_id = ")
+                    .push_bind(user_id)
+                    .push("))"),
+                None => builder
+                    .push("(sender_id = ")
+                    .push_bind(user_id)
+                    .push(" OR receiver_id = ")
+                    .push_bind(user_id)
+                    .push(")"),
+            };
+            push_search_conditions(&mut builder, query);
+            builder.push(")");
+        }
+        if query.peer_id.is_none() {
+            if query.group_id.is_none() {
+                builder.push(" UNION ALL ");
+            }
+            builder
+                .push("(SELECT 'group' AS conversation_type, group_id AS conversation_id, ")
+                .push(SESSION_MESSAGE_COLUMNS)
+                .push(" FROM ugroup_messages WHERE recalled_at IS NULL AND ");
+            push_visible_condition(&mut builder, true, "ugroup_messages", user_id);
+            builder
+                .push(" AND group_id IN (SELECT group_id FROM group_members WHERE user_id = ")
+                .push_bind(user_id)
+                .push(")");
+            if let Some(group_id) = query.group_id {
+                builder.push(" AND group_id = ").push_bind(group_id as i32);
+            }
+            push_search_conditions(&mut builder, query);
+            builder.push(")");
+        }
+        builder
+            .push(r#" ORDER BY "timestamp" DESC, id DESC LIMIT "#)
+            .push_bind(limit as i64)
+            .push(" OFFSET ")
+            .push_bind(query.offset as i64);
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
             .filter_map(|r| {
-                r.peer_id.map(|peer_id| {
-                    (
-                        peer_id as u32,
-                        SessionMessage {
-                            sender_id: r.sender_id as u32,
-                            timestamp: r.timestamp,
-                            message: r.message,
-                        },
-                    )
-                })
+                let kind: String = r.get("conversation_type");
+                Conversation::from_parts(&kind, r.get::<i32, _>("conversation_id") as u32)
+                    .map(|conversation| (conversation, session_message_from_row(r)))
             })
             .collect())
     }
@@ -0,0 +1,244 @@
+use super::PgSqlDB;
+use crate::db::{ScheduledMessageDB, error::DBError};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::types::Json;
+use uchat_model::{
+    Attachment, Conversation, MessageId, MessageType, ScheduledMessage, ScheduledStatus,
+    request::ScheduleMessageRequest,
+};
+
+fn millis(t: NaiveDateTime) -> i64 {
+    t.and_utc().timestamp_millis()
+}
+
+fn from_millis(ms: i64) -> NaiveDateTime {
+    DateTime::from_timestamp_millis(ms)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+/// 定时消息行，conversation_type 与 conversation_id 组成发送者视角下的会话
+struct ScheduledRow {
+    id: i32,
+    sender_id: i32,
+    conversation_type: String,
+    conversation_id: i32,
+    message_type: MessageType,
+    message: String,
+    attachment: Option<Json<Attachment>>,
+    reply_to: Option<i32>,
+    mentions: Option<Json<Vec<u32>>>,
+    mention_all: bool,
+    send_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    status: ScheduledStatus,
+    message_id: Option<i32>,
+    failure_reason: Option<String>,
+}
+
+impl ScheduledRow {
+    fn into_scheduled(self) -> Option<ScheduledMessage> {
+        Some(ScheduledMessage {
+            id: self.id as u64,
+            sender_id: self.sender_id as u32,
+            conversation: Conversation::from_parts(&self.conversation_type, self.conversation_id as u32)?,
+            message_type: self.message_type,
+            message: self.message,
+            attachment: self.attachment.map(|a| a.0),
+            reply_to: self.reply_to.map(|v| v as u64),
+            mentions: self.mentions.map(|m| m.0).unwrap_or_default(),
+            mention_all: self.mention_all,
+            send_at: millis(self.send_at),
+            created_at: millis(self.created_at),
+            status: self.status,
+            message_id: self.message_id.map(|v| v as u64),
+            failure_reason: self.failure_reason,
+        })
+    }
+}
+
+#[async_trait]
+impl ScheduledMessageDB for PgSqlDB {
+    async fn add_scheduled_message(
+        &self,
+        sender_id: u32,
+        request: &ScheduleMessageRequest,
+    ) -> Result<u64, DBError> {
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO scheduled_messages
+                (sender_id, conversation_type, conversation_id, message_type, message, attachment,
+                 reply_to, mentions, mention_all, send_at)
+            VALUES ($1, $2::TEXT::conversation_type, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id
+            "#,
+            sender_id as i32,
+            request.conversation.kind_str(),
+            request.conversation.id() as i32,
+            request.message_type as MessageType,
+            request.message,
+            request.attachment.as_ref().map(Json) as _,
+            request.reply_to.map(|v| v as i32),
+            Json(&request.mentions) as _,
+            request.mention_all,
+            from_millis(request.send_at)
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id as u64)
+    }
+
+    async fn get_scheduled_messages(&self, sender_id: u32) -> Result<Vec<ScheduledMessage>, DBError> {
+        let rows = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+            SELECT id, sender_id, conversation_type::TEXT AS "conversation_type!", conversation_id,
+            message_type AS "message_type: MessageType", message, attachment AS "attachment: Json<Attachment>",
+            reply_to, mentions AS "mentions: Json<Vec<u32>>", mention_all, send_at, created_at AS "created_at!",
+            status AS "status: ScheduledStatus", message_id, failure_reason
+            FROM scheduled_messages
+            WHERE sender_id = $1
+            ORDER BY send_at, id
+            "#,
+            sender_id as i32
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().filter_map(ScheduledRow::into_scheduled).collect())
+    }
+
+    async fn get_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+    ) -> Result<Option<ScheduledMessage>, DBError> {
+        let row = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+            SELECT id, sender_id, conversation_type::TEXT AS "conversation_type!", conversation_id,
+            message_type AS "message_type: MessageType", message, attachment AS "attachment: Json<Attachment>",
+            reply_to, mentions AS "mentions: Json<Vec<u32>>", mention_all, send_at, created_at AS "created_at!",
+            status AS "status: ScheduledStatus", message_id, failure_reason
+            FROM scheduled_messages
+            WHERE id = $1 AND sender_id = $2
+            "#,
+            id as i32,
+            sender_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.and_then(ScheduledRow::into_scheduled))
+    }
+
+    async fn update_scheduled_message(
+        &self,
+        sender_id: u32,
+        id: u64,
+        message: Option<&str>,
+        send_at: Option<i64>,
+    ) -> Result<Option<ScheduledMessage>, DBError> {
+        let row = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+            UPDATE scheduled_messages
+            SET message = COALESCE($1, message), send_at = COALESCE($2, send_at)
+            WHERE id = $3 AND sender_id = $4 AND status = 'pending'
+            RETURNING id, sender_id, conversation_type::TEXT AS "conversation_type!", conversation_id,
+            message_type AS "message_type: MessageType", message, attachment AS "attachment: Json<Attachment>",
+            reply_to, mentions AS "mentions: Json<Vec<u32>>", mention_all, send_at, created_at AS "created_at!",
+            status AS "status: ScheduledStatus", message_id, failure_reason
+            "#,
+            message,
+            send_at.map(from_millis),
+            id as i32,
+            sender_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.and_then(ScheduledRow::into_scheduled))
+    }
+
+    async fn cancel_scheduled_message(&self, sender_id: u32, id: u64) -> Result<bool, DBError> {
+        let result = sqlx::query!(
+            "DELETE FROM scheduled_messages WHERE id = $1 AND sender_id = $2 AND status = 'pending'",
+            id as i32,
+            sender_id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn claim_due_scheduled_messages(
+        &self,
+        now: i64,
+        limit: u32,
+    ) -> Result<Vec<ScheduledMessage>, DBError> {
+        // SKIP LOCKED：多个实例同时领取时互不等待，也不会领到同一条
+        let rows = sqlx::query_as!(
+            ScheduledRow,
+            r#"
+            UPDATE scheduled_messages SET status = 'sending', claimed_at = $1
+            WHERE id IN (
+                SELECT id FROM scheduled_messages
+                WHERE status = 'pending' AND send_at <= $1
+                ORDER BY send_at, id LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, sender_id, conversation_type::TEXT AS "conversation_type!", conversation_id,
+            message_type AS "message_type: MessageType", message, attachment AS "attachment: Json<Attachment>",
+            reply_to, mentions AS "mentions: Json<Vec<u32>>", mention_all, send_at, created_at AS "created_at!",
+            status AS "status: ScheduledStatus", message_id, failure_reason
+            "#,
+            from_millis(now),
+            limit as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        // RETURNING 不保证顺序
+        let mut claimed: Vec<ScheduledMessage> =
+            rows.into_iter().filter_map(ScheduledRow::into_scheduled).collect();
+        claimed.sort_by_key(|scheduled| (scheduled.send_at, scheduled.id));
+        Ok(claimed)
+    }
+
+    async fn fail_stale_scheduled_messages(
+        &self,
+        claimed_before: i64,
+        reason: &str,
+    ) -> Result<u64, DBError> {
+        let result = sqlx::query!(
+            "UPDATE scheduled_messages SET status = 'failed', failure_reason = $1 \
+             WHERE status = 'sending' AND claimed_at < $2",
+            reason,
+            from_millis(claimed_before)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn mark_scheduled_sent(&self, id: u64, message_id: MessageId) -> Result<(), DBError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = 'sent', message_id = $1 WHERE id = $2",
+            message_id as i32,
+            id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_scheduled_failed(&self, id: u64, reason: &str) -> Result<(), DBError> {
+        sqlx::query!(
+            "UPDATE scheduled_messages SET status = 'failed', failure_reason = $1 WHERE id = $2",
+            reason,
+            id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -1,11 +1,12 @@
 use super::PgSqlDB;
-use crate::{
-    db::{UserDB, error::DBError},
-    protocol::{PatchUserRequest, UpdateUserRequest, UserDetailedInfo},
-};
+use crate::db::{UserDB, error::DBError};
 use anyhow::Result;
 use async_trait::async_trait;
 use sqlx::Arguments;
+use uchat_model::{
+    RoleType, UpdateTimestamps, UserDetailedInfo,
+    request::{PatchUserRequest, UpdateUserRequest},
+};
 
 #[async_trait]
 impl UserDB for PgSqlDB {
@@ -13,13 +14,36 @@ impl UserDB for PgSqlDB {
     // async fn set_userinfo(&self, id: u32, userinfo: UserDetailedInfo) -> Result<()> {
 
     // }
+    /// 查询用户密码和身份
+    async fn get_user_password_and_role(
+        &self,
+        user_id: u32,
+    ) -> Result<(String, RoleType), DBError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT password_hash, role AS "role: RoleType"
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some(row) = row {
+            Ok((row.password_hash, row.role))
+        } else {
+            Err(DBError::NotFound)
+        }
+    }
+
     /// 查询用户密码哈希
-    async fn get_password_hash(&self, id: u32) -> Result<Option<String>, DBError> {
+    async fn get_password_hash(&self, id: u32) -> Result<String, DBError> {
         let row = sqlx::query!("SELECT password_hash FROM users WHERE id = $1", id as i32)
             .fetch_optional(&self.pool)
             .await?;
 
-        Ok(row.map(|r| r.password_hash))
+        row.map(|r| r.password_hash).ok_or(DBError::NotFound)
     }
 
     /// 更新用户密码
@@ -35,6 +59,32 @@ impl UserDB for PgSqlDB {
         Ok(())
     }
 
+    /// 获取用户的好友和群组更新时间（返回时间戳，单位：秒）
+    async fn get_update_timestamps(&self, id: u32) -> Result<UpdateTimestamps, DBError> {
+        let row = sqlx::query!(
+            "SELECT friends_updated_at, groups_updated_at FROM users WHERE id = $1",
+            id as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(DBError::NotFound)?;
+
+        let friends_ts = row
+            .friends_updated_at
+            .ok_or(DBError::NotFound)?
+            .and_utc()
+            .timestamp();
+        let groups_ts = row
+            .groups_updated_at
+            .ok_or(DBError::NotFound)?
+            .and_utc()
+            .timestamp();
+        Ok(UpdateTimestamps {
+            friends_updated_at: friends_ts,
+            groups_updated_at: groups_ts,
+        })
+    }
+
     /// 创建新用户
     async fn new_user(&self, username: &str, password_hash: &str) -> Result<u32, DBError> {
         let row = sqlx::query!(
@@ -90,7 +140,7 @@ impl UserDB for PgSqlDB {
 
         if let Some(username) = patch.username {
             sets.push(format!("username = ${}", param_index));
-            args.add(username);
+            let _ = args.add(username);
             param_index += 1;
         }
 
@@ -101,17 +151,34 @@ impl UserDB for PgSqlDB {
 
         sql.push_str(&sets.join(", "));
         sql.push_str(&format!(" WHERE id = ${}", param_index));
-        args.add(id as i32);
+        let _ = args.add(id as i32);
 
         sqlx::query_with(&sql, args).execute(&self.pool).await?;
 
         Ok(())
     }
 
+    /// 更新用户头像
+    async fn update_user_avatar(&self, id: u32, avatar_url: &str) -> Result<(), DBError> {
+        sqlx::query!(
+            "UPDATE users SET avatar_url = $1 WHERE id = $2",
+            avatar_url,
+            id as i32
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// 根据id查找用户详细信息
     async fn get_userinfo(&self, id: u32) -> Result<Option<UserDetailedInfo>, DBError> {
         let row = sqlx::query!(
-            "SELECT id AS user_id, username FROM users WHERE id = $1",
+            r#"
+            SELECT id AS user_id, username, role AS "role: RoleType", avatar_url
+            FROM users
+            WHERE id = $1
+            "#,
             id as i32
         )
         .fetch_optional(&self.pool)
@@ -120,6 +187,8 @@ impl UserDB for PgSqlDB {
         Ok(row.map(|r| UserDetailedInfo {
             user_id: r.user_id as u32,
             username: r.username,
+            role: r.role,
+            avatar_url: r.avatar_url,
         }))
     }
 }
@@ -3,7 +3,7 @@
 mod route;
 
 use crate::api::manager::Manager;
-use crate::api::request::Request;
+use crate::api::request::{Request, spawn_expire_task, spawn_schedule_task};
 use crate::config::get_config;
 use crate::db::factory::{DbType, create_database};
 #[cfg(feature = "redis-support")]
@@ -124,8 +124,13 @@ impl Server {
             db.clone(),
             sessions.clone(),
             storage.clone(),
-            cache
+            cache,
+            config.message.clone(),
         )));
+        // 后台定时清除到期的阅后即焚消息
+        spawn_expire_task(request.clone(), config.message.expire_interval_secs);
+        // 后台定时投递到期的定时消息，重启后会补发停机期间到期的消息
+        spawn_schedule_task(request.clone(), config.message.scheduler_interval_secs);
         let manager = Arc::new(Mutex::new(Manager::new(db, sessions, storage)));
         let state = AppState { request, manager };
         // 构建路由
@@ -0,0 +1,23 @@
+use crate::api::handler;
+use axum::{
+    Router,
+    routing::{get, patch, put},
+};
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/list",
+            get(handler::request::conversation::list_conversation::handle_list_conversation),
+        )
+        .route(
+            "/settings",
+            patch(
+                handler::request::conversation::settings_conversation::handle_update_conversation_settings,
+            ),
+        )
+        .route(
+            "/ttl",
+            put(handler::request::conversation::settings_conversation::handle_update_message_ttl),
+        )
+}
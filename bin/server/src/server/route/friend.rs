@@ -26,4 +26,13 @@ pub fn router() -> Router {
             "/status",
             post(handler::request::friend::status_friend::handle_get_status_by_userid),
         )
+        .route(
+            "/{friend_id}/pins",
+            get(handler::request::friend::pin_friend::handle_list_private_pins),
+        )
+        .route(
+            "/{friend_id}/pins/{message_id}",
+            post(handler::request::friend::pin_friend::handle_pin_private_message)
+                .delete(handler::request::friend::pin_friend::handle_unpin_private_message),
+        )
 }
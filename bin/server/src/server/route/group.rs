@@ -30,4 +30,13 @@ pub fn router() -> Router {
             "/members",
             get(handler::request::group::members_group::handle_members_group),
         )
+        .route(
+            "/{group_id}/pins",
+            get(handler::request::group::pin_group::handle_list_group_pins),
+        )
+        .route(
+            "/{group_id}/pins/{message_id}",
+            post(handler::request::group::pin_group::handle_pin_group_message)
+                .delete(handler::request::group::pin_group::handle_unpin_group_message),
+        )
 }
@@ -1 +1,16 @@
+use crate::api::handler::manager::message::group;
+use axum::{
+    Router,
+    routing::{delete, get},
+};
 
+pub fn router() -> Router {
+    Router::new()
+        .route("/recent", get(group::handle_get_recent_group_messages))
+        .route("/group", get(group::handle_get_recent_messages_of_group))
+        .route("/user", get(group::handle_get_user_recent_group_messages))
+        .route(
+            "/",
+            delete(group::handle_delete_group_message).get(group::handle_get_full_group_message),
+        )
+}
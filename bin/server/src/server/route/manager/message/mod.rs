@@ -1,8 +1,17 @@
 mod group;
 mod privite;
-use axum::Router;
+use crate::api::handler::manager::message::import;
+use axum::{Router, extract::DefaultBodyLimit, routing::post};
+
+/// 导入请求体的大小上限（64 MiB），导出文件较大时需拆分后分别导入
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
 
 pub fn router() -> Router {
-    Router::new().nest("/privite", privite::router())
-    // .nest("/group", group::router())
+    Router::new()
+        .nest("/privite", privite::router())
+        .nest("/group", group::router())
+        .route(
+            "/import",
+            post(import::handle_import_messages).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
 }
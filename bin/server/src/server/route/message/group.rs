@@ -24,4 +24,8 @@ pub fn router() -> Router {
             "/after",
             get(group::handle_get_all_group_messages_after_timestamp),
         )
+        .route(
+            "/{group_id}/thread/{message_id}",
+            get(group::handle_get_group_thread),
+        )
 }
@@ -1,10 +1,34 @@
 mod group;
 mod user;
 
-use axum::Router;
+use crate::api::handler::request::message::{export, forward, mention, reaction, recall, scheduled, search, unread};
+use axum::{
+    Router,
+    routing::{get, post},
+};
 
 pub fn router() -> Router {
     Router::new()
         .nest("/group", group::router())
         .nest("/user", user::router())
+        .route("/unread", get(unread::handle_get_unread_counts))
+        .route("/mentions", get(mention::handle_get_unread_mentions))
+        .route("/search", get(search::handle_search_messages))
+        .route("/recall", post(recall::handle_recall_message))
+        .route("/delete", post(recall::handle_delete_own_message))
+        .route("/edit", post(recall::handle_edit_message))
+        .route("/edits", get(recall::handle_get_message_edits))
+        .route("/forward", post(forward::handle_forward_messages))
+        .route("/export", get(export::handle_export_messages))
+        .route(
+            "/reaction",
+            post(reaction::handle_add_reaction).delete(reaction::handle_remove_reaction),
+        )
+        .route(
+            "/scheduled",
+            get(scheduled::handle_list_scheduled_messages)
+                .post(scheduled::handle_schedule_message)
+                .patch(scheduled::handle_update_scheduled_message)
+                .delete(scheduled::handle_cancel_scheduled_message),
+        )
 }
@@ -1,5 +1,6 @@
 // server/route/mod.rs
 mod authentication;
+mod conversation;
 mod friend;
 mod group;
 mod manager;
@@ -14,6 +15,7 @@ pub fn router() -> Router {
         .route("/", get(handler::handle_request))
         .route("/ping", get(handler::ping))
         .nest("/auth", authentication::router())
+        .nest("/conversation", conversation::router())
         .nest("/friend", friend::router())
         .nest("/group", group::router())
         .nest("/message", message::router())
@@ -1,4 +1,4 @@
-use super::{SenderStore, SessionManagerTrait};
+use super::{Outbound, SenderStore, SessionManagerTrait};
 use crate::session::SessionInfo;
 use async_trait::async_trait;
 use axum::extract::ws::Message;
@@ -65,13 +65,13 @@ impl SessionManagerTrait for SessionManager {
             .map(|set| set.iter().map(|r| r.key().clone()).collect())
     }
 
-    async fn register_sender(&self, session_id: &str, sender: UnboundedSender<Message>) {
+    async fn register_sender(&self, session_id: &str, sender: UnboundedSender<Outbound>) {
         self.senders.insert(session_id, sender);
     }
 
     async fn unregister_sender(&self, session_id: &str) {
         if let Some(sender) = self.senders.remove(session_id) {
-            let _ = sender.send(Message::Close(None));
+            let _ = sender.send(Message::Close(None).into());
         }
     }
 
@@ -101,15 +101,19 @@ impl SessionManagerTrait for SessionManager {
         }
     }
 
-    async fn send_to_user(&self, user_id: u32, msg: Message) {
-        if let Some(set) = self.user_index.get(&user_id) {
-            let ids: Vec<String> = set.iter().map(|r| r.key().clone()).collect();
-            self.senders.broadcast(&ids, msg);
-        }
+    async fn send_to_user(&self, user_id: u32, msg: Outbound) -> usize {
+        let Some(ids) = self
+            .user_index
+            .get(&user_id)
+            .map(|set| set.iter().map(|r| r.key().clone()).collect::<Vec<String>>())
+        else {
+            return 0;
+        };
+        self.senders.broadcast(&ids, msg)
     }
 
-    async fn send_to_session(&self, session_id: &str, msg: Message) {
-        self.senders.send(session_id, msg);
+    async fn send_to_session(&self, session_id: &str, msg: Outbound) -> bool {
+        self.senders.send(session_id, msg)
     }
 
     async fn clear_all_sessions(&self) {
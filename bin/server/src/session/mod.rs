@@ -11,10 +11,11 @@ use async_trait::async_trait;
 use axum::extract::ws::Message;
 use chrono::DateTime;
 use dashmap::DashMap;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, net::{IpAddr}, sync::Arc};
 use tokio::sync::mpsc::UnboundedSender;
-use uchat_model::{RoleType, manager::UserSessionInfo};
+use uchat_model::{RoleType, manager::UserSessionInfo, message::ServerMessage};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SessionInfo {
@@ -40,9 +41,31 @@ impl SessionInfo {
     }
 }
 
+/// 投递给单个 WebSocket 连接的出站内容
+/// 业务消息以 ServerMessage 的形式投递，由各连接按协商好的编码自行序列化
+#[derive(Debug, Clone)]
+pub enum Outbound {
+    /// 控制帧（Ping/Pong/Close）等原样发送的 WebSocket 消息
+    Raw(Message),
+    /// 业务消息，共享同一份数据，避免群发时多次 clone
+    Server(Arc<ServerMessage>),
+}
+
+impl From<Message> for Outbound {
+    fn from(msg: Message) -> Self {
+        Outbound::Raw(msg)
+    }
+}
+
+impl From<ServerMessage> for Outbound {
+    fn from(msg: ServerMessage) -> Self {
+        Outbound::Server(Arc::new(msg))
+    }
+}
+
 #[derive(Clone)]
 pub struct SenderStore {
-    inner: Arc<DashMap<String, UnboundedSender<Message>>>,
+    inner: Arc<DashMap<String, UnboundedSender<Outbound>>>,
 }
 
 impl SenderStore {
@@ -52,34 +75,44 @@ impl SenderStore {
         }
     }
 
-    pub fn insert(&self, session_id: &str, sender: UnboundedSender<Message>) {
+    pub fn insert(&self, session_id: &str, sender: UnboundedSender<Outbound>) {
         self.inner.insert(session_id.to_string(), sender);
     }
 
-    pub fn remove(&self, session_id: &str) -> Option<UnboundedSender<Message>> {
+    pub fn remove(&self, session_id: &str) -> Option<UnboundedSender<Outbound>> {
         if let Some(sender) = self.inner.get(session_id) {
             // 尝试发送关闭消息
-            let _ = sender.send(Message::Close(None));
+            let _ = sender.send(Message::Close(None).into());
         }
         // 移除并返回
         self.inner.remove(session_id).map(|(_, sender)| sender)
     }
 
-    pub fn send(&self, session_id: &str, msg: Message) {
-        if let Some(sender) = self.inner.get(session_id) {
-            let _ = sender.send(msg);
+    /// 投递到连接的发送队列，返回是否成功入队
+    /// 连接任务已退出（接收端已关闭）时移除失效的 sender
+    pub fn send(&self, session_id: &str, msg: Outbound) -> bool {
+        let sent = match self.inner.get(session_id) {
+            Some(sender) => sender.send(msg).is_ok(),
+            None => return false,
+        };
+        if !sent {
+            warn!("会话 {} 的连接已关闭，移除失效的发送通道", session_id);
+            self.inner.remove(session_id);
         }
+        sent
     }
 
-    pub fn broadcast(&self, session_ids: &[String], msg: Message) {
-        for session_id in session_ids {
-            self.send(session_id, msg.clone());
-        }
+    /// 返回成功入队的连接数
+    pub fn broadcast(&self, session_ids: &[String], msg: Outbound) -> usize {
+        session_ids
+            .iter()
+            .filter(|session_id| self.send(session_id, msg.clone()))
+            .count()
     }
 
     pub fn clear_all(&self) {
         for entry in self.inner.iter() {
-            let _ = entry.send(Message::Close(None));
+            let _ = entry.send(Message::Close(None).into());
         }
         self.inner.clear();
     }
@@ -106,12 +139,14 @@ pub trait SessionManagerTrait: Send + Sync {
     async fn register_sender(
         &self,
         session_id: &str,
-        sender: tokio::sync::mpsc::UnboundedSender<Message>,
+        sender: tokio::sync::mpsc::UnboundedSender<Outbound>,
     );
     async fn unregister_sender(&self, session_id: &str);
     async fn delete_session(&self, session_id: &str);
-    async fn send_to_user(&self, user_id: u32, msg: Message);
-    async fn send_to_session(&self, session_id: &str, msg: Message);
+    /// 发送给用户的所有连接，返回成功入队的连接数
+    async fn send_to_user(&self, user_id: u32, msg: Outbound) -> usize;
+    /// 发送给单个连接，返回是否成功入队
+    async fn send_to_session(&self, session_id: &str, msg: Outbound) -> bool;
     async fn get_all_online_users_tree(&self) -> HashMap<u32, Vec<(String, SessionInfo)>>;
     async fn clear_all_sessions(&self);
 }
@@ -6,7 +6,7 @@ use std::{collections::HashMap, sync::Arc};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::redis::SharedRedis;
-use crate::session::{SenderStore, SessionInfo, SessionManagerTrait};
+use crate::session::{Outbound, SenderStore, SessionInfo, SessionManagerTrait};
 use uchat_model::RoleType;
 
 pub struct SessionConfig {
@@ -112,13 +112,13 @@ impl SessionManagerTrait for RedisSessionManager {
         }
     }
 
-    async fn register_sender(&self, session_id: &str, sender: UnboundedSender<Message>) {
+    async fn register_sender(&self, session_id: &str, sender: UnboundedSender<Outbound>) {
         self.sender_store.insert(session_id, sender);
     }
 
     async fn unregister_sender(&self, session_id: &str) {
         if let Some(sender) = self.sender_store.remove(session_id) {
-            let _ = sender.send(Message::Close(None));
+            let _ = sender.send(Message::Close(None).into());
         }
     }
 
@@ -140,18 +140,19 @@ impl SessionManagerTrait for RedisSessionManager {
         }
     }
 
-    async fn send_to_user(&self, user_id: u32, msg: Message) {
-        if let Ok(session_ids) = self
+    async fn send_to_user(&self, user_id: u32, msg: Outbound) -> usize {
+        match self
             .redis
             .smembers(&format!("user_sessions:{}", user_id))
             .await
         {
-            self.sender_store.broadcast(&session_ids, msg);
+            Ok(session_ids) => self.sender_store.broadcast(&session_ids, msg),
+            Err(_) => 0,
         }
     }
 
-    async fn send_to_session(&self, session_id: &str, msg: Message) {
-        self.sender_store.send(session_id, msg);
+    async fn send_to_session(&self, session_id: &str, msg: Outbound) -> bool {
+        self.sender_store.send(session_id, msg)
     }
 
     async fn clear_all_sessions(&self) {
@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use aws_sdk_s3::config::Builder as S3ConfigBuilder;
 use aws_sdk_s3::config::Credentials;
 use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use aws_sdk_s3::{Client, config::Region};
 use log::info;
 
@@ -64,6 +65,88 @@ impl ObjectStorage for MinioStorage {
         Ok(self.get_url(object_path))
     }
 
+    async fn create_multipart_upload(
+        &self,
+        object_path: &str,
+        content_type: &str,
+    ) -> Result<String> {
+        let resp = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_path)
+            .content_type(content_type)
+            .send()
+            .await
+            .context("failed to create multipart upload in MinIO (S3)")?;
+        resp.upload_id.context("MinIO (S3) returned no upload id")
+    }
+
+    async fn upload_part(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let resp = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(object_path)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .context("failed to upload part to MinIO (S3)")?;
+        resp.e_tag.context("MinIO (S3) returned no ETag for part")
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        parts: &[(i32, String)],
+    ) -> Result<String> {
+        let parts = parts
+            .iter()
+            .map(|(part_number, e_tag)| {
+                CompletedPart::builder()
+                    .part_number(*part_number)
+                    .e_tag(e_tag)
+                    .build()
+            })
+            .collect();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_path)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .context("failed to complete multipart upload in MinIO (S3)")?;
+
+        Ok(self.get_url(object_path))
+    }
+
+    async fn abort_multipart_upload(&self, object_path: &str, upload_id: &str) -> Result<()> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(object_path)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .context("failed to abort multipart upload in MinIO (S3)")?;
+        Ok(())
+    }
+
     async fn delete(&self, object_path: &str) -> Result<()> {
         self.client
             .delete_object()
@@ -56,6 +56,37 @@ pub trait ObjectStorage: Send + Sync {
         content_type: &str,
     ) -> anyhow::Result<String>;
 
+    /// 开始分片上传，返回 upload_id
+    async fn create_multipart_upload(
+        &self,
+        object_path: &str,
+        content_type: &str,
+    ) -> anyhow::Result<String>;
+
+    /// 上传第 part_number 个分片（从 1 开始，除最后一片外不小于 5 MiB），返回分片的 ETag
+    async fn upload_part(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> anyhow::Result<String>;
+
+    /// 按 (part_number, ETag) 合并分片，返回公开 URL
+    async fn complete_multipart_upload(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+        parts: &[(i32, String)],
+    ) -> anyhow::Result<String>;
+
+    /// 放弃分片上传，清理已上传的分片
+    async fn abort_multipart_upload(
+        &self,
+        object_path: &str,
+        upload_id: &str,
+    ) -> anyhow::Result<()>;
+
     /// 删除文件
     async fn delete(&self, object_path: &str) -> anyhow::Result<()>;
 
@@ -0,0 +1,169 @@
+// src/codec.rs
+//! tokio_util::codec 适配：让同一套二进制帧协议可以跑在 TCP / Unix socket 等字节流上
+//! 用法：Framed::new(stream, ServerCodec::default())
+use bytes::BytesMut;
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::frame::{
+    DEFAULT_MAX_FRAGMENT_LEN, FrameCodec, FrameError, FrameReassembler, MAX_DECOMPRESSED_LEN,
+    VERSION, frame_len,
+};
+use crate::message::{ClientMessage, ServerMessage};
+
+/// 服务端使用：解码 ClientMessage，编码 ServerMessage
+pub type ServerCodec = UchatCodec<ClientMessage, ServerMessage>;
+/// 客户端使用：解码 ServerMessage，编码 ClientMessage
+pub type ClientCodec = UchatCodec<ServerMessage, ClientMessage>;
+
+/// 字节流编解码器：
+/// - 解码端按帧边界切帧，分片帧交给内部的 FrameReassembler 重组，压缩帧透明解压
+/// - 编码端超过 max_fragment_len 的消息自动分片，frag_id 在本连接内自增
+/// - 编码端按 version 打帧（默认 VERSION），握手协商出旧版本时用 with_version 指定
+pub struct UchatCodec<D: FrameCodec, E: FrameCodec> {
+    reassembler: FrameReassembler<D>,
+    max_payload_len: usize,
+    max_fragment_len: usize,
+    version: u8,
+    next_frag_id: u32,
+    _encode: PhantomData<E>,
+}
+
+impl<D: FrameCodec, E: FrameCodec> Default for UchatCodec<D, E> {
+    fn default() -> Self {
+        Self::new(FrameReassembler::default(), MAX_DECOMPRESSED_LEN, DEFAULT_MAX_FRAGMENT_LEN)
+    }
+}
+
+impl<D: FrameCodec, E: FrameCodec> UchatCodec<D, E> {
+    pub fn new(reassembler: FrameReassembler<D>, max_payload_len: usize, max_fragment_len: usize) -> Self {
+        Self { reassembler, max_payload_len, max_fragment_len, version: VERSION, next_frag_id: 0, _encode: PhantomData }
+    }
+
+    /// 指定编码使用的帧版本（通常为握手协商结果）
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+impl<D: FrameCodec, E: FrameCodec> Decoder for UchatCodec<D, E> {
+    type Item = D;
+    type Error = FrameError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<D>, FrameError> {
+        // 一次读取可能包含多个帧，分片帧未收齐时继续切下一个
+        while let Some(len) = frame_len(src, self.max_payload_len)? {
+            let frame = src.split_to(len);
+            if let Some(msg) = self.reassembler.push(&frame)? {
+                return Ok(Some(msg));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<D: FrameCodec, E: FrameCodec> Encoder<E> for UchatCodec<D, E> {
+    type Error = FrameError;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), FrameError> {
+        let frames = item.to_frames_versioned(self.version, self.max_fragment_len, self.next_frag_id)?;
+        if frames.len() > 1 {
+            self.next_frag_id = self.next_frag_id.wrapping_add(1);
+        }
+        for frame in frames {
+            dst.extend_from_slice(&frame);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageType;
+
+    fn send_message(len: usize) -> ClientMessage {
+        ClientMessage::SendMessage {
+            receiver: 2,
+            message: "消息".repeat(len),
+            client_msg_id: Some("c-1".to_string()),
+            message_type: MessageType::Text,
+            attachment: None,
+            reply_to: Some(7),
+        }
+    }
+
+    fn assert_send_message(msg: ClientMessage, len: usize) {
+        match msg {
+            ClientMessage::SendMessage { receiver, message, client_msg_id, reply_to, .. } => {
+                assert_eq!(receiver, 2);
+                assert_eq!(message, "消息".repeat(len));
+                assert_eq!(client_msg_id.as_deref(), Some("c-1"));
+                assert_eq!(reply_to, Some(7));
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn codec_roundtrip_with_partial_reads() {
+        let mut client = ClientCodec::default();
+        let mut server = ServerCodec::default();
+        let mut wire = BytesMut::new();
+        client.encode(send_message(10), &mut wire).unwrap();
+        client.encode(send_message(1000), &mut wire).unwrap();
+
+        let mut src = BytesMut::new();
+        let mut decoded = Vec::new();
+        for chunk in wire.chunks(7) {
+            src.extend_from_slice(chunk);
+            while let Some(msg) = server.decode(&mut src).unwrap() {
+                decoded.push(msg);
+            }
+        }
+        assert!(src.is_empty());
+        assert_eq!(decoded.len(), 2);
+        let mut decoded = decoded.into_iter();
+        assert_send_message(decoded.next().unwrap(), 10);
+        assert_send_message(decoded.next().unwrap(), 1000);
+    }
+
+    #[test]
+    fn codec_fragments_large_messages() {
+        let mut client = ClientCodec::new(FrameReassembler::default(), MAX_DECOMPRESSED_LEN, 256);
+        let mut server = ServerCodec::default();
+        let mut wire = BytesMut::new();
+        // 不可压缩的长消息会被拆成多个分片帧
+        let noisy: String = (0..20_000u32).map(|i| char::from_u32(0x4e00 + (i * 7919) % 0x5000).unwrap()).collect();
+        let msg = ClientMessage::SendMessage {
+            receiver: 2,
+            message: noisy.clone(),
+            client_msg_id: None,
+            message_type: MessageType::Text,
+            attachment: None,
+            reply_to: None,
+        };
+        client.encode(msg, &mut wire).unwrap();
+        assert!(wire.len() > 256 * 2);
+
+        match server.decode(&mut wire).unwrap() {
+            Some(ClientMessage::SendMessage { message, .. }) => assert_eq!(message, noisy),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(wire.is_empty());
+        assert!(server.decode(&mut wire).unwrap().is_none());
+    }
+
+    #[test]
+    fn codec_encodes_negotiated_version() {
+        let mut client = ClientCodec::new(FrameReassembler::default(), MAX_DECOMPRESSED_LEN, 256).with_version(1);
+        let mut server = ServerCodec::default();
+        let mut wire = BytesMut::new();
+        client.encode(send_message(1000), &mut wire).unwrap();
+        // v1 不分片：整条消息只有一个帧
+        assert_eq!(wire[2], 1);
+        assert_eq!(frame_len(&wire, MAX_DECOMPRESSED_LEN).unwrap(), Some(wire.len()));
+        assert_send_message(server.decode(&mut wire).unwrap().unwrap(), 1000);
+    }
+}
@@ -1,9 +1,65 @@
 // src/frame.rs
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::io::{Cursor, Read};
+use flate2::{Compression, read::DeflateDecoder, write::DeflateEncoder};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 pub const MAGIC: &[u8; 2] = b"IM";
-pub const VERSION: u8 = 1;
+/// 当前实现的最高帧版本，默认按该版本打包
+pub const VERSION: u8 = 2;
+/// 仍然兼容的最低帧版本，低于该版本的客户端会在握手时被要求升级
+pub const MIN_VERSION: u8 = 1;
+
+/* ---------------- flags 位定义 ---------------- */
+
+/// payload 经过 deflate（RFC 1951，无 zlib 头）压缩
+pub const FLAG_COMPRESSED: u16 = 0x0001;
+/// 分片帧：帧头后紧跟分片扩展头，payload 只是整条消息的一段
+pub const FLAG_FRAGMENTED: u16 = 0x0002;
+/// 当前实现能识别的全部 flag 位，其余位出现即视为未知
+pub const KNOWN_FLAGS: u16 = FLAG_COMPRESSED | FLAG_FRAGMENTED;
+
+/* ---------------- 版本 ---------------- */
+
+/// 各帧版本允许出现的 flag 位：
+/// - v1：支持压缩
+/// - v2：在 v1 基础上增加分片
+pub fn version_flags(version: u8) -> Option<u16> {
+    match version {
+        1 => Some(FLAG_COMPRESSED),
+        2 => Some(KNOWN_FLAGS),
+        _ => None,
+    }
+}
+
+/// 是否支持该帧版本
+pub fn is_supported_version(version: u8) -> bool {
+    (MIN_VERSION..=VERSION).contains(&version) && version_flags(version).is_some()
+}
+
+/// 版本协商：客户端声明自己支持 [min, max]，取双方共同支持的最高版本，没有交集时返回 None
+pub fn negotiate_version(client_min: u8, client_max: u8) -> Option<u8> {
+    let high = client_max.min(VERSION);
+    let low = client_min.max(MIN_VERSION);
+    (high >= low).then_some(high)
+}
+
+/// to_frame() 自动压缩的阈值：消息体超过该字节数才尝试压缩
+pub const COMPRESS_THRESHOLD: usize = 512;
+/// 解压后允许的最大字节数，防止压缩炸弹
+pub const MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+/// 单个分片 payload 的默认上限，超过后 to_frames() 会拆分，避免超出代理的单帧限制
+pub const DEFAULT_MAX_FRAGMENT_LEN: usize = 64 * 1024;
+/// 重组器默认允许的单条消息最大字节数（拼接后、解压前）
+pub const DEFAULT_MAX_REASSEMBLED_LEN: usize = MAX_DECOMPRESSED_LEN;
+/// 重组器默认的超时时间：首个分片到达后超过该时间仍未收齐则丢弃
+pub const DEFAULT_REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+/// 重组器同时缓存的未完成消息数上限
+pub const MAX_PENDING_MESSAGES: usize = 16;
 
 /// 方向：服务端只发送 S2C，只接收 C2S
 #[repr(u8)]
@@ -26,24 +82,70 @@ pub enum FrameError {
     Utf8(std::string::FromUtf8Error),
     Io(std::io::Error),
     InvalidKind(u8), // 由各类型在 from_bytes 时决定是否使用
+    UnknownFlags(u16), // 出现了本实现无法识别的 flag 位
+    PayloadTooLarge(usize), // 负载（或解压后的负载）超过上限
+    InvalidFragment { frag_id: u32, index: u16, total: u16 }, // 分片序号越界/重复或与首个分片不一致
+    ReassemblyTimeout(u32), // 分片在超时时间内未收齐
+    TooManyPending(usize), // 未完成的分片消息过多
 }
 impl From<std::io::Error> for FrameError { fn from(e: std::io::Error) -> Self { Self::Io(e) } }
 impl From<std::string::FromUtf8Error> for FrameError { fn from(e: std::string::FromUtf8Error) -> Self { Self::Utf8(e) } }
 
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::InvalidMagic(m) => write!(f, "非法魔数: {:?}", m),
+            FrameError::InvalidVersion(v) => write!(f, "不支持的协议版本: {}", v),
+            FrameError::InvalidDirection(d) => write!(f, "非法方向: {}", d),
+            FrameError::Truncated => write!(f, "帧数据不完整"),
+            FrameError::Utf8(e) => write!(f, "UTF-8 解码失败: {}", e),
+            FrameError::Io(e) => write!(f, "IO 错误: {}", e),
+            FrameError::InvalidKind(k) => write!(f, "未知的消息类型: {}", k),
+            FrameError::UnknownFlags(fl) => write!(f, "未知的 flag 位: {:#06x}", fl),
+            FrameError::PayloadTooLarge(n) => write!(f, "负载过大: {} 字节", n),
+            FrameError::InvalidFragment { frag_id, index, total } => {
+                write!(f, "非法分片: 消息 {} 的第 {}/{} 片", frag_id, index, total)
+            }
+            FrameError::ReassemblyTimeout(id) => write!(f, "分片消息 {} 重组超时", id),
+            FrameError::TooManyPending(n) => write!(f, "未完成的分片消息过多: {}", n),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
 /// 帧头（固定）：
-/// [ magic(2)="IM" | version(1)=1 | dir(1) | kind(1) | flags(2) | payload_len(4) | payload ... ]
+/// [ magic(2)="IM" | version(1) | dir(1) | kind(1) | flags(2) | payload_len(4) | payload ... ]
+/// version: MIN_VERSION..=VERSION，决定 flags 中哪些位可用（见 version_flags）
+/// flags:
+///   bit0 = FLAG_COMPRESSED，payload 为 deflate 压缩数据，payload_len 为压缩后长度
+///   bit1 = FLAG_FRAGMENTED，帧头后追加分片扩展头：
+///          [ frag_id(4) | frag_index(2) | frag_total(2) ]
+///          payload_len 为本分片长度；压缩针对整条消息，拼接后再解压
 #[derive(Debug, Clone, Copy)]
 pub struct Header {
+    pub version: u8,
     pub dir: Direction,
     pub kind_u8: u8,   // 各消息自定义意义空间
-    pub flags: u16,    // 预留（压缩/加密/分片等）
+    pub flags: u16,    // 见 FLAG_* 常量；其余位预留（加密等）
     pub payload_len: usize,
+    pub fragment: Option<Fragment>, // 仅 FLAG_FRAGMENTED 时存在
 }
 
-fn write_header(out: &mut Vec<u8>, dir: Direction, kind_u8: u8, flags: u16, payload_len: usize) -> Result<(), FrameError> {
+/// 分片扩展头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fragment {
+    pub frag_id: u32, // 同一条消息的所有分片共享，由发送方在连接内自行分配
+    pub index: u16,   // 从 0 开始
+    pub total: u16,   // 分片总数（>= 1）
+}
+
+fn write_header(out: &mut Vec<u8>, version: u8, dir: Direction, kind_u8: u8, flags: u16, payload_len: usize) -> Result<(), FrameError> {
     if payload_len > u32::MAX as usize { return Err(FrameError::Truncated); }
+    let allowed = version_flags(version).ok_or(FrameError::InvalidVersion(version))?;
+    if flags & !allowed != 0 { return Err(FrameError::UnknownFlags(flags & !allowed)); }
     out.extend_from_slice(MAGIC);
-    out.push(VERSION);
+    out.push(version);
     out.push(dir as u8);
     out.push(kind_u8);
     out.write_u16::<BigEndian>(flags)?;
@@ -51,43 +153,220 @@ fn write_header(out: &mut Vec<u8>, dir: Direction, kind_u8: u8, flags: u16, payl
     Ok(())
 }
 
+fn write_fragment(out: &mut Vec<u8>, frag: Fragment) -> Result<(), FrameError> {
+    out.write_u32::<BigEndian>(frag.frag_id)?;
+    out.write_u16::<BigEndian>(frag.index)?;
+    out.write_u16::<BigEndian>(frag.total)?;
+    Ok(())
+}
+
 fn read_header(c: &mut Cursor<&[u8]>) -> Result<Header, FrameError> {
     let mut mg = [0u8; 2];
     c.read_exact(&mut mg)?;
     if &mg != MAGIC { return Err(FrameError::InvalidMagic(mg)); }
 
-    let ver = c.read_u8()?;
-    if ver != VERSION { return Err(FrameError::InvalidVersion(ver)); }
+    let version = c.read_u8()?;
+    if !is_supported_version(version) { return Err(FrameError::InvalidVersion(version)); }
+    let allowed = version_flags(version).unwrap_or(0);
 
     let dir = Direction::try_from(c.read_u8()?)?;
     let kind_u8 = c.read_u8()?;
     let flags = c.read_u16::<BigEndian>()?;
+    if flags & !allowed != 0 { return Err(FrameError::UnknownFlags(flags & !allowed)); }
     let payload_len = c.read_u32::<BigEndian>()? as usize;
 
-    Ok(Header { dir, kind_u8, flags, payload_len })
+    let fragment = if flags & FLAG_FRAGMENTED != 0 {
+        let frag_id = c.read_u32::<BigEndian>()?;
+        let index = c.read_u16::<BigEndian>()?;
+        let total = c.read_u16::<BigEndian>()?;
+        if total == 0 || index >= total {
+            return Err(FrameError::InvalidFragment { frag_id, index, total });
+        }
+        Some(Fragment { frag_id, index, total })
+    } else {
+        None
+    };
+
+    Ok(Header { version, dir, kind_u8, flags, payload_len, fragment })
+}
+
+/// 固定帧头长度（不含分片扩展头）
+pub const HEADER_LEN: usize = 2 + 1 + 1 + 1 + 2 + 4;
+/// 分片扩展头长度
+pub const FRAGMENT_HEADER_LEN: usize = 4 + 2 + 2;
+
+/// 探测缓冲区开头的完整帧长度（帧头 + 扩展头 + payload）：
+/// - Ok(Some(len))：缓冲区内至少已有一个完整帧，前 len 字节即为该帧
+/// - Ok(None)：数据还不够，需要继续读取
+/// - Err(_)：帧头非法或 payload_len 超过 max_payload_len，字节流已无法继续解析
+pub fn frame_len(bytes: &[u8], max_payload_len: usize) -> Result<Option<usize>, FrameError> {
+    let mut c = Cursor::new(bytes);
+    let header = match read_header(&mut c) {
+        Ok(h) => h,
+        Err(FrameError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if header.payload_len > max_payload_len { return Err(FrameError::PayloadTooLarge(header.payload_len)); }
+    let len = c.position() as usize + header.payload_len;
+    Ok((bytes.len() >= len).then_some(len))
+}
+
+/// 流式拆帧器：接收任意切分的字节块，按帧边界切出完整帧
+/// 适用于 TCP / Unix socket 等没有消息边界的字节流；切出的帧可交给 from_frame() 或 FrameReassembler
+/// 出错后字节流已失去同步，调用方应关闭连接
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    max_payload_len: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(MAX_DECOMPRESSED_LEN)
+    }
+}
+
+impl FrameDecoder {
+    /// max_payload_len 为单帧 payload 的上限，防止恶意的超大 payload_len 导致无限缓存
+    pub fn new(max_payload_len: usize) -> Self {
+        Self { buf: Vec::new(), max_payload_len }
+    }
+
+    /// 追加读到的字节
+    pub fn extend(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// 取出下一个完整帧，数据不足时返回 None
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+        match frame_len(&self.buf, self.max_payload_len)? {
+            Some(len) => Ok(Some(self.buf.drain(..len).collect())),
+            None => Ok(None),
+        }
+    }
+
+    /// 追加字节并取出当前所有完整帧（可能为 0 个或多个）
+    pub fn decode(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, FrameError> {
+        self.extend(chunk);
+        let mut frames = Vec::new();
+        while let Some(frame) = self.next_frame()? {
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+
+    /// 缓冲区中尚未组成完整帧的字节数
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
 }
 
-/// 原始帧（头 + 负载切片）
+/// 原始帧（头 + 负载）
+/// header 保留线上的原始值（flags、payload_len 均为压缩后的），
+/// payload 则是已经解压后的消息体：未压缩时直接借用输入缓冲区，压缩时为解压得到的新缓冲区
+/// 分片帧例外：payload 为本分片的原始字节，解压由 FrameReassembler 在拼接完成后进行
 pub struct RawFrame<'a> {
     pub header: Header,
-    pub payload: &'a [u8],
+    pub payload: Cow<'a, [u8]>,
+}
+
+/// deflate 压缩
+pub fn compress_payload(payload: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let mut enc = DeflateEncoder::new(Vec::with_capacity(payload.len() / 2), Compression::fast());
+    enc.write_all(payload)?;
+    Ok(enc.finish()?)
+}
+
+/// deflate 解压，超过 MAX_DECOMPRESSED_LEN 视为错误
+pub fn decompress_payload(payload: &[u8]) -> Result<Vec<u8>, FrameError> {
+    let mut out = Vec::with_capacity(payload.len() * 2);
+    DeflateDecoder::new(payload)
+        .take(MAX_DECOMPRESSED_LEN as u64 + 1)
+        .read_to_end(&mut out)?;
+    if out.len() > MAX_DECOMPRESSED_LEN { return Err(FrameError::PayloadTooLarge(out.len())); }
+    Ok(out)
 }
 
-/// 仅打包（服务端发送或客户端发送均可复用）
+/// 仅打包（服务端发送或客户端发送均可复用），使用当前最高版本 VERSION
 pub fn encode_raw(dir: Direction, kind_u8: u8, payload: &[u8], flags: u16) -> Result<Vec<u8>, FrameError> {
-    let mut out = Vec::with_capacity(2 + 1 + 1 + 1 + 2 + 4 + payload.len());
-    write_header(&mut out, dir, kind_u8, flags, payload.len())?;
+    encode_raw_versioned(VERSION, dir, kind_u8, payload, flags)
+}
+
+/// 按指定帧版本打包，flags 必须是该版本允许的位
+pub fn encode_raw_versioned(version: u8, dir: Direction, kind_u8: u8, payload: &[u8], flags: u16) -> Result<Vec<u8>, FrameError> {
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    write_header(&mut out, version, dir, kind_u8, flags, payload.len())?;
     out.extend_from_slice(payload);
     Ok(out)
 }
 
-/// 仅拆包（返回头与 payload 切片）
+/// 打包并按阈值自动压缩：payload 超过 threshold 且压缩后确实更小时才置 FLAG_COMPRESSED
+/// threshold 为 None 时不压缩
+pub fn encode_auto(dir: Direction, kind_u8: u8, payload: &[u8], threshold: Option<usize>) -> Result<Vec<u8>, FrameError> {
+    if let Some(threshold) = threshold && payload.len() > threshold {
+        let compressed = compress_payload(payload)?;
+        if compressed.len() < payload.len() {
+            return encode_raw(dir, kind_u8, &compressed, FLAG_COMPRESSED);
+        }
+    }
+    encode_raw(dir, kind_u8, payload, 0)
+}
+
+/// 打包并在需要时分片：先按阈值压缩整条消息，结果不超过 max_fragment_len 时输出单个普通帧，
+/// 否则切成多个带 FLAG_FRAGMENTED 的帧（共享 frag_id），分片数不能超过 u16::MAX
+/// 所选版本不支持分片（如 v1）时始终输出单个帧
+pub fn encode_fragments(
+    version: u8,
+    dir: Direction,
+    kind_u8: u8,
+    payload: &[u8],
+    threshold: Option<usize>,
+    max_fragment_len: usize,
+    frag_id: u32,
+) -> Result<Vec<Vec<u8>>, FrameError> {
+    let allowed = version_flags(version).ok_or(FrameError::InvalidVersion(version))?;
+    let max_fragment_len = if allowed & FLAG_FRAGMENTED != 0 { max_fragment_len.max(1) } else { usize::MAX };
+    let threshold = if allowed & FLAG_COMPRESSED != 0 { threshold } else { None };
+    let mut flags = 0;
+    let mut body = Cow::Borrowed(payload);
+    if let Some(threshold) = threshold && payload.len() > threshold {
+        let compressed = compress_payload(payload)?;
+        if compressed.len() < payload.len() {
+            flags |= FLAG_COMPRESSED;
+            body = Cow::Owned(compressed);
+        }
+    }
+    if body.len() <= max_fragment_len {
+        return Ok(vec![encode_raw_versioned(version, dir, kind_u8, &body, flags)?]);
+    }
+
+    let total = body.len().div_ceil(max_fragment_len);
+    if total > u16::MAX as usize { return Err(FrameError::PayloadTooLarge(body.len())); }
+    let total = total as u16;
+    flags |= FLAG_FRAGMENTED;
+    body.chunks(max_fragment_len)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut out = Vec::with_capacity(HEADER_LEN + FRAGMENT_HEADER_LEN + chunk.len());
+            write_header(&mut out, version, dir, kind_u8, flags, chunk.len())?;
+            write_fragment(&mut out, Fragment { frag_id, index: index as u16, total })?;
+            out.extend_from_slice(chunk);
+            Ok(out)
+        })
+        .collect()
+}
+
+/// 仅拆包（返回头与 payload；带 FLAG_COMPRESSED 时透明解压，分片帧除外）
 pub fn decode_raw(bytes: &[u8]) -> Result<RawFrame<'_>, FrameError> {
     let mut c = Cursor::new(bytes);
     let header = read_header(&mut c)?;
     let pos = c.position() as usize;
     if bytes.len() < pos + header.payload_len { return Err(FrameError::Truncated); }
-    let payload = &bytes[pos..pos + header.payload_len];
+    let wire = &bytes[pos..pos + header.payload_len];
+    let payload = if header.flags & FLAG_COMPRESSED != 0 && header.fragment.is_none() {
+        Cow::Owned(decompress_payload(wire)?)
+    } else {
+        Cow::Borrowed(wire)
+    };
     Ok(RawFrame { header, payload })
 }
 
@@ -107,17 +386,389 @@ pub trait FrameCodec: Sized {
     /// 根据 kind + payload 反序列化为本消息类型
     fn from_bytes(kind: u8, payload: &[u8]) -> Result<Self, FrameError>;
 
-    /// 默认：把本消息打成完整帧字节
+    /// 默认：把本消息打成完整帧字节，超过 COMPRESS_THRESHOLD 时自动压缩
     fn to_frame(&self) -> Result<Vec<u8>, FrameError> {
-        encode_raw(Self::DIR, self.kind(), &self.to_bytes(), 0)
+        self.to_frame_with_threshold(Some(COMPRESS_THRESHOLD))
+    }
+
+    /// 指定压缩阈值打帧，None 表示不压缩
+    fn to_frame_with_threshold(&self, threshold: Option<usize>) -> Result<Vec<u8>, FrameError> {
+        encode_auto(Self::DIR, self.kind(), &self.to_bytes(), threshold)
+    }
+
+    /// 打帧并在超过 max_fragment_len 时分片，frag_id 由调用方在连接内分配（如自增计数）
+    fn to_frames(&self, max_fragment_len: usize, frag_id: u32) -> Result<Vec<Vec<u8>>, FrameError> {
+        self.to_frames_versioned(VERSION, max_fragment_len, frag_id)
+    }
+
+    /// 按握手协商出的帧版本打帧，旧版本不支持的压缩/分片会自动关闭
+    fn to_frames_versioned(&self, version: u8, max_fragment_len: usize, frag_id: u32) -> Result<Vec<Vec<u8>>, FrameError> {
+        encode_fragments(version, Self::DIR, self.kind(), &self.to_bytes(), Some(COMPRESS_THRESHOLD), max_fragment_len, frag_id)
     }
 
-    /// 默认：从完整帧字节解析本消息类型（会校验方向）
+    /// 默认：从完整帧字节解析本消息类型（会校验方向，压缩帧透明解压，兼容所有受支持的版本）
+    /// 分片帧无法单独解析，需交给 FrameReassembler
     fn from_frame(frame_bytes: &[u8]) -> Result<Self, FrameError> {
         let rf = decode_raw(frame_bytes)?;
         if rf.header.dir != Self::DIR {
             return Err(FrameError::InvalidDirection(rf.header.dir as u8));
         }
-        Self::from_bytes(rf.header.kind_u8, rf.payload)
+        if let Some(frag) = rf.header.fragment {
+            return Err(FrameError::InvalidFragment { frag_id: frag.frag_id, index: frag.index, total: frag.total });
+        }
+        Self::from_bytes(rf.header.kind_u8, &rf.payload)
+    }
+}
+
+/* ---------------- 分片重组 ---------------- */
+
+/// 一条尚未收齐的分片消息
+struct PendingMessage {
+    kind_u8: u8,
+    flags: u16,
+    parts: Vec<Option<Vec<u8>>>,
+    received: usize,
+    size: usize,
+    started: Instant,
+}
+
+/// 分片重组器：每个连接持有一个，按 frag_id 收集分片，收齐后解压并还原为 T
+/// - 非分片帧直接解析返回，因此可以无差别地把收到的所有二进制帧交给 push()
+/// - 单条消息拼接后的字节数超过 max_total_len 时丢弃并报错
+/// - 首个分片到达后超过 timeout 仍未收齐的消息会被丢弃
+pub struct FrameReassembler<T: FrameCodec> {
+    max_total_len: usize,
+    timeout: Duration,
+    pending: HashMap<u32, PendingMessage>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: FrameCodec> Default for FrameReassembler<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_REASSEMBLED_LEN, DEFAULT_REASSEMBLY_TIMEOUT)
+    }
+}
+
+impl<T: FrameCodec> FrameReassembler<T> {
+    pub fn new(max_total_len: usize, timeout: Duration) -> Self {
+        Self { max_total_len, timeout, pending: HashMap::new(), _marker: PhantomData }
+    }
+
+    /// 当前未收齐的消息数
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 丢弃所有已超时的未完成消息，返回被丢弃的 frag_id
+    pub fn purge_expired(&mut self) -> Vec<u32> {
+        let timeout = self.timeout;
+        let expired: Vec<u32> = self.pending.iter()
+            .filter(|(_, p)| p.started.elapsed() > timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired { self.pending.remove(id); }
+        expired
+    }
+
+    /// 喂入一个完整的二进制帧：
+    /// - Ok(Some(msg))：得到一条完整消息
+    /// - Ok(None)：分片已缓存，消息尚未收齐
+    /// - Err(_)：帧非法；若属于某条分片消息，该消息的已缓存分片会一并丢弃
+    pub fn push(&mut self, frame_bytes: &[u8]) -> Result<Option<T>, FrameError> {
+        let rf = decode_raw(frame_bytes)?;
+        if rf.header.dir != T::DIR {
+            return Err(FrameError::InvalidDirection(rf.header.dir as u8));
+        }
+        let Some(frag) = rf.header.fragment else {
+            return T::from_bytes(rf.header.kind_u8, &rf.payload).map(Some);
+        };
+
+        let result = self.accept(&rf.header, frag, &rf.payload);
+        if result.is_err() { self.pending.remove(&frag.frag_id); }
+        result
+    }
+
+    fn accept(&mut self, header: &Header, frag: Fragment, chunk: &[u8]) -> Result<Option<T>, FrameError> {
+        let invalid = FrameError::InvalidFragment { frag_id: frag.frag_id, index: frag.index, total: frag.total };
+
+        if let Some(p) = self.pending.get(&frag.frag_id) && p.started.elapsed() > self.timeout {
+            return Err(FrameError::ReassemblyTimeout(frag.frag_id));
+        }
+        if !self.pending.contains_key(&frag.frag_id) {
+            self.purge_expired();
+            if self.pending.len() >= MAX_PENDING_MESSAGES {
+                return Err(FrameError::TooManyPending(self.pending.len()));
+            }
+            self.pending.insert(frag.frag_id, PendingMessage {
+                kind_u8: header.kind_u8,
+                flags: header.flags,
+                parts: vec![None; frag.total as usize],
+                received: 0,
+                size: 0,
+                started: Instant::now(),
+            });
+        }
+
+        let p = self.pending.get_mut(&frag.frag_id).ok_or(FrameError::Truncated)?;
+        let slot = p.parts.get_mut(frag.index as usize);
+        match slot {
+            Some(slot @ None) if p.kind_u8 == header.kind_u8 && p.flags == header.flags => {
+                *slot = Some(chunk.to_vec());
+            }
+            _ => return Err(invalid),
+        }
+        p.received += 1;
+        p.size += chunk.len();
+        if p.size > self.max_total_len { return Err(FrameError::PayloadTooLarge(p.size)); }
+        if p.received < p.parts.len() { return Ok(None); }
+
+        let p = self.pending.remove(&frag.frag_id).ok_or(FrameError::Truncated)?;
+        let mut body = Vec::with_capacity(p.size);
+        for part in p.parts.into_iter().flatten() { body.extend_from_slice(&part); }
+        if p.flags & FLAG_COMPRESSED != 0 { body = decompress_payload(&body)?; }
+        T::from_bytes(p.kind_u8, &body).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 测试用消息：kind + 原样的消息体
+    #[derive(Debug, PartialEq)]
+    struct Blob(u8, Vec<u8>);
+
+    impl FrameCodec for Blob {
+        const DIR: Direction = Direction::C2S;
+        fn kind(&self) -> u8 { self.0 }
+        fn to_bytes(&self) -> Vec<u8> { self.1.clone() }
+        fn from_bytes(kind: u8, payload: &[u8]) -> Result<Self, FrameError> { Ok(Blob(kind, payload.to_vec())) }
+    }
+
+    /// 可压缩的消息体
+    fn repetitive(len: usize) -> Vec<u8> {
+        b"uchat frame ".iter().copied().cycle().take(len).collect()
+    }
+
+    /// 几乎不可压缩的消息体（线性同余序列）
+    fn noisy(len: usize) -> Vec<u8> {
+        let mut x: u32 = 0x1234_5678;
+        (0..len).map(|_| { x = x.wrapping_mul(1_103_515_245).wrapping_add(12_345); (x >> 24) as u8 }).collect()
+    }
+
+    fn flags_of(frame: &[u8]) -> u16 {
+        u16::from_be_bytes([frame[5], frame[6]])
+    }
+
+    #[test]
+    fn compress_roundtrip() {
+        let payload = repetitive(4096);
+        let compressed = compress_payload(&payload).unwrap();
+        assert!(compressed.len() < payload.len());
+        assert_eq!(decompress_payload(&compressed).unwrap(), payload);
+    }
+
+    #[test]
+    fn to_frame_compresses_above_threshold() {
+        let large = Blob(7, repetitive(COMPRESS_THRESHOLD * 4));
+        let frame = large.to_frame().unwrap();
+        assert_eq!(flags_of(&frame) & FLAG_COMPRESSED, FLAG_COMPRESSED);
+        assert!(frame.len() < HEADER_LEN + large.1.len());
+        assert_eq!(Blob::from_frame(&frame).unwrap(), large);
+
+        let small = Blob(7, repetitive(COMPRESS_THRESHOLD));
+        let frame = small.to_frame().unwrap();
+        assert_eq!(flags_of(&frame), 0);
+        assert_eq!(Blob::from_frame(&frame).unwrap(), small);
+
+        let frame = large.to_frame_with_threshold(None).unwrap();
+        assert_eq!(flags_of(&frame), 0);
+        assert_eq!(Blob::from_frame(&frame).unwrap(), large);
+    }
+
+    #[test]
+    fn decompress_rejects_bomb() {
+        let bomb = compress_payload(&vec![0u8; MAX_DECOMPRESSED_LEN + 1]).unwrap();
+        assert!(matches!(decompress_payload(&bomb), Err(FrameError::PayloadTooLarge(_))));
+
+        let frame = encode_raw(Direction::C2S, 1, &bomb, FLAG_COMPRESSED).unwrap();
+        assert!(matches!(Blob::from_frame(&frame), Err(FrameError::PayloadTooLarge(_))));
+    }
+
+    #[test]
+    fn unknown_flags_rejected() {
+        let mut frame = encode_raw(Direction::C2S, 1, b"hi", 0).unwrap();
+        frame[5..7].copy_from_slice(&0x8000u16.to_be_bytes());
+        assert!(matches!(decode_raw(&frame), Err(FrameError::UnknownFlags(0x8000))));
+        assert!(matches!(encode_raw(Direction::C2S, 1, b"hi", 0x8000), Err(FrameError::UnknownFlags(0x8000))));
+    }
+
+    fn fragments(payload: &[u8], max_fragment_len: usize, frag_id: u32) -> Vec<Vec<u8>> {
+        encode_fragments(VERSION, Direction::C2S, 3, payload, None, max_fragment_len, frag_id).unwrap()
+    }
+
+    #[test]
+    fn split_and_reassemble_in_order() {
+        let payload = noisy(10_000);
+        let frames = fragments(&payload, 1000, 42);
+        assert_eq!(frames.len(), 10);
+        assert!(frames.iter().all(|f| flags_of(f) & FLAG_FRAGMENTED != 0));
+
+        let mut reassembler = FrameReassembler::<Blob>::default();
+        for frame in &frames[..9] {
+            assert_eq!(reassembler.push(frame).unwrap(), None);
+        }
+        assert_eq!(reassembler.pending_len(), 1);
+        assert_eq!(reassembler.push(&frames[9]).unwrap(), Some(Blob(3, payload)));
+        assert_eq!(reassembler.pending_len(), 0);
+    }
+
+    #[test]
+    fn reassemble_out_of_order_and_interleaved() {
+        let a = noisy(5000);
+        let b = repetitive(3000);
+        let frames_a = fragments(&a, 1000, 1);
+        let frames_b = fragments(&b, 1000, 2);
+
+        let mut reassembler = FrameReassembler::<Blob>::default();
+        let mut done = Vec::new();
+        for frame in frames_a.iter().rev().zip(frames_b.iter().rev()).flat_map(|(x, y)| [x, y]) {
+            done.extend(reassembler.push(frame).unwrap());
+        }
+        for frame in frames_a.iter().rev().skip(frames_b.len()) {
+            done.extend(reassembler.push(frame).unwrap());
+        }
+        assert_eq!(done, vec![Blob(3, b), Blob(3, a)]);
+        assert_eq!(reassembler.pending_len(), 0);
+    }
+
+    #[test]
+    fn duplicate_fragment_discards_message() {
+        let frames = fragments(&noisy(3000), 1000, 9);
+        let mut reassembler = FrameReassembler::<Blob>::default();
+        assert_eq!(reassembler.push(&frames[0]).unwrap(), None);
+        assert!(matches!(
+            reassembler.push(&frames[0]),
+            Err(FrameError::InvalidFragment { frag_id: 9, index: 0, total: 3 })
+        ));
+        assert_eq!(reassembler.pending_len(), 0);
+    }
+
+    #[test]
+    fn compressed_message_is_fragmented_after_compression() {
+        let large = Blob(3, repetitive(200_000));
+        let frames = large.to_frames(1024, 5).unwrap();
+        assert!(frames.len() > 1);
+        assert!(frames.iter().all(|f| flags_of(f) == FLAG_COMPRESSED | FLAG_FRAGMENTED));
+
+        let mut reassembler = FrameReassembler::<Blob>::default();
+        let mut done = Vec::new();
+        for frame in frames.iter().rev() {
+            done.extend(reassembler.push(frame).unwrap());
+        }
+        assert_eq!(done, vec![large]);
+    }
+
+    #[test]
+    fn fragment_limits() {
+        let frames = fragments(&noisy(3000), 1000, 1);
+        assert!(matches!(Blob::from_frame(&frames[0]), Err(FrameError::InvalidFragment { .. })));
+
+        let mut reassembler = FrameReassembler::<Blob>::new(2000, DEFAULT_REASSEMBLY_TIMEOUT);
+        assert_eq!(reassembler.push(&frames[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&frames[1]).unwrap(), None);
+        assert!(matches!(reassembler.push(&frames[2]), Err(FrameError::PayloadTooLarge(3000))));
+        assert_eq!(reassembler.pending_len(), 0);
+
+        let mut reassembler = FrameReassembler::<Blob>::default();
+        for frag_id in 0..MAX_PENDING_MESSAGES as u32 {
+            assert_eq!(reassembler.push(&fragments(&noisy(2000), 1000, frag_id)[0]).unwrap(), None);
+        }
+        assert!(matches!(
+            reassembler.push(&fragments(&noisy(2000), 1000, 999)[0]),
+            Err(FrameError::TooManyPending(MAX_PENDING_MESSAGES))
+        ));
+
+        let single = Blob(3, b"hello".to_vec());
+        assert_eq!(reassembler.push(&single.to_frame().unwrap()).unwrap(), Some(single));
+    }
+
+    #[test]
+    fn decoder_handles_partial_reads() {
+        let first = Blob(1, noisy(300)).to_frame().unwrap();
+        let second = Blob(2, b"second".to_vec()).to_frame().unwrap();
+        let stream: Vec<u8> = first.iter().chain(&second).copied().collect();
+
+        assert_eq!(frame_len(&stream[..HEADER_LEN - 1], MAX_DECOMPRESSED_LEN).unwrap(), None);
+        assert_eq!(frame_len(&stream[..HEADER_LEN], MAX_DECOMPRESSED_LEN).unwrap(), None);
+        assert_eq!(frame_len(&stream, MAX_DECOMPRESSED_LEN).unwrap(), Some(first.len()));
+
+        // 逐字节喂入
+        let mut decoder = FrameDecoder::default();
+        let mut frames = Vec::new();
+        for byte in &stream {
+            frames.extend(decoder.decode(std::slice::from_ref(byte)).unwrap());
+        }
+        assert_eq!(frames, vec![first.clone(), second.clone()]);
+        assert_eq!(decoder.buffered_len(), 0);
+
+        // 一次读取包含一个半帧
+        let mut decoder = FrameDecoder::default();
+        let split = first.len() + 3;
+        assert_eq!(decoder.decode(&stream[..split]).unwrap(), vec![first]);
+        assert_eq!(decoder.buffered_len(), 3);
+        assert_eq!(decoder.decode(&stream[split..]).unwrap(), vec![second]);
+    }
+
+    #[test]
+    fn decoder_rejects_oversized_and_garbage() {
+        let frame = Blob(1, noisy(2000)).to_frame().unwrap();
+        let mut decoder = FrameDecoder::new(1000);
+        assert!(matches!(decoder.decode(&frame[..HEADER_LEN]), Err(FrameError::PayloadTooLarge(2000))));
+
+        let mut decoder = FrameDecoder::default();
+        assert!(matches!(decoder.decode(b"XX\x02\x00"), Err(FrameError::InvalidMagic(_))));
+    }
+
+    #[test]
+    fn negotiate_version_overlap() {
+        assert_eq!(negotiate_version(MIN_VERSION, VERSION), Some(VERSION));
+        assert_eq!(negotiate_version(1, 1), Some(1));
+        assert_eq!(negotiate_version(1, u8::MAX), Some(VERSION));
+        assert_eq!(negotiate_version(0, 1), Some(1));
+        assert_eq!(negotiate_version(VERSION + 1, VERSION + 3), None);
+        assert_eq!(negotiate_version(0, 0), None);
+        assert_eq!(negotiate_version(2, 1), None);
+    }
+
+    #[test]
+    fn v1_frames_compress_but_never_fragment() {
+        let large = Blob(3, repetitive(200_000));
+        let frames = large.to_frames_versioned(1, 1024, 0).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0][2], 1);
+        assert_eq!(flags_of(&frames[0]), FLAG_COMPRESSED);
+        assert_eq!(Blob::from_frame(&frames[0]).unwrap(), large);
+
+        let noisy = Blob(3, noisy(5000));
+        let frames = noisy.to_frames_versioned(1, 1024, 0).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(Blob::from_frame(&frames[0]).unwrap(), noisy);
+
+        // v1 帧头不允许出现分片位
+        let mut frame = fragments(&noisy.1, 1000, 1).swap_remove(0);
+        frame[2] = 1;
+        assert!(matches!(decode_raw(&frame), Err(FrameError::UnknownFlags(FLAG_FRAGMENTED))));
+    }
+
+    #[test]
+    fn unsupported_versions_rejected() {
+        assert!(is_supported_version(MIN_VERSION) && is_supported_version(VERSION));
+        assert!(!is_supported_version(0) && !is_supported_version(VERSION + 1));
+
+        let mut frame = Blob(1, b"hi".to_vec()).to_frame().unwrap();
+        frame[2] = VERSION + 1;
+        assert!(matches!(Blob::from_frame(&frame), Err(FrameError::InvalidVersion(v)) if v == VERSION + 1));
+        assert!(matches!(encode_raw_versioned(0, Direction::C2S, 1, b"hi", 0), Err(FrameError::InvalidVersion(0))));
     }
 }
@@ -1,4 +1,6 @@
 pub mod frame;
+#[cfg(feature = "codec")]
+pub mod codec;
 pub mod manager;
 pub mod message;
 pub mod model;
@@ -1,4 +1,6 @@
-use crate::RoleType;
+use std::collections::HashMap;
+
+use crate::{ExportDocument, GroupId, RoleType, UserId};
 use serde::Deserialize;
 use utoipa::{IntoParams, ToSchema};
 
@@ -54,6 +56,14 @@ pub struct GetUserRecentMessageRequest {
     pub user_id: u32,
 }
 
+/// 获取群聊近期聊天记录
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct GetGroupRecentMessageRequest {
+    pub count: u32,
+    pub offset: u32,
+    pub group_id: u32,
+}
+
 /// 删除单条消息
 #[derive(Deserialize, Debug, IntoParams)]
 pub struct DeleteMessageRequest {
@@ -65,3 +75,15 @@ pub struct DeleteMessageRequest {
 pub struct GetMessageRequest {
     pub message_id: u64,
 }
+
+/// 导入聊天记录，document 为导出接口生成的 JSON 文件
+/// 用户和群组优先按 user_map/group_map（源ID -> 本实例ID）对应，未指定时按唯一的用户名/群名对应
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct ImportMessagesRequest {
+    pub source: String, // 来源标识（如源实例名），与源消息ID一起用于去重，重复导入同一来源不会产生重复消息
+    pub document: ExportDocument,
+    #[serde(default)]
+    pub user_map: HashMap<UserId, UserId>,
+    #[serde(default)]
+    pub group_map: HashMap<GroupId, GroupId>,
+}
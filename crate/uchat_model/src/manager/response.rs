@@ -51,6 +51,16 @@ impl<T> ManagerResponse<T> {
             data: None,
         }
     }
+
+    /// 请求参数不合法
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: false,
+            code: 400,
+            message: message.into(),
+            data: None,
+        }
+    }
 }
 
 impl<T: serde::Serialize> IntoResponse for ManagerResponse<T> {
@@ -72,3 +82,11 @@ pub struct UserSessionInfo {
 pub struct OnlineUserTree {
     pub users: HashMap<u32, Vec<UserSessionInfo>>,
 }
+
+/// 导入聊天记录的结果统计
+#[derive(Serialize, Debug, Default, ToSchema)]
+pub struct ImportSummary {
+    pub imported: u32,   // 新写入的消息数
+    pub duplicated: u32, // 之前已导入过而跳过的消息数
+    pub unmapped: u32,   // 找不到对应用户或群组而跳过的消息数
+}
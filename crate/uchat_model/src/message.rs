@@ -1,15 +1,106 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Cursor, Read};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use crate::{
-    event::content::public::PublicEvent, frame::{Direction, FrameCodec, FrameError}, MessageId, Timestamp, UserId
+    event::content::public::PublicEvent, frame::{Direction, FrameCodec, FrameError}, Attachment, Conversation, ForwardedFrom, GroupId, MessageId, MessageType, Timestamp, UserId
 };
 
+/// WebSocket 连接的消息编码方式，在升级时协商：
+/// - 查询参数 `?encoding=json|binary`（优先）
+/// - 或请求头 `Sec-WebSocket-Protocol: uchat.json / uchat.binary`
+///
+/// 都未指定时使用 JSON 文本帧
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WsEncoding {
+    /// JSON 文本帧
+    #[default]
+    Json,
+    /// FrameCodec 二进制帧
+    Binary,
+}
+
+impl WsEncoding {
+    pub const PROTOCOL_JSON: &'static str = "uchat.json";
+    pub const PROTOCOL_BINARY: &'static str = "uchat.binary";
+    /// 服务端支持的全部子协议，按优先级排列
+    pub const PROTOCOLS: [&'static str; 2] = [Self::PROTOCOL_BINARY, Self::PROTOCOL_JSON];
+
+    /// 根据 Sec-WebSocket-Protocol 子协议名解析编码方式
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol.trim() {
+            Self::PROTOCOL_JSON => Some(WsEncoding::Json),
+            Self::PROTOCOL_BINARY => Some(WsEncoding::Binary),
+            _ => None,
+        }
+    }
+
+    /// 该编码方式对应的子协议名
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            WsEncoding::Json => Self::PROTOCOL_JSON,
+            WsEncoding::Binary => Self::PROTOCOL_BINARY,
+        }
+    }
+}
+
+/// 握手时未声明帧版本范围的旧客户端，视为只支持该版本
+pub const LEGACY_FRAME_VERSION: u8 = 1;
+/// 客户端与服务端没有共同帧版本时使用的 WebSocket 关闭码（4000-4999 为应用自定义区间，对应 HTTP 426）
+pub const CLOSE_UPGRADE_REQUIRED: u16 = 4426;
+/// 一条群消息最多 @ 的成员数（二进制帧中以 u16 记录个数）
+pub const MAX_MENTIONS: usize = u16::MAX as usize;
+
+/// 输入状态
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TypingState {
+    /// 正在输入
+    Started,
+    /// 停止输入（发送或清空输入框）
+    Stopped,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
-    SendMessage { receiver: UserId, message: String },
-    SendGroupMessage { group_id: UserId, message: String },
+    /// client_msg_id 为客户端生成的消息标识（如 UUID），用于服务端回执与重试去重
+    /// message_type 缺省为 text；非文本消息需携带 attachment（文件先经对象存储上传）
+    SendMessage {
+        receiver: UserId,
+        message: String,
+        client_msg_id: Option<String>,
+        #[serde(default)]
+        message_type: MessageType,
+        #[serde(default)]
+        attachment: Option<Attachment>,
+        /// 引用回复的消息ID，必须是同一会话中的消息
+        #[serde(default)]
+        reply_to: Option<MessageId>,
+    },
+    SendGroupMessage {
+        group_id: UserId,
+        message: String,
+        client_msg_id: Option<String>,
+        #[serde(default)]
+        message_type: MessageType,
+        #[serde(default)]
+        attachment: Option<Attachment>,
+        #[serde(default)]
+        reply_to: Option<MessageId>,
+        /// 被 @ 的群成员ID；mention_all 为 @所有人，仅群管理员可用
+        #[serde(default)]
+        mentions: Vec<UserId>,
+        #[serde(default)]
+        mention_all: bool,
+    },
+    /// 输入状态，只转发不入库；target 为对方用户ID或群号
+    Typing { target: u32, is_group: bool, state: TypingState },
+    /// 标记会话中 up_to_message_id 及之前的消息为已读（游标只前进不后退）
+    MarkRead { conversation: Conversation, up_to_message_id: MessageId },
+    /// 确认已收到服务端推送的私聊消息（ServerMessage::SendMessage）
+    Received { message_id: MessageId },
 }
 
 #[derive(Serialize, Debug)]
@@ -21,6 +112,10 @@ pub enum ServerMessage {
         receiver: UserId,
         message: String,
         timestamp: Timestamp, // 使用 i64 存储时间戳，单位为秒
+        message_type: MessageType,
+        attachment: Option<Attachment>,
+        reply_to: Option<MessageId>, // 引用回复的消息ID
+        forwarded_from: Option<ForwardedFrom>, // 转发来源
     },
     SendGroupMessage {
         message_id: MessageId, // 消息ID
@@ -28,8 +123,282 @@ pub enum ServerMessage {
         group_id: UserId,
         message: String,
         timestamp: Timestamp, // 使用 i64 存储时间戳，单位为秒
+        message_type: MessageType,
+        attachment: Option<Attachment>,
+        reply_to: Option<MessageId>,
+        mentions: Vec<UserId>, // 被 @ 的群成员ID
+        mention_all: bool,     // 是否 @所有人
+        forwarded_from: Option<ForwardedFrom>,
     },
     Event(PublicEvent),
+    /// 消息已入库，仅回给发送该消息的会话；重试命中去重窗口时返回首次入库的结果
+    Ack {
+        client_msg_id: String,
+        message_id: MessageId,
+        timestamp: Timestamp,
+    },
+    /// 消息未能入库
+    Nack {
+        client_msg_id: Option<String>,
+        reason: String,
+    },
+    /// 输入状态：sender 正在 target（私聊时为接收者自己，群聊时为群号）中输入
+    Typing {
+        sender: UserId,
+        target: u32,
+        is_group: bool,
+        state: TypingState,
+    },
+    /// 已读回执：reader 已读到 up_to_message_id；conversation 为接收方视角下的会话
+    ReadReceipt {
+        reader: UserId,
+        conversation: Conversation,
+        up_to_message_id: MessageId,
+    },
+    /// 送达回执：私聊消息已被 receiver 的某个设备确认收到，只发给发送者
+    Delivered {
+        message_id: MessageId,
+        receiver: UserId,
+        timestamp: Timestamp, // 首次确认收到的时间
+    },
+    /// 消息被发送者撤回；conversation 为接收方视角下的会话
+    MessageRecalled {
+        message_id: MessageId,
+        conversation: Conversation,
+        sender: UserId,
+        timestamp: Timestamp, // 撤回时间
+    },
+    /// 消息被发送者编辑；conversation 为接收方视角下的会话
+    MessageEdited {
+        message_id: MessageId,
+        conversation: Conversation,
+        sender: UserId,
+        message: String, // 编辑后的内容
+        edited_at: Timestamp,
+    },
+    /// 表情回应变化：user 添加（added）或取消了 emoji，count 为变化后的回应人数
+    Reaction {
+        message_id: MessageId,
+        conversation: Conversation,
+        user: UserId,
+        emoji: String,
+        added: bool,
+        count: u32,
+    },
+    /// 群消息中 @ 了当前用户（含 @所有人），独立于群消息推送，不受群免打扰影响
+    Mentioned {
+        message_id: MessageId,
+        group_id: GroupId,
+        sender: UserId,
+        timestamp: Timestamp,
+        mention_all: bool,
+    },
+    /// 消息被发送者为所有人删除；conversation 为接收方视角下的会话
+    MessageDeleted {
+        message_id: MessageId,
+        conversation: Conversation,
+        sender: UserId,
+        timestamp: Timestamp, // 删除时间
+    },
+    /// 会话中的阅后即焚消息已到期并被清除；conversation 为接收方视角下的会话
+    MessagesExpired {
+        conversation: Conversation,
+        message_ids: Vec<MessageId>,
+    },
+    /// 会话的消息有效期被修改，ttl_secs 为 0 表示关闭；之后发送的消息才受影响
+    MessageTtlChanged {
+        conversation: Conversation,
+        changed_by: UserId,
+        ttl_secs: u32,
+    },
+    /// 会话的置顶消息变化：user 置顶（pinned）或取消置顶了消息；conversation 为接收方视角下的会话
+    MessagePinned {
+        message_id: MessageId,
+        conversation: Conversation,
+        user: UserId,
+        pinned: bool,
+        timestamp: Timestamp,
+    },
+}
+
+/* ---------------- 编解码辅助 ---------------- */
+
+/// 写入 u32 长度前缀的 UTF-8 字符串
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    let b = s.as_bytes();
+    out.write_u32::<BigEndian>(b.len() as u32).unwrap();
+    out.extend_from_slice(b);
+}
+
+/// 长度前缀来自对端，分配前先与剩余字节数比较，避免按伪造的长度分配内存
+fn read_str(c: &mut Cursor<&[u8]>) -> Result<String, FrameError> {
+    let len = c.read_u32::<BigEndian>()? as usize;
+    if len > remaining(c) { return Err(FrameError::Truncated); }
+    let mut buf = vec![0u8; len];
+    c.read_exact(&mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+/// 可选字符串：[present(1) | str]，只用于追加在 payload 末尾的字段
+fn write_opt_str(out: &mut Vec<u8>, s: &Option<String>) {
+    match s {
+        Some(s) => { out.push(1); write_str(out, s); }
+        None => out.push(0),
+    }
+}
+
+/// 读取追加在末尾的可选字段；旧版本客户端编码的 payload 没有这部分字节，视为 None
+fn read_opt_str(c: &mut Cursor<&[u8]>) -> Result<Option<String>, FrameError> {
+    if at_end(c) { return Ok(None); }
+    match c.read_u8()? {
+        0 => Ok(None),
+        _ => Ok(Some(read_str(c)?)),
+    }
+}
+
+/// 可选 u64：[present(1) | u64]，只用于追加在 payload 末尾的字段
+fn write_opt_u64(out: &mut Vec<u8>, v: Option<u64>) {
+    match v {
+        Some(v) => { out.push(1); out.write_u64::<BigEndian>(v).unwrap(); }
+        None => out.push(0),
+    }
+}
+
+fn read_opt_u64(c: &mut Cursor<&[u8]>) -> Result<Option<u64>, FrameError> {
+    if at_end(c) || c.read_u8()? == 0 { return Ok(None); }
+    Ok(Some(c.read_u64::<BigEndian>()?))
+}
+
+/// @ 列表：[count(u16) | user_id(u32) * count | all(1)]，只用于追加在 payload 末尾的字段
+/// 超过 MAX_MENTIONS 的部分无法编码，发送前应先经 OutgoingMessage::validate 拒绝
+fn write_mentions(out: &mut Vec<u8>, mentions: &[UserId], mention_all: bool) {
+    let count = u16::try_from(mentions.len()).unwrap_or(u16::MAX);
+    out.write_u16::<BigEndian>(count).unwrap();
+    for user_id in &mentions[..count as usize] {
+        out.write_u32::<BigEndian>(*user_id).unwrap();
+    }
+    out.push(mention_all as u8);
+}
+
+fn read_mentions(c: &mut Cursor<&[u8]>) -> Result<(Vec<UserId>, bool), FrameError> {
+    if at_end(c) { return Ok((Vec::new(), false)); }
+    let count = c.read_u16::<BigEndian>()? as usize;
+    let mut mentions = Vec::with_capacity(count);
+    for _ in 0..count {
+        mentions.push(c.read_u32::<BigEndian>()?);
+    }
+    let mention_all = c.read_u8()? != 0;
+    Ok((mentions, mention_all))
+}
+
+#[inline]
+fn at_end(c: &Cursor<&[u8]>) -> bool {
+    remaining(c) == 0
+}
+
+#[inline]
+fn remaining(c: &Cursor<&[u8]>) -> usize {
+    c.get_ref().len().saturating_sub(c.position() as usize)
+}
+
+fn typing_state_u8(state: TypingState) -> u8 {
+    match state {
+        TypingState::Started => 1,
+        TypingState::Stopped => 0,
+    }
+}
+
+fn read_typing_state(c: &mut Cursor<&[u8]>) -> Result<TypingState, FrameError> {
+    match c.read_u8()? {
+        0 => Ok(TypingState::Stopped),
+        1 => Ok(TypingState::Started),
+        x => Err(FrameError::InvalidKind(x)),
+    }
+}
+
+/// 会话：[kind(1): 0=私聊 1=群聊 | id(4)]
+fn write_conversation(out: &mut Vec<u8>, conversation: &Conversation) {
+    out.push(conversation.is_group() as u8);
+    out.write_u32::<BigEndian>(conversation.id()).unwrap();
+}
+
+fn read_conversation(c: &mut Cursor<&[u8]>) -> Result<Conversation, FrameError> {
+    let kind = c.read_u8()?;
+    let id = c.read_u32::<BigEndian>()?;
+    match kind {
+        0 => Ok(Conversation::Private(id)),
+        1 => Ok(Conversation::Group(id)),
+        x => Err(FrameError::InvalidKind(x)),
+    }
+}
+
+fn message_type_u8(t: MessageType) -> u8 {
+    match t {
+        MessageType::Text => 0,
+        MessageType::Image => 1,
+        MessageType::File => 2,
+        MessageType::Video => 3,
+        MessageType::Audio => 4,
+    }
+}
+
+/// 末尾的消息类型字段，缺失时为 text
+fn read_message_type(c: &mut Cursor<&[u8]>) -> Result<MessageType, FrameError> {
+    if at_end(c) { return Ok(MessageType::Text); }
+    match c.read_u8()? {
+        0 => Ok(MessageType::Text),
+        1 => Ok(MessageType::Image),
+        2 => Ok(MessageType::File),
+        3 => Ok(MessageType::Video),
+        4 => Ok(MessageType::Audio),
+        x => Err(FrameError::InvalidKind(x)),
+    }
+}
+
+/// 可选附件：[present(1) | object_key(str) | mime_type(str) | size(8) | mask(1) | width(4)? | height(4)? | duration_ms(8)?]
+/// mask: bit0=width bit1=height bit2=duration_ms
+fn write_opt_attachment(out: &mut Vec<u8>, a: &Option<Attachment>) {
+    let Some(a) = a else { out.push(0); return; };
+    out.push(1);
+    write_str(out, &a.object_key);
+    write_str(out, &a.mime_type);
+    out.write_u64::<BigEndian>(a.size).unwrap();
+    let mask = a.width.is_some() as u8 | (a.height.is_some() as u8) << 1 | (a.duration_ms.is_some() as u8) << 2;
+    out.push(mask);
+    if let Some(w) = a.width { out.write_u32::<BigEndian>(w).unwrap(); }
+    if let Some(h) = a.height { out.write_u32::<BigEndian>(h).unwrap(); }
+    if let Some(d) = a.duration_ms { out.write_u64::<BigEndian>(d).unwrap(); }
+}
+
+fn read_opt_attachment(c: &mut Cursor<&[u8]>) -> Result<Option<Attachment>, FrameError> {
+    if at_end(c) || c.read_u8()? == 0 { return Ok(None); }
+    let object_key = read_str(c)?;
+    let mime_type = read_str(c)?;
+    let size = c.read_u64::<BigEndian>()?;
+    let mask = c.read_u8()?;
+    let width = if mask & 0b001 != 0 { Some(c.read_u32::<BigEndian>()?) } else { None };
+    let height = if mask & 0b010 != 0 { Some(c.read_u32::<BigEndian>()?) } else { None };
+    let duration_ms = if mask & 0b100 != 0 { Some(c.read_u64::<BigEndian>()?) } else { None };
+    Ok(Some(Attachment { object_key, mime_type, size, width, height, duration_ms }))
+}
+
+/// 可选转发来源：[present(1) | is_group(1) | message_id(8) | sender_id(4) | timestamp(8)]，只用于追加在 payload 末尾的字段
+fn write_opt_forwarded(out: &mut Vec<u8>, f: &Option<ForwardedFrom>) {
+    let Some(f) = f else { out.push(0); return; };
+    out.push(1);
+    out.push(f.is_group as u8);
+    out.write_u64::<BigEndian>(f.message_id).unwrap();
+    out.write_u32::<BigEndian>(f.sender_id).unwrap();
+    out.write_i64::<BigEndian>(f.timestamp).unwrap();
+}
+
+fn read_opt_forwarded(c: &mut Cursor<&[u8]>) -> Result<Option<ForwardedFrom>, FrameError> {
+    if at_end(c) || c.read_u8()? == 0 { return Ok(None); }
+    let is_group = c.read_u8()? != 0;
+    let message_id = c.read_u64::<BigEndian>()?;
+    let sender_id = c.read_u32::<BigEndian>()?;
+    let timestamp = c.read_i64::<BigEndian>()?;
+    Ok(Some(ForwardedFrom { is_group, message_id, sender_id, timestamp }))
 }
 
 /* ---------------- ClientMessage: C2S ---------------- */
@@ -40,22 +409,46 @@ impl ClientMessage {
         match self {
             ClientMessage::SendMessage { .. } => 0,
             ClientMessage::SendGroupMessage { .. } => 1,
+            ClientMessage::Typing { .. } => 2,
+            ClientMessage::MarkRead { .. } => 3,
+            ClientMessage::Received { .. } => 4,
         }
     }
 
     fn encode_payload(&self, out: &mut Vec<u8>) {
         match self {
-            ClientMessage::SendMessage { receiver, message } => {
+            ClientMessage::SendMessage { receiver, message, client_msg_id, message_type, attachment, reply_to } => {
                 out.write_u32::<BigEndian>(*receiver).unwrap();
                 let m = message.as_bytes();
                 out.write_u32::<BigEndian>(m.len() as u32).unwrap();
                 out.extend_from_slice(m);
+                write_opt_str(out, client_msg_id);
+                out.push(message_type_u8(*message_type));
+                write_opt_attachment(out, attachment);
+                write_opt_u64(out, *reply_to);
             }
-            ClientMessage::SendGroupMessage { group_id, message } => {
+            ClientMessage::SendGroupMessage { group_id, message, client_msg_id, message_type, attachment, reply_to, mentions, mention_all } => {
                 out.write_u32::<BigEndian>(*group_id).unwrap();
                 let m = message.as_bytes();
                 out.write_u32::<BigEndian>(m.len() as u32).unwrap();
                 out.extend_from_slice(m);
+                write_opt_str(out, client_msg_id);
+                out.push(message_type_u8(*message_type));
+                write_opt_attachment(out, attachment);
+                write_opt_u64(out, *reply_to);
+                write_mentions(out, mentions, *mention_all);
+            }
+            ClientMessage::Typing { target, is_group, state } => {
+                out.write_u32::<BigEndian>(*target).unwrap();
+                out.push(*is_group as u8);
+                out.push(typing_state_u8(*state));
+            }
+            ClientMessage::MarkRead { conversation, up_to_message_id } => {
+                write_conversation(out, conversation);
+                out.write_u64::<BigEndian>(*up_to_message_id).unwrap();
+            }
+            ClientMessage::Received { message_id } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
             }
         }
     }
@@ -64,19 +457,37 @@ impl ClientMessage {
         match kind {
             0 => {
                 let receiver = c.read_u32::<BigEndian>()?;
-                let len = c.read_u32::<BigEndian>()? as usize;
-                let mut buf = vec![0u8; len];
-                c.read_exact(&mut buf)?;
-                let message = String::from_utf8(buf)?;
-                Ok(ClientMessage::SendMessage { receiver, message })
+                let message = read_str(&mut c)?;
+                let client_msg_id = read_opt_str(&mut c)?;
+                let message_type = read_message_type(&mut c)?;
+                let attachment = read_opt_attachment(&mut c)?;
+                let reply_to = read_opt_u64(&mut c)?;
+                Ok(ClientMessage::SendMessage { receiver, message, client_msg_id, message_type, attachment, reply_to })
             }
             1 => {
                 let group_id = c.read_u32::<BigEndian>()?;
-                let len = c.read_u32::<BigEndian>()? as usize;
-                let mut buf = vec![0u8; len];
-                c.read_exact(&mut buf)?;
-                let message = String::from_utf8(buf)?;
-                Ok(ClientMessage::SendGroupMessage { group_id, message })
+                let message = read_str(&mut c)?;
+                let client_msg_id = read_opt_str(&mut c)?;
+                let message_type = read_message_type(&mut c)?;
+                let attachment = read_opt_attachment(&mut c)?;
+                let reply_to = read_opt_u64(&mut c)?;
+                let (mentions, mention_all) = read_mentions(&mut c)?;
+                Ok(ClientMessage::SendGroupMessage { group_id, message, client_msg_id, message_type, attachment, reply_to, mentions, mention_all })
+            }
+            2 => {
+                let target = c.read_u32::<BigEndian>()?;
+                let is_group = c.read_u8()? != 0;
+                let state = read_typing_state(&mut c)?;
+                Ok(ClientMessage::Typing { target, is_group, state })
+            }
+            3 => {
+                let conversation = read_conversation(&mut c)?;
+                let up_to_message_id = c.read_u64::<BigEndian>()?;
+                Ok(ClientMessage::MarkRead { conversation, up_to_message_id })
+            }
+            4 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                Ok(ClientMessage::Received { message_id })
             }
             x => Err(FrameError::InvalidKind(x)),
         }
@@ -107,12 +518,25 @@ impl ServerMessage {
             ServerMessage::SendMessage { .. } => 0,
             ServerMessage::SendGroupMessage { .. } => 1,
             ServerMessage::Event(_) => 2,
+            ServerMessage::Ack { .. } => 3,
+            ServerMessage::Nack { .. } => 4,
+            ServerMessage::Typing { .. } => 5,
+            ServerMessage::ReadReceipt { .. } => 6,
+            ServerMessage::Delivered { .. } => 7,
+            ServerMessage::MessageRecalled { .. } => 8,
+            ServerMessage::MessageEdited { .. } => 9,
+            ServerMessage::Reaction { .. } => 10,
+            ServerMessage::Mentioned { .. } => 11,
+            ServerMessage::MessageDeleted { .. } => 12,
+            ServerMessage::MessagesExpired { .. } => 13,
+            ServerMessage::MessageTtlChanged { .. } => 14,
+            ServerMessage::MessagePinned { .. } => 15,
         }
     }
 
     fn encode_payload(&self, out: &mut Vec<u8>) {
         match self {
-            ServerMessage::SendMessage { message_id, sender, receiver, message, timestamp } => {
+            ServerMessage::SendMessage { message_id, sender, receiver, message, timestamp, message_type, attachment, reply_to, forwarded_from } => {
                 out.write_u64::<BigEndian>(*message_id).unwrap();
                 out.write_u32::<BigEndian>(*sender).unwrap();
                 out.write_u32::<BigEndian>(*receiver).unwrap();
@@ -120,8 +544,12 @@ impl ServerMessage {
                 let m = message.as_bytes();
                 out.write_u32::<BigEndian>(m.len() as u32).unwrap();
                 out.extend_from_slice(m);
+                out.push(message_type_u8(*message_type));
+                write_opt_attachment(out, attachment);
+                write_opt_u64(out, *reply_to);
+                write_opt_forwarded(out, forwarded_from);
             }
-            ServerMessage::SendGroupMessage { message_id, sender, group_id, message, timestamp } => {
+            ServerMessage::SendGroupMessage { message_id, sender, group_id, message, timestamp, message_type, attachment, reply_to, mentions, mention_all, forwarded_from } => {
                 out.write_u64::<BigEndian>(*message_id).unwrap();
                 out.write_u32::<BigEndian>(*sender).unwrap();
                 out.write_u32::<BigEndian>(*group_id).unwrap();
@@ -129,11 +557,94 @@ impl ServerMessage {
                 let m = message.as_bytes();
                 out.write_u32::<BigEndian>(m.len() as u32).unwrap();
                 out.extend_from_slice(m);
+                out.push(message_type_u8(*message_type));
+                write_opt_attachment(out, attachment);
+                write_opt_u64(out, *reply_to);
+                write_mentions(out, mentions, *mention_all);
+                write_opt_forwarded(out, forwarded_from);
             }
             ServerMessage::Event(ev) => {
                 let bytes = ev.to_bytes();
                 out.extend_from_slice(&bytes);
             }
+            ServerMessage::Ack { client_msg_id, message_id, timestamp } => {
+                write_str(out, client_msg_id);
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+            }
+            ServerMessage::Nack { client_msg_id, reason } => {
+                write_str(out, reason);
+                write_opt_str(out, client_msg_id);
+            }
+            ServerMessage::Typing { sender, target, is_group, state } => {
+                out.write_u32::<BigEndian>(*sender).unwrap();
+                out.write_u32::<BigEndian>(*target).unwrap();
+                out.push(*is_group as u8);
+                out.push(typing_state_u8(*state));
+            }
+            ServerMessage::ReadReceipt { reader, conversation, up_to_message_id } => {
+                out.write_u32::<BigEndian>(*reader).unwrap();
+                write_conversation(out, conversation);
+                out.write_u64::<BigEndian>(*up_to_message_id).unwrap();
+            }
+            ServerMessage::Delivered { message_id, receiver, timestamp } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                out.write_u32::<BigEndian>(*receiver).unwrap();
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+            }
+            ServerMessage::MessageRecalled { message_id, conversation, sender, timestamp } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*sender).unwrap();
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+            }
+            ServerMessage::MessageEdited { message_id, conversation, sender, message, edited_at } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*sender).unwrap();
+                out.write_i64::<BigEndian>(*edited_at).unwrap();
+                write_str(out, message);
+            }
+            ServerMessage::Reaction { message_id, conversation, user, emoji, added, count } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*user).unwrap();
+                out.push(*added as u8);
+                out.write_u32::<BigEndian>(*count).unwrap();
+                write_str(out, emoji);
+            }
+            ServerMessage::Mentioned { message_id, group_id, sender, timestamp, mention_all } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                out.write_u32::<BigEndian>(*group_id).unwrap();
+                out.write_u32::<BigEndian>(*sender).unwrap();
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+                out.push(*mention_all as u8);
+            }
+            ServerMessage::MessageDeleted { message_id, conversation, sender, timestamp } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*sender).unwrap();
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+            }
+            ServerMessage::MessagesExpired { conversation, message_ids } => {
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(message_ids.len() as u32).unwrap();
+                for message_id in message_ids {
+                    out.write_u64::<BigEndian>(*message_id).unwrap();
+                }
+            }
+            ServerMessage::MessageTtlChanged { conversation, changed_by, ttl_secs } => {
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*changed_by).unwrap();
+                out.write_u32::<BigEndian>(*ttl_secs).unwrap();
+            }
+            ServerMessage::MessagePinned { message_id, conversation, user, pinned, timestamp } => {
+                out.write_u64::<BigEndian>(*message_id).unwrap();
+                write_conversation(out, conversation);
+                out.write_u32::<BigEndian>(*user).unwrap();
+                out.push(*pinned as u8);
+                out.write_i64::<BigEndian>(*timestamp).unwrap();
+            }
         }
     }
 
@@ -144,22 +655,25 @@ impl ServerMessage {
                 let sender = c.read_u32::<BigEndian>()?;
                 let receiver = c.read_u32::<BigEndian>()?;
                 let timestamp = c.read_i64::<BigEndian>()?;
-                let len = c.read_u32::<BigEndian>()? as usize;
-                let mut buf = vec![0u8; len];
-                c.read_exact(&mut buf)?;
-                let message = String::from_utf8(buf)?;
-                Ok(ServerMessage::SendMessage { message_id, sender, receiver, message, timestamp })
+                let message = read_str(&mut c)?;
+                let message_type = read_message_type(&mut c)?;
+                let attachment = read_opt_attachment(&mut c)?;
+                let reply_to = read_opt_u64(&mut c)?;
+                let forwarded_from = read_opt_forwarded(&mut c)?;
+                Ok(ServerMessage::SendMessage { message_id, sender, receiver, message, timestamp, message_type, attachment, reply_to, forwarded_from })
             }
             1 => {
                 let message_id = c.read_u64::<BigEndian>()?;
                 let sender = c.read_u32::<BigEndian>()?;
                 let group_id = c.read_u32::<BigEndian>()?;
                 let timestamp = c.read_i64::<BigEndian>()?;
-                let len = c.read_u32::<BigEndian>()? as usize;
-                let mut buf = vec![0u8; len];
-                c.read_exact(&mut buf)?;
-                let message = String::from_utf8(buf)?;
-                Ok(ServerMessage::SendGroupMessage { message_id, sender, group_id, message, timestamp })
+                let message = read_str(&mut c)?;
+                let message_type = read_message_type(&mut c)?;
+                let attachment = read_opt_attachment(&mut c)?;
+                let reply_to = read_opt_u64(&mut c)?;
+                let (mentions, mention_all) = read_mentions(&mut c)?;
+                let forwarded_from = read_opt_forwarded(&mut c)?;
+                Ok(ServerMessage::SendGroupMessage { message_id, sender, group_id, message, timestamp, message_type, attachment, reply_to, mentions, mention_all, forwarded_from })
             }
             2 => {
                 // 剩余 payload 全部属于 PublicEvent
@@ -170,6 +684,98 @@ impl ServerMessage {
                 let ev = PublicEvent::from_bytes(remaining)?;
                 Ok(ServerMessage::Event(ev))
             }
+            3 => {
+                let client_msg_id = read_str(&mut c)?;
+                let message_id = c.read_u64::<BigEndian>()?;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                Ok(ServerMessage::Ack { client_msg_id, message_id, timestamp })
+            }
+            4 => {
+                let reason = read_str(&mut c)?;
+                let client_msg_id = read_opt_str(&mut c)?;
+                Ok(ServerMessage::Nack { client_msg_id, reason })
+            }
+            5 => {
+                let sender = c.read_u32::<BigEndian>()?;
+                let target = c.read_u32::<BigEndian>()?;
+                let is_group = c.read_u8()? != 0;
+                let state = read_typing_state(&mut c)?;
+                Ok(ServerMessage::Typing { sender, target, is_group, state })
+            }
+            6 => {
+                let reader = c.read_u32::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let up_to_message_id = c.read_u64::<BigEndian>()?;
+                Ok(ServerMessage::ReadReceipt { reader, conversation, up_to_message_id })
+            }
+            7 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let receiver = c.read_u32::<BigEndian>()?;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                Ok(ServerMessage::Delivered { message_id, receiver, timestamp })
+            }
+            8 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let sender = c.read_u32::<BigEndian>()?;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                Ok(ServerMessage::MessageRecalled { message_id, conversation, sender, timestamp })
+            }
+            9 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let sender = c.read_u32::<BigEndian>()?;
+                let edited_at = c.read_i64::<BigEndian>()?;
+                let message = read_str(&mut c)?;
+                Ok(ServerMessage::MessageEdited { message_id, conversation, sender, message, edited_at })
+            }
+            10 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let user = c.read_u32::<BigEndian>()?;
+                let added = c.read_u8()? != 0;
+                let count = c.read_u32::<BigEndian>()?;
+                let emoji = read_str(&mut c)?;
+                Ok(ServerMessage::Reaction { message_id, conversation, user, emoji, added, count })
+            }
+            11 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let group_id = c.read_u32::<BigEndian>()?;
+                let sender = c.read_u32::<BigEndian>()?;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                let mention_all = c.read_u8()? != 0;
+                Ok(ServerMessage::Mentioned { message_id, group_id, sender, timestamp, mention_all })
+            }
+            12 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let sender = c.read_u32::<BigEndian>()?;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                Ok(ServerMessage::MessageDeleted { message_id, conversation, sender, timestamp })
+            }
+            13 => {
+                let conversation = read_conversation(&mut c)?;
+                let count = c.read_u32::<BigEndian>()? as usize;
+                let mut message_ids = Vec::with_capacity(count);
+                for _ in 0..count {
+                    message_ids.push(c.read_u64::<BigEndian>()?);
+                }
+                Ok(ServerMessage::MessagesExpired { conversation, message_ids })
+            }
+            14 => {
+                let conversation = read_conversation(&mut c)?;
+                let changed_by = c.read_u32::<BigEndian>()?;
+                let ttl_secs = c.read_u32::<BigEndian>()?;
+                Ok(ServerMessage::MessageTtlChanged { conversation, changed_by, ttl_secs })
+            }
+            15 => {
+                let message_id = c.read_u64::<BigEndian>()?;
+                let conversation = read_conversation(&mut c)?;
+                let user = c.read_u32::<BigEndian>()?;
+                let pinned = c.read_u8()? != 0;
+                let timestamp = c.read_i64::<BigEndian>()?;
+                Ok(ServerMessage::MessagePinned { message_id, conversation, user, pinned, timestamp })
+            }
             x => Err(FrameError::InvalidKind(x)),
         }
     }
@@ -189,3 +795,133 @@ impl FrameCodec for ServerMessage {
         Self::decode_payload(kind, Cursor::new(payload))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{ActorKind, content::public::PublicEventContent};
+
+    /// 编码后再解码，Debug 输出一致即视为各字段都原样往返
+    fn roundtrip<M: FrameCodec + std::fmt::Debug>(msg: M) {
+        let frame = msg.to_frame().unwrap();
+        let decoded = M::from_frame(&frame).unwrap();
+        assert_eq!(decoded.kind(), msg.kind());
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", msg));
+    }
+
+    #[test]
+    fn client_message_roundtrip() {
+        roundtrip(ClientMessage::Typing { target: 7, is_group: true, state: TypingState::Started });
+        roundtrip(ClientMessage::Typing { target: 8, is_group: false, state: TypingState::Stopped });
+        roundtrip(ClientMessage::MarkRead { conversation: Conversation::Private(2), up_to_message_id: 42 });
+        roundtrip(ClientMessage::MarkRead { conversation: Conversation::Group(3), up_to_message_id: u64::MAX });
+        roundtrip(ClientMessage::Received { message_id: 99 });
+    }
+
+    #[test]
+    fn server_message_roundtrip() {
+        let group = Conversation::Group(10_000_001);
+        let private = Conversation::Private(10_000_002);
+        let messages = vec![
+            ServerMessage::Event(PublicEvent {
+                event_id: 1,
+                timestamp: 1_700_000_000_000,
+                actor: ActorKind::User,
+                event_name: PublicEventContent::LoginOut,
+            }),
+            ServerMessage::Ack { client_msg_id: "c-1".to_string(), message_id: 5, timestamp: 1_700_000_000_001 },
+            ServerMessage::Nack { client_msg_id: Some("c-2".to_string()), reason: "消息内容不能为空".to_string() },
+            ServerMessage::Nack { client_msg_id: None, reason: String::new() },
+            ServerMessage::Typing { sender: 2, target: 3, is_group: false, state: TypingState::Started },
+            ServerMessage::ReadReceipt { reader: 2, conversation: private, up_to_message_id: 6 },
+            ServerMessage::Delivered { message_id: 7, receiver: 3, timestamp: 1_700_000_000_002 },
+            ServerMessage::MessageRecalled { message_id: 8, conversation: group, sender: 2, timestamp: 1_700_000_000_003 },
+            ServerMessage::MessageEdited {
+                message_id: 9,
+                conversation: private,
+                sender: 2,
+                message: "编辑后的内容".to_string(),
+                edited_at: 1_700_000_000_004,
+            },
+            ServerMessage::Reaction {
+                message_id: 10,
+                conversation: group,
+                user: 4,
+                emoji: "👍".to_string(),
+                added: true,
+                count: 3,
+            },
+            ServerMessage::Reaction {
+                message_id: 10,
+                conversation: private,
+                user: 4,
+                emoji: "❤️".to_string(),
+                added: false,
+                count: 0,
+            },
+            ServerMessage::Mentioned {
+                message_id: 11,
+                group_id: 10_000_001,
+                sender: 2,
+                timestamp: 1_700_000_000_005,
+                mention_all: true,
+            },
+            ServerMessage::MessageDeleted { message_id: 12, conversation: group, sender: 2, timestamp: 1_700_000_000_006 },
+            ServerMessage::MessagesExpired { conversation: private, message_ids: vec![13, 14, 15] },
+            ServerMessage::MessagesExpired { conversation: group, message_ids: Vec::new() },
+            ServerMessage::MessageTtlChanged { conversation: group, changed_by: 2, ttl_secs: 86_400 },
+            ServerMessage::MessagePinned {
+                message_id: 16,
+                conversation: private,
+                user: 3,
+                pinned: true,
+                timestamp: 1_700_000_000_007,
+            },
+        ];
+        let mut kinds: Vec<u8> = messages.iter().map(|m| m.kind()).collect();
+        kinds.dedup();
+        assert_eq!(kinds, (2..=15).collect::<Vec<u8>>());
+        for msg in messages {
+            roundtrip(msg);
+        }
+    }
+
+    #[test]
+    fn forged_string_length_is_rejected_before_allocation() {
+        // receiver + 声称 4GiB 的消息体，实际只有 2 字节
+        let mut payload = 2u32.to_be_bytes().to_vec();
+        payload.extend_from_slice(&u32::MAX.to_be_bytes());
+        payload.extend_from_slice(b"hi");
+        assert!(matches!(ClientMessage::from_bytes(0, &payload), Err(FrameError::Truncated)));
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&1u64.to_be_bytes());
+        payload.extend_from_slice(&1u32.to_be_bytes());
+        payload.extend_from_slice(&2u32.to_be_bytes());
+        payload.extend_from_slice(&0i64.to_be_bytes());
+        payload.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert!(matches!(ServerMessage::from_bytes(0, &payload), Err(FrameError::Truncated)));
+        assert!(matches!(ServerMessage::from_bytes(1, &payload), Err(FrameError::Truncated)));
+    }
+
+    #[test]
+    fn oversized_mentions_stay_decodable() {
+        let msg = ClientMessage::SendGroupMessage {
+            group_id: 1,
+            message: "hi".to_string(),
+            client_msg_id: None,
+            message_type: MessageType::Text,
+            attachment: None,
+            reply_to: None,
+            mentions: (0..MAX_MENTIONS as u32 + 2).collect(),
+            mention_all: true,
+        };
+        match ClientMessage::from_bytes(msg.kind(), &msg.to_bytes()).unwrap() {
+            ClientMessage::SendGroupMessage { mentions, mention_all, .. } => {
+                assert_eq!(mentions.len(), MAX_MENTIONS);
+                assert!(mention_all);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
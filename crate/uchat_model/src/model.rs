@@ -51,15 +51,116 @@ pub struct GroupSimpleInfo {
 pub struct GroupDetailedInfo {
     pub group_id: GroupId,
     pub title: String,
+    #[serde(default)]
+    pub pinned: Vec<PinnedMessage>, // 群置顶消息，只对群成员返回
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+/// 会话中被置顶的消息，message 等字段为消息当前的内容
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct PinnedMessage {
+    pub message_id: MessageId,
+    pub sender_id: UserId,
+    pub message_type: MessageType,
+    pub message: String,
+    pub timestamp: Timestamp, // 消息的发送时间
+    pub pinned_by: UserId,
+    pub pinned_at: Timestamp,
+}
+
+/// 消息附件（图片/文件/视频/音频），文件本体存放在对象存储中，数据库中以 JSON 列保存
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Attachment {
+    pub object_key: String, // 对象存储中的 key
+    pub mime_type: String,
+    pub size: u64, // 字节数
+    #[serde(default)]
+    pub width: Option<u32>, // 图片/视频宽度（像素）
+    #[serde(default)]
+    pub height: Option<u32>, // 图片/视频高度（像素）
+    #[serde(default)]
+    pub duration_ms: Option<u64>, // 音频/视频时长（毫秒）
+}
+
+/// 转发来源；转发已转发过的消息时沿用最初的来源
+/// 私聊与群聊消息ID各自独立，需结合 is_group 区分
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ForwardedFrom {
+    pub is_group: bool,
+    pub message_id: MessageId,
+    pub sender_id: UserId, // 原消息的发送者
+    pub timestamp: Timestamp, // 原消息的发送时间
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct SessionMessage {
     pub message_id: MessageId,
     pub message_type: MessageType, // enum 类型更安全
     pub sender_id: UserId,
     pub message: String,
     pub timestamp: Timestamp,
+    #[sqlx(json(nullable))]
+    #[serde(default)]
+    pub attachment: Option<Attachment>, // 非文本消息的附件信息
+    #[serde(default)]
+    pub edited_at: Option<Timestamp>, // 最后一次编辑的时间，未编辑过为 None
+    #[serde(default)]
+    pub recalled_at: Option<Timestamp>, // 撤回时间，撤回后 message 为空、attachment 为 None
+    #[serde(default)]
+    pub reply_to: Option<MessageId>, // 引用回复的消息ID
+    #[serde(default)]
+    pub expires_at: Option<Timestamp>, // 阅后即焚消息的到期时间，到期后消息被清除
+    #[sqlx(json(nullable))]
+    #[serde(default)]
+    pub forwarded_from: Option<ForwardedFrom>, // 转发来源，非转发消息为 None
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub reactions: Vec<ReactionCount>, // 表情回应统计，按首次回应时间排序
+}
+
+/// 按消息ID游标分页的聊天记录，messages 按消息ID递增
+/// next_cursor 为继续翻页时使用的游标（向前翻页时是本页最早的消息ID，向后时是最新的），没有更多消息时为 None
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MessagePage {
+    pub messages: Vec<SessionMessage>,
+    pub next_cursor: Option<MessageId>,
+}
+
+/// 搜索结果中关键词命中的区间，以字符（Unicode 标量值）计，左闭右开
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct HighlightRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// 一条命中的消息；conversation 为调用者视角下的会话
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchHit {
+    pub conversation: Conversation,
+    pub message: SessionMessage,
+    pub highlights: Vec<HighlightRange>, // message.message 中命中关键词的区间，按起点递增且互不重叠
+}
+
+/// 搜索结果分页，按时间递减；next_offset 为下一页的 offset，没有更多结果时为 None
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    pub next_offset: Option<u32>,
+}
+
+/// 群聊话题：根消息及直接回复它的消息（消息ID递增，按游标分页）
+/// next_cursor 的含义与 MessagePage 相同
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MessageThread {
+    pub root: SessionMessage,
+    pub replies: Vec<SessionMessage>,
+    pub next_cursor: Option<MessageId>,
+}
+
+/// 消息上某个表情的回应人数
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: u32,
 }
 
 #[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
@@ -70,6 +171,8 @@ pub struct GroupSessionMessage {
     pub sender_id: UserId,
     pub timestamp: Timestamp,
     pub message: String,
+    #[sqlx(json(nullable))]
+    pub attachment: Option<Attachment>,
 }
 
 /// 用于manager后台获取消息
@@ -97,8 +200,160 @@ pub struct FullPrivateMessage {
     pub timestamp: Timestamp,
 }
 
+/// 用于manager后台获取群消息
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct PreviewGroupMessage {
+    pub id: MessageId,
+    pub group_id: GroupId,
+    pub group_name: String,
+    pub sender_id: UserId,
+    pub sender_username: String,
+    pub message_type: MessageType,
+    pub message_preview: String, // message 前 100 字符
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct FullGroupMessage {
+    pub id: MessageId,
+    pub group_id: GroupId,
+    pub group_name: String,
+    pub sender_id: UserId,
+    pub sender_username: String,
+    pub message_type: MessageType,
+    pub message: String, // 完整消息内容
+    pub timestamp: Timestamp,
+}
+
+/// 会话标识：私聊以对方用户ID表示，群聊以群号表示
+/// JSON 形如 {"kind": "private", "id": 2}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", content = "id", rename_all = "lowercase")]
+pub enum Conversation {
+    Private(UserId),
+    Group(GroupId),
+}
+
+impl Conversation {
+    /// 数据库中 conversation_type 列的取值
+    pub fn kind_str(&self) -> &'static str {
+        match self {
+            Conversation::Private(_) => "private",
+            Conversation::Group(_) => "group",
+        }
+    }
+
+    /// 对方用户ID 或 群号
+    pub fn id(&self) -> u32 {
+        match self {
+            Conversation::Private(id) | Conversation::Group(id) => *id,
+        }
+    }
+
+    pub fn is_group(&self) -> bool {
+        matches!(self, Conversation::Group(_))
+    }
+
+    /// 由 conversation_type 列与 ID 还原
+    pub fn from_parts(kind: &str, id: u32) -> Option<Self> {
+        match kind {
+            "private" => Some(Conversation::Private(id)),
+            "group" => Some(Conversation::Group(id)),
+            _ => None,
+        }
+    }
+}
+
+/// 消息的一次编辑记录，old_message 为编辑前的内容
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct MessageEdit {
+    pub editor_id: UserId,
+    pub old_message: String,
+    pub edited_at: Timestamp,
+}
+
+/// 某个会话的未读数，由已读游标推导
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UnreadCount {
+    pub conversation: Conversation,
+    pub last_read_message_id: MessageId, // 已读游标，0 表示从未读过
+    pub unread: u32,                     // 游标之后他人发送的消息数
+}
+
+/// 会话列表中最后一条消息的预览
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MessagePreview {
+    pub message_id: MessageId,
+    pub sender_id: UserId,
+    pub message_type: MessageType,
+    pub message: String, // 消息内容的前若干个字符，非文本消息与已撤回的消息为空
+    pub recalled: bool,
+}
+
+/// 会话列表中的一项；conversation 为当前用户视角下的会话
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConversationSummary {
+    pub conversation: Conversation,
+    pub title: String,                        // 对方用户名或群名
+    pub avatar_url: Option<String>,           // 对方头像或群头像
+    pub last_message: Option<MessagePreview>, // 会话中还没有消息时为 None
+    pub last_timestamp: Option<Timestamp>,
+    pub unread: u32,
+    pub pinned: bool,
+    pub muted: bool,
+    pub message_ttl_secs: Option<u32>, // 会话开启阅后即焚时新消息的有效期（秒）
+}
+
+/// 用户对某个会话的个人设置
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+pub struct ConversationSettings {
+    pub pinned: bool, // 置顶
+    pub muted: bool,  // 免打扰，只影响客户端的通知提醒，@ 通知不受影响
+}
+
+/// 定时消息的状态，取消的定时消息直接删除
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize, ToSchema)]
-#[sqlx(type_name = "ENUM", rename_all = "lowercase")]
+#[cfg_attr(feature = "mysql", sqlx(type_name = "text"))]
+#[cfg_attr(feature = "postgres", sqlx(type_name = "scheduled_status"))]
+#[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledStatus {
+    Pending, // 等待发送，只有这一状态可以修改或取消
+    Sending, // 已被后台任务领取，正在投递
+    Sent,
+    Failed,
+}
+
+/// 定时消息；conversation 为发送者视角下的会话
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledMessage {
+    pub id: u64,
+    pub sender_id: UserId,
+    pub conversation: Conversation,
+    pub message_type: MessageType,
+    pub message: String,
+    pub attachment: Option<Attachment>,
+    pub reply_to: Option<MessageId>,
+    pub mentions: Vec<UserId>, // 仅群消息使用
+    pub mention_all: bool,
+    pub send_at: Timestamp, // 计划发送时间
+    pub created_at: Timestamp,
+    pub status: ScheduledStatus,
+    pub message_id: Option<MessageId>, // 发送成功后的消息ID
+    pub failure_reason: Option<String>, // 发送失败的原因
+}
+
+/// 提及（@）当前用户且位于已读游标之后的群消息
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UnreadMention {
+    pub group_id: GroupId,
+    pub message: SessionMessage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "mysql", sqlx(type_name = "ENUM"))]
+#[cfg_attr(feature = "postgres", sqlx(type_name = "role_type"))]
+#[sqlx(rename_all = "lowercase")]
 #[serde(rename_all = "PascalCase")]
 pub enum RoleType {
     #[sqlx(rename = "user")]
@@ -139,11 +394,12 @@ impl RoleType {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Type, Serialize, Deserialize, ToSchema)]
 #[cfg_attr(feature = "mysql", sqlx(type_name = "text"))]
 #[cfg_attr(feature = "postgres", sqlx(type_name = "message_type"))]
 #[sqlx(rename_all = "lowercase")]
 pub enum MessageType {
+    #[default]
     #[serde(rename = "text")]
     #[sqlx(rename = "text")]
     Text,
@@ -189,6 +445,62 @@ impl fmt::Display for MessageType {
     }
 }
 
+/// 导出的一条消息；sender_name 与 attachment_url 在导出时解析
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportedMessage {
+    pub message_id: MessageId,
+    pub sender_id: UserId,
+    pub sender_name: String,
+    pub timestamp: Timestamp,
+    pub message_type: MessageType,
+    pub message: String,
+    #[serde(default)]
+    pub attachment: Option<Attachment>,
+    #[serde(default)]
+    pub attachment_url: Option<String>,
+    #[serde(default)]
+    pub reply_to: Option<MessageId>,
+    #[serde(default)]
+    pub forwarded_from: Option<ForwardedFrom>,
+    #[serde(default)]
+    pub edited_at: Option<Timestamp>,
+}
+
+/// 导出的一个会话；conversation 为导出者视角下的会话
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportedConversation {
+    pub conversation: Conversation,
+    pub title: String, // 对方用户名或群名
+    pub messages: Vec<ExportedMessage>,
+}
+
+/// JSON 格式的导出文件，也是导入时接受的格式
+/// 只导出单个会话时 friends 与 groups 为空
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportDocument {
+    pub version: u32,
+    pub exported_at: Timestamp,
+    pub user_id: UserId,
+    pub username: String,
+    #[serde(default)]
+    pub friends: Vec<UserSimpleInfo>,
+    #[serde(default)]
+    pub groups: Vec<GroupSimpleInfo>,
+    pub conversations: Vec<ExportedConversation>,
+}
+
+impl ExportDocument {
+    /// 导出文件的格式版本，导入时据此判断是否兼容
+    pub const VERSION: u32 = 1;
+}
+
+/// 体积较大的导出文件保存到对象存储后返回的信息
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportedFile {
+    pub url: String,
+    pub size: u64, // 字节数
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct Empty;
 
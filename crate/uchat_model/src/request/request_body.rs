@@ -1,3 +1,7 @@
+use crate::{
+    Attachment, Conversation, GroupId, MessageId, MessageType, Timestamp, UserId,
+    message::WsEncoding,
+};
 use serde::Deserialize;
 use utoipa::{IntoParams, ToSchema};
 
@@ -36,11 +40,14 @@ pub struct CreateGroupRequest {
     pub members: Vec<u32>, // 成员ID列表
 }
 
-/// 获取聊天记录的请求
+/// 获取聊天记录的请求，按消息ID游标分页
+/// before_id 与 after_id 都缺省时返回最新的一页，二者不能同时指定
 #[derive(Deserialize, Debug, IntoParams)]
 pub struct MessageRequest {
-    pub id: u32,     // 用户ID或群组ID
-    pub offset: u32, // 偏移量，用于分页
+    pub id: u32,                      // 用户ID或群组ID
+    pub before_id: Option<MessageId>, // 取早于该消息的一页，用于向前翻历史
+    pub after_id: Option<MessageId>,  // 取晚于该消息的一页，用于补齐新消息
+    pub limit: Option<u32>,           // 每页条数，缺省 30，服务端限制最大 100
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -58,7 +65,180 @@ pub struct CheckStatusRequest {
     pub user_ids: Vec<u32>, // 用户ID列表
 }
 
+/// 撤回消息，conversation 为请求者视角下的会话（私聊为对方用户ID）
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RecallMessageRequest {
+    pub conversation: Conversation,
+    pub message_id: MessageId,
+}
+
+/// 编辑消息，只支持文本消息
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditMessageRequest {
+    pub conversation: Conversation,
+    pub message_id: MessageId,
+    pub message: String, // 新的消息内容
+}
+
+/// 删除消息：默认仅对自己隐藏，for_everyone 为 true 时由发送者为所有人删除
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteMessageRequest {
+    pub conversation: Conversation,
+    pub message_id: MessageId,
+    #[serde(default)]
+    pub for_everyone: bool,
+}
+
+/// 将 source 会话中的若干消息转发到 target 会话，两者均为请求者视角下的会话
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ForwardMessagesRequest {
+    pub source: Conversation,
+    pub message_ids: Vec<MessageId>,
+    pub target: Conversation,
+}
+
+/// 添加或取消表情回应，conversation 为请求者视角下的会话（私聊为对方用户ID）
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReactionRequest {
+    pub conversation: Conversation,
+    pub message_id: MessageId,
+    pub emoji: String,
+}
+
+/// 查询消息的编辑历史
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MessageEditsQuery {
+    pub message_id: MessageId,
+    #[serde(default)]
+    pub is_group: bool, // 群聊消息与私聊消息的ID各自独立
+}
+
+/// 话题回复的分页参数，与聊天记录相同按消息ID游标分页
+/// 都缺省时返回最新的一页，after_id 为 0 时返回最早的一页
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ThreadQuery {
+    pub before_id: Option<MessageId>, // 取早于该回复的一页
+    pub after_id: Option<MessageId>,  // 取晚于该回复的一页
+    pub limit: Option<u32>,           // 每页条数，缺省 30，服务端限制最大 100
+}
+
+/// 修改会话的置顶、免打扰设置，缺省的字段保持不变
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateConversationSettingsRequest {
+    pub conversation: Conversation,
+    pub pinned: Option<bool>,
+    pub muted: Option<bool>,
+}
+
+/// 设置会话的消息有效期（阅后即焚），ttl_secs 为 0 表示关闭
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateMessageTtlRequest {
+    pub conversation: Conversation,
+    pub ttl_secs: u32,
+}
+
+/// 创建定时消息，send_at 为计划发送的毫秒时间戳
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ScheduleMessageRequest {
+    pub conversation: Conversation,
+    #[serde(default)]
+    pub message_type: MessageType,
+    pub message: String,
+    #[serde(default)]
+    pub attachment: Option<Attachment>,
+    #[serde(default)]
+    pub reply_to: Option<MessageId>,
+    #[serde(default)]
+    pub mentions: Vec<u32>, // 仅群消息使用
+    #[serde(default)]
+    pub mention_all: bool,
+    pub send_at: Timestamp,
+}
+
+/// 修改尚未发送的定时消息，缺省的字段保持不变
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateScheduledMessageRequest {
+    pub id: u64,
+    pub message: Option<String>,
+    pub send_at: Option<Timestamp>,
+}
+
+/// 取消尚未发送的定时消息
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CancelScheduledMessageRequest {
+    pub id: u64,
+}
+
+/// 搜索聊天记录的查询参数，只会搜索调用者可见的消息（本人参与的私聊与仍在的群）
+/// peer_id 与 group_id 用于限定会话，二者不能同时指定
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchMessagesQuery {
+    pub q: String,                         // 关键词，多个关键词以空格分隔，需全部命中
+    pub peer_id: Option<UserId>,           // 只搜索与该用户的私聊
+    pub group_id: Option<GroupId>,         // 只搜索该群的群聊
+    pub sender_id: Option<UserId>,         // 只搜索该用户发送的消息
+    pub start: Option<Timestamp>,          // 起始时间（毫秒，含）
+    pub end: Option<Timestamp>,            // 截止时间（毫秒，不含）
+    pub message_type: Option<MessageType>, // 只搜索该类型的消息
+    #[serde(default)]
+    pub offset: u32, // 跳过的结果条数
+    pub limit: Option<u32>, // 每页条数，缺省 20，服务端限制最大 50
+}
+
+/// 导出文件的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+    Html,
+}
+
+impl ExportFormat {
+    /// 导出文件的扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    /// 下载时的 Content-Type
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// 导出聊天记录的查询参数；peer_id 与 group_id 都缺省时导出该用户的全部数据
+/// start/end 可用于定期增量导出
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExportQuery {
+    pub peer_id: Option<UserId>,   // 只导出与该用户的私聊
+    pub group_id: Option<GroupId>, // 只导出该群的群聊
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub start: Option<Timestamp>, // 起始时间（毫秒，含）
+    pub end: Option<Timestamp>,   // 截止时间（毫秒，不含）
+}
+
 #[derive(Debug, Deserialize, IntoParams)]
 pub struct AfterTimestampQuery {
     pub timestamp: i64,
 }
+
+/// 建立 WebSocket 连接时的查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WsConnectQuery {
+    /// 消息编码方式，缺省时参考 Sec-WebSocket-Protocol，再缺省则为 json
+    pub encoding: Option<WsEncoding>,
+    /// 客户端支持的最低帧版本，缺省为 1
+    pub min_version: Option<u8>,
+    /// 客户端支持的最高帧版本，缺省为 1（未声明版本的旧客户端）
+    pub max_version: Option<u8>,
+}
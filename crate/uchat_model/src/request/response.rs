@@ -47,6 +47,15 @@ impl<T> RequestResponse<T> {
         }
     }
 
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: false,
+            code: 403,
+            message: message.into(),
+            data: None,
+        }
+    }
+
     pub fn not_found() -> Self {
         Self {
             status: false,
@@ -81,11 +81,21 @@ SQL_CREATE_TABLES = [
         receiver_id INT UNSIGNED NOT NULL,
         message_type ENUM('text', 'image', 'file', 'video', 'audio') NOT NULL,
         message TEXT NOT NULL,
+        attachment JSON DEFAULT NULL,
         timestamp BIGINT DEFAULT 0 NOT NULL,
+        delivered_at BIGINT DEFAULT NULL,
+        edited_at BIGINT DEFAULT NULL,
+        recalled_at BIGINT DEFAULT NULL,
+        deleted_at BIGINT DEFAULT NULL,
+        reply_to BIGINT UNSIGNED DEFAULT NULL,
+        forwarded_from JSON DEFAULT NULL,
+        expires_at BIGINT DEFAULT NULL,
         FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE,
         FOREIGN KEY (receiver_id) REFERENCES users(id) ON DELETE CASCADE,
         INDEX idx_sender_receiver_time (sender_id, receiver_id, timestamp),
-        INDEX idx_receiver_time (receiver_id, timestamp)
+        INDEX idx_receiver_time (receiver_id, timestamp),
+        INDEX idx_expires_at (expires_at),
+        FULLTEXT INDEX ft_message (message) WITH PARSER ngram
     );
     """,
     # friendships
@@ -129,13 +139,157 @@ SQL_CREATE_TABLES = [
         id BIGINT UNSIGNED PRIMARY KEY AUTO_INCREMENT,
         group_id INT UNSIGNED NOT NULL,
         sender_id INT UNSIGNED NOT NULL,
-        message_type ENUM('text', 'image', 'file', 'video', 'audio') NOT NULL,
+        message_type ENUM('text', 'image', 'file', 'video', 'audio') NOT NULL DEFAULT 'text',
         message TEXT NOT NULL,
+        attachment JSON DEFAULT NULL,
         timestamp BIGINT DEFAULT 0 NOT NULL,
+        edited_at BIGINT DEFAULT NULL,
+        recalled_at BIGINT DEFAULT NULL,
+        deleted_at BIGINT DEFAULT NULL,
+        reply_to BIGINT UNSIGNED DEFAULT NULL,
+        forwarded_from JSON DEFAULT NULL,
+        expires_at BIGINT DEFAULT NULL,
         FOREIGN KEY (group_id) REFERENCES ugroups(id) ON DELETE CASCADE,
         FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE,
         INDEX idx_group_time (group_id, timestamp),
-        INDEX idx_sender_group_time (sender_id, group_id, timestamp)
+        INDEX idx_group_reply (group_id, reply_to),
+        INDEX idx_sender_group_time (sender_id, group_id, timestamp),
+        INDEX idx_expires_at (expires_at),
+        FULLTEXT INDEX ft_message (message) WITH PARSER ngram
+    );
+    """,
+    # read_cursors：每个用户在每个会话中的已读位置，未读数由此推导
+    """
+    CREATE TABLE IF NOT EXISTS read_cursors (
+        user_id INT UNSIGNED NOT NULL,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        conversation_id INT UNSIGNED NOT NULL,
+        last_read_message_id BIGINT UNSIGNED NOT NULL DEFAULT 0,
+        updated_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (user_id, conversation_type, conversation_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+    # conversation_settings：用户对会话的个人设置（置顶、免打扰），没有记录时视为默认值
+    """
+    CREATE TABLE IF NOT EXISTS conversation_settings (
+        user_id INT UNSIGNED NOT NULL,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        conversation_id INT UNSIGNED NOT NULL,
+        pinned BOOLEAN DEFAULT FALSE NOT NULL,
+        muted BOOLEAN DEFAULT FALSE NOT NULL,
+        updated_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (user_id, conversation_type, conversation_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+    # message_ttls：会话的消息有效期（阅后即焚），由会话双方或全体群成员共享
+    # 群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+    """
+    CREATE TABLE IF NOT EXISTS message_ttls (
+        conversation_type ENUM('private', 'group') NOT NULL,
+        conversation_id INT UNSIGNED NOT NULL,
+        peer_id INT UNSIGNED DEFAULT 0 NOT NULL,
+        ttl_secs INT UNSIGNED NOT NULL,
+        updated_by INT UNSIGNED NOT NULL,
+        updated_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (conversation_type, conversation_id, peer_id)
+    );
+    """,
+    # scheduled_messages：定时消息，到达 send_at 后由后台任务投递，conversation 为发送者视角
+    """
+    CREATE TABLE IF NOT EXISTS scheduled_messages (
+        id BIGINT UNSIGNED PRIMARY KEY AUTO_INCREMENT,
+        sender_id INT UNSIGNED NOT NULL,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        conversation_id INT UNSIGNED NOT NULL,
+        message_type ENUM('text', 'image', 'file', 'video', 'audio') DEFAULT 'text' NOT NULL,
+        message TEXT NOT NULL,
+        attachment JSON NULL,
+        reply_to BIGINT UNSIGNED NULL,
+        mentions JSON NULL,
+        mention_all BOOLEAN DEFAULT FALSE NOT NULL,
+        send_at BIGINT NOT NULL,
+        created_at BIGINT DEFAULT 0 NOT NULL,
+        status ENUM('pending', 'sending', 'sent', 'failed') DEFAULT 'pending' NOT NULL,
+        claimed_at BIGINT NULL,
+        message_id BIGINT UNSIGNED NULL,
+        failure_reason TEXT NULL,
+        FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE,
+        INDEX idx_status_send_at (status, send_at),
+        INDEX idx_sender (sender_id, send_at)
+    );
+    """,
+    # message_edits：消息编辑历史，记录每次编辑前的内容
+    """
+    CREATE TABLE IF NOT EXISTS message_edits (
+        id BIGINT UNSIGNED PRIMARY KEY AUTO_INCREMENT,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        message_id BIGINT UNSIGNED NOT NULL,
+        editor_id INT UNSIGNED NOT NULL,
+        old_message TEXT NOT NULL,
+        edited_at BIGINT DEFAULT 0 NOT NULL,
+        FOREIGN KEY (editor_id) REFERENCES users(id) ON DELETE CASCADE,
+        INDEX idx_message (conversation_type, message_id, edited_at)
+    );
+    """,
+    # message_reactions：表情回应，同一用户对同一消息的同一表情只记一次
+    """
+    CREATE TABLE IF NOT EXISTS message_reactions (
+        conversation_type ENUM('private', 'group') NOT NULL,
+        message_id BIGINT UNSIGNED NOT NULL,
+        user_id INT UNSIGNED NOT NULL,
+        emoji VARCHAR(32) NOT NULL,
+        created_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (conversation_type, message_id, user_id, emoji),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+    # message_mentions：群消息中的 @，@所有人在发送时展开为当时的全体成员
+    """
+    CREATE TABLE IF NOT EXISTS message_mentions (
+        message_id BIGINT UNSIGNED NOT NULL,
+        group_id INT UNSIGNED NOT NULL,
+        user_id INT UNSIGNED NOT NULL,
+        PRIMARY KEY (message_id, user_id),
+        FOREIGN KEY (message_id) REFERENCES ugroup_messages(id) ON DELETE CASCADE,
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+        INDEX idx_user_group (user_id, group_id, message_id)
+    );
+    """,
+    # hidden_messages：用户仅对自己隐藏的消息（“仅为我删除”）
+    """
+    CREATE TABLE IF NOT EXISTS hidden_messages (
+        user_id INT UNSIGNED NOT NULL,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        message_id BIGINT UNSIGNED NOT NULL,
+        hidden_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (user_id, conversation_type, message_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+    # pinned_messages：会话的置顶消息，会话的键与 message_ttls 相同
+    # 群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+    """
+    CREATE TABLE IF NOT EXISTS pinned_messages (
+        conversation_type ENUM('private', 'group') NOT NULL,
+        message_id BIGINT UNSIGNED NOT NULL,
+        conversation_id INT UNSIGNED NOT NULL,
+        peer_id INT UNSIGNED DEFAULT 0 NOT NULL,
+        pinned_by INT UNSIGNED NOT NULL,
+        pinned_at BIGINT DEFAULT 0 NOT NULL,
+        PRIMARY KEY (conversation_type, message_id),
+        INDEX idx_conversation (conversation_type, conversation_id, peer_id, pinned_at)
+    );
+    """,
+    # imported_messages：已导入的历史消息，按 (来源, 源消息ID) 去重
+    """
+    CREATE TABLE IF NOT EXISTS imported_messages (
+        source VARCHAR(64) NOT NULL,
+        conversation_type ENUM('private', 'group') NOT NULL,
+        source_message_id BIGINT UNSIGNED NOT NULL,
+        message_id BIGINT UNSIGNED NOT NULL,
+        PRIMARY KEY (source, conversation_type, source_message_id)
     );
     """,
     # view（用 OR REPLACE，避免重复创建报错）
@@ -155,6 +309,79 @@ SQL_CREATE_TABLES = [
     JOIN users AS sender ON m.sender_id = sender.id
     JOIN users AS receiver ON m.receiver_id = receiver.id;
     """,
+    """
+    CREATE OR REPLACE VIEW recent_group_messages_view AS
+    SELECT
+        m.id,
+        m.group_id,
+        g.name AS group_name,
+        m.sender_id,
+        sender.username AS sender_username,
+        m.message_type,
+        LEFT(m.message, 100) AS message_preview,
+        m.timestamp
+    FROM
+        ugroup_messages AS m
+    JOIN ugroups AS g ON m.group_id = g.id
+    JOIN users AS sender ON m.sender_id = sender.id;
+    """,
+]
+
+# 迁移：CREATE TABLE IF NOT EXISTS 不会修改已存在的表，旧版本建好的库需要补充后续新增的列与索引
+# 每项为 (类型, 表名, 列名或索引名, ALTER 语句)，类型为 column 或 index；
+# 执行前先查 information_schema，已存在则跳过，因此可以重复执行
+SQL_MIGRATIONS = [
+    # 消息类型与附件
+    ("column", "messages", "attachment",
+     "ALTER TABLE messages ADD COLUMN attachment JSON DEFAULT NULL;"),
+    ("column", "ugroup_messages", "attachment",
+     "ALTER TABLE ugroup_messages ADD COLUMN attachment JSON DEFAULT NULL;"),
+    # 送达回执
+    ("column", "messages", "delivered_at",
+     "ALTER TABLE messages ADD COLUMN delivered_at BIGINT DEFAULT NULL;"),
+    # 撤回与编辑
+    ("column", "messages", "edited_at",
+     "ALTER TABLE messages ADD COLUMN edited_at BIGINT DEFAULT NULL;"),
+    ("column", "messages", "recalled_at",
+     "ALTER TABLE messages ADD COLUMN recalled_at BIGINT DEFAULT NULL;"),
+    ("column", "ugroup_messages", "edited_at",
+     "ALTER TABLE ugroup_messages ADD COLUMN edited_at BIGINT DEFAULT NULL;"),
+    ("column", "ugroup_messages", "recalled_at",
+     "ALTER TABLE ugroup_messages ADD COLUMN recalled_at BIGINT DEFAULT NULL;"),
+    # 引用回复
+    ("column", "messages", "reply_to",
+     "ALTER TABLE messages ADD COLUMN reply_to BIGINT UNSIGNED DEFAULT NULL;"),
+    ("column", "ugroup_messages", "reply_to",
+     "ALTER TABLE ugroup_messages ADD COLUMN reply_to BIGINT UNSIGNED DEFAULT NULL;"),
+    ("index", "ugroup_messages", "idx_group_reply",
+     "ALTER TABLE ugroup_messages ADD INDEX idx_group_reply (group_id, reply_to);"),
+    # 全文检索
+    ("index", "messages", "ft_message",
+     "ALTER TABLE messages ADD FULLTEXT INDEX ft_message (message) WITH PARSER ngram;"),
+    ("index", "ugroup_messages", "ft_message",
+     "ALTER TABLE ugroup_messages ADD FULLTEXT INDEX ft_message (message) WITH PARSER ngram;"),
+    # 为所有人删除
+    ("column", "messages", "deleted_at",
+     "ALTER TABLE messages ADD COLUMN deleted_at BIGINT DEFAULT NULL;"),
+    ("column", "ugroup_messages", "deleted_at",
+     "ALTER TABLE ugroup_messages ADD COLUMN deleted_at BIGINT DEFAULT NULL;"),
+    # 消息有效期
+    ("column", "messages", "expires_at",
+     "ALTER TABLE messages ADD COLUMN expires_at BIGINT DEFAULT NULL;"),
+    ("column", "ugroup_messages", "expires_at",
+     "ALTER TABLE ugroup_messages ADD COLUMN expires_at BIGINT DEFAULT NULL;"),
+    ("index", "messages", "idx_expires_at",
+     "ALTER TABLE messages ADD INDEX idx_expires_at (expires_at);"),
+    ("index", "ugroup_messages", "idx_expires_at",
+     "ALTER TABLE ugroup_messages ADD INDEX idx_expires_at (expires_at);"),
+    # 转发来源
+    ("column", "messages", "forwarded_from",
+     "ALTER TABLE messages ADD COLUMN forwarded_from JSON DEFAULT NULL;"),
+    ("column", "ugroup_messages", "forwarded_from",
+     "ALTER TABLE ugroup_messages ADD COLUMN forwarded_from JSON DEFAULT NULL;"),
+    # 定时消息投递前领取
+    ("column", "scheduled_messages", "claimed_at",
+     "ALTER TABLE scheduled_messages MODIFY COLUMN status ENUM('pending', 'sending', 'sent', 'failed') DEFAULT 'pending' NOT NULL, ADD COLUMN claimed_at BIGINT NULL;"),
 ]
 
 # 触发器：用 DROP IF EXISTS + CREATE，兼容性更好
@@ -215,6 +442,33 @@ def connect_db():
         autocommit=False,
     )
 
+def migration_applied(cursor, kind, table, name):
+    """
+    检查列或索引是否已经存在
+    """
+    if kind == "column":
+        sql = (
+            "SELECT COUNT(*) AS n FROM information_schema.COLUMNS "
+            "WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = %s AND COLUMN_NAME = %s"
+        )
+    else:
+        sql = (
+            "SELECT COUNT(*) AS n FROM information_schema.STATISTICS "
+            "WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = %s AND INDEX_NAME = %s"
+        )
+    cursor.execute(sql, (table, name))
+    return cursor.fetchone()["n"] > 0
+
+def migrate(cursor):
+    """
+    为已存在的表补充缺失的列与索引
+    """
+    for kind, table, name, q in SQL_MIGRATIONS:
+        if migration_applied(cursor, kind, table, name):
+            continue
+        cursor.execute(q)
+        print(f"迁移成功：{table}.{name}")
+
 def create_tables():
     try:
         connection = connect_db()
@@ -226,6 +480,9 @@ def create_tables():
                 first_line = q.strip().splitlines()[0]
                 print(f"执行成功：{first_line}")
 
+            # 旧库补充新增的列与索引
+            migrate(cursor)
+
             # 自增起始值
             cursor.execute("ALTER TABLE users AUTO_INCREMENT = 10000000;")
             cursor.execute("ALTER TABLE ugroups AUTO_INCREMENT = 10000000;")
@@ -37,7 +37,10 @@ else:
 SQL_QUERIES = [
     # 用户身份枚举类型
     """
-    CREATE TYPE role_type AS ENUM ('user', 'admin');
+    DO $$ BEGIN
+        CREATE TYPE role_type AS ENUM ('user', 'admin');
+    EXCEPTION WHEN duplicate_object THEN NULL;
+    END $$;
     """,
     # 用户表
     """
@@ -47,13 +50,31 @@ SQL_QUERIES = [
         password_hash VARCHAR(255) NOT NULL,
         role role_type NOT NULL DEFAULT 'user',
         bio VARCHAR(256),
-        avatar_url VARCHAR(255)
+        avatar_url VARCHAR(255),
+        friends_updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        groups_updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
     );
     """,
     
     # message_type枚举类型
     """
-    CREATE TYPE message_type AS ENUM ('text', 'image', 'file', 'video', 'audio');
+    DO $$ BEGIN
+        CREATE TYPE message_type AS ENUM ('text', 'image', 'file', 'video', 'audio');
+    EXCEPTION WHEN duplicate_object THEN NULL;
+    END $$;
+    """,
+
+    # 中文全文检索配置，依赖 zhparser 扩展
+    """
+    CREATE EXTENSION IF NOT EXISTS zhparser;
+    """,
+    """
+    DO $$ BEGIN
+        IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'chinese_zh') THEN
+            CREATE TEXT SEARCH CONFIGURATION chinese_zh (PARSER = zhparser);
+            ALTER TEXT SEARCH CONFIGURATION chinese_zh ADD MAPPING FOR n,v,a,i,e,l,j,x WITH simple;
+        END IF;
+    END $$;
     """,
 
     # 消息表（私聊）
@@ -64,7 +85,15 @@ SQL_QUERIES = [
         receiver_id INTEGER NOT NULL,
         message_type message_type NOT NULL,
         message TEXT NOT NULL,
+        attachment JSONB,
         timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        delivered_at TIMESTAMP,
+        edited_at TIMESTAMP,
+        recalled_at TIMESTAMP,
+        deleted_at TIMESTAMP,
+        reply_to INTEGER,
+        forwarded_from JSONB,
+        expires_at TIMESTAMP,
         FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE,
         FOREIGN KEY (receiver_id) REFERENCES users(id) ON DELETE CASCADE
     );
@@ -113,8 +142,16 @@ SQL_QUERIES = [
         id SERIAL PRIMARY KEY,
         group_id INTEGER NOT NULL,
         sender_id INTEGER NOT NULL,
+        message_type message_type NOT NULL DEFAULT 'text',
         message TEXT NOT NULL,
+        attachment JSONB,
         timestamp TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        edited_at TIMESTAMP,
+        recalled_at TIMESTAMP,
+        deleted_at TIMESTAMP,
+        reply_to INTEGER,
+        forwarded_from JSONB,
+        expires_at TIMESTAMP,
         FOREIGN KEY (group_id) REFERENCES ugroups(id) ON DELETE CASCADE,
         FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE
     );
@@ -140,6 +177,193 @@ SQL_QUERIES = [
     );
     """,
 
+    # 会话类型枚举类型
+    """
+    DO $$ BEGIN
+        CREATE TYPE conversation_type AS ENUM ('private', 'group');
+    EXCEPTION WHEN duplicate_object THEN NULL;
+    END $$;
+    """,
+
+    # 已读游标表：每个用户在每个会话中的已读位置，未读数由此推导
+    """
+    CREATE TABLE IF NOT EXISTS read_cursors (
+        user_id INTEGER NOT NULL,
+        conversation_type conversation_type NOT NULL,
+        conversation_id INTEGER NOT NULL,
+        last_read_message_id INTEGER NOT NULL DEFAULT 0,
+        updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (user_id, conversation_type, conversation_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 会话设置表：用户对会话的个人设置（置顶、免打扰），没有记录时视为默认值
+    """
+    CREATE TABLE IF NOT EXISTS conversation_settings (
+        user_id INTEGER NOT NULL,
+        conversation_type conversation_type NOT NULL,
+        conversation_id INTEGER NOT NULL,
+        pinned BOOLEAN NOT NULL DEFAULT FALSE,
+        muted BOOLEAN NOT NULL DEFAULT FALSE,
+        updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (user_id, conversation_type, conversation_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 会话消息有效期表（阅后即焚）：群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+    """
+    CREATE TABLE IF NOT EXISTS message_ttls (
+        conversation_type conversation_type NOT NULL,
+        conversation_id INTEGER NOT NULL,
+        peer_id INTEGER NOT NULL DEFAULT 0,
+        ttl_secs INTEGER NOT NULL,
+        updated_by INTEGER NOT NULL,
+        updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (conversation_type, conversation_id, peer_id)
+    );
+    """,
+
+    # 定时消息状态枚举类型
+    """
+    DO $$ BEGIN
+        CREATE TYPE scheduled_status AS ENUM ('pending', 'sending', 'sent', 'failed');
+    EXCEPTION WHEN duplicate_object THEN NULL;
+    END $$;
+    """,
+
+    # 定时消息表：到达 send_at 后由后台任务投递，conversation 为发送者视角
+    """
+    CREATE TABLE IF NOT EXISTS scheduled_messages (
+        id SERIAL PRIMARY KEY,
+        sender_id INTEGER NOT NULL,
+        conversation_type conversation_type NOT NULL,
+        conversation_id INTEGER NOT NULL,
+        message_type message_type NOT NULL DEFAULT 'text',
+        message TEXT NOT NULL,
+        attachment JSONB,
+        reply_to INTEGER,
+        mentions JSONB,
+        mention_all BOOLEAN NOT NULL DEFAULT FALSE,
+        send_at TIMESTAMP NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        status scheduled_status NOT NULL DEFAULT 'pending',
+        claimed_at TIMESTAMP,
+        message_id INTEGER,
+        failure_reason TEXT,
+        FOREIGN KEY (sender_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 消息编辑历史表
+    """
+    CREATE TABLE IF NOT EXISTS message_edits (
+        id SERIAL PRIMARY KEY,
+        conversation_type conversation_type NOT NULL,
+        message_id INTEGER NOT NULL,
+        editor_id INTEGER NOT NULL,
+        old_message TEXT NOT NULL,
+        edited_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        FOREIGN KEY (editor_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 表情回应表：同一用户对同一消息的同一表情只记一次
+    """
+    CREATE TABLE IF NOT EXISTS message_reactions (
+        conversation_type conversation_type NOT NULL,
+        message_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        emoji VARCHAR(32) NOT NULL,
+        created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (conversation_type, message_id, user_id, emoji),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 群消息中的 @：@所有人在发送时展开为当时的全体成员
+    """
+    CREATE TABLE IF NOT EXISTS message_mentions (
+        message_id INTEGER NOT NULL,
+        group_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        PRIMARY KEY (message_id, user_id),
+        FOREIGN KEY (message_id) REFERENCES ugroup_messages(id) ON DELETE CASCADE,
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 隐藏消息表：用户仅对自己隐藏的消息（“仅为我删除”）
+    """
+    CREATE TABLE IF NOT EXISTS hidden_messages (
+        user_id INTEGER NOT NULL,
+        conversation_type conversation_type NOT NULL,
+        message_id INTEGER NOT NULL,
+        hidden_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (user_id, conversation_type, message_id),
+        FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+    );
+    """,
+
+    # 置顶消息表：会话的键与 message_ttls 相同，群聊为 (群号, 0)，私聊为双方用户ID (较小, 较大)
+    """
+    CREATE TABLE IF NOT EXISTS pinned_messages (
+        conversation_type conversation_type NOT NULL,
+        message_id INTEGER NOT NULL,
+        conversation_id INTEGER NOT NULL,
+        peer_id INTEGER NOT NULL DEFAULT 0,
+        pinned_by INTEGER NOT NULL,
+        pinned_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (conversation_type, message_id)
+    );
+    """,
+
+    # 已导入的历史消息表：按 (来源, 源消息ID) 去重
+    """
+    CREATE TABLE IF NOT EXISTS imported_messages (
+        source VARCHAR(64) NOT NULL,
+        conversation_type conversation_type NOT NULL,
+        source_message_id BIGINT NOT NULL,
+        message_id INTEGER NOT NULL,
+        PRIMARY KEY (source, conversation_type, source_message_id)
+    );
+    """
+]
+
+# 迁移：CREATE TABLE IF NOT EXISTS 不会修改已存在的表，旧版本建好的库需要补充后续新增的列
+# 均使用 IF NOT EXISTS，可以重复执行；须在 VIEW_QUERIES 与 INDEX_QUERIES 之前执行
+MIGRATION_QUERIES = [
+    # 消息类型与附件
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS message_type message_type NOT NULL DEFAULT 'text';",
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS attachment JSONB;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS attachment JSONB;",
+    # 送达回执
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS delivered_at TIMESTAMP;",
+    # 撤回与编辑
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS edited_at TIMESTAMP;",
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS recalled_at TIMESTAMP;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS edited_at TIMESTAMP;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS recalled_at TIMESTAMP;",
+    # 引用回复
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS reply_to INTEGER;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS reply_to INTEGER;",
+    # 为所有人删除
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;",
+    # 消息有效期
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP;",
+    # 转发来源
+    "ALTER TABLE messages ADD COLUMN IF NOT EXISTS forwarded_from JSONB;",
+    "ALTER TABLE ugroup_messages ADD COLUMN IF NOT EXISTS forwarded_from JSONB;",
+    # 定时消息投递前领取
+    "ALTER TYPE scheduled_status ADD VALUE IF NOT EXISTS 'sending' BEFORE 'sent';",
+    "ALTER TABLE scheduled_messages ADD COLUMN IF NOT EXISTS claimed_at TIMESTAMP;",
+]
+
+# 视图依赖迁移补充的列，须在 MIGRATION_QUERIES 之后执行
+VIEW_QUERIES = [
     # 近期聊天记录视图
     """
     CREATE OR REPLACE VIEW recent_private_messages_view AS
@@ -156,6 +380,22 @@ SQL_QUERIES = [
         messages AS m
     JOIN users AS sender ON m.sender_id = sender.id
     JOIN users AS receiver ON m.receiver_id = receiver.id;
+""",
+    """
+    CREATE OR REPLACE VIEW recent_group_messages_view AS
+    SELECT 
+        m.id,
+        m.group_id,
+        g.name AS group_name,
+        m.sender_id,
+        sender.username AS sender_username,
+        m.message_type,
+        LEFT(m.message, 100) AS message_preview,
+        m.timestamp
+    FROM 
+        ugroup_messages AS m
+    JOIN ugroups AS g ON m.group_id = g.id
+    JOIN users AS sender ON m.sender_id = sender.id;
 """
 ]
 
@@ -165,6 +405,16 @@ INDEX_QUERIES = [
     "CREATE INDEX IF NOT EXISTS idx_user_id ON group_members (user_id);",
     "CREATE INDEX IF NOT EXISTS idx_group_time ON ugroup_messages (group_id, timestamp);",
     "CREATE INDEX IF NOT EXISTS idx_sender_group_time ON ugroup_messages (sender_id, group_id, timestamp);",
+    "CREATE INDEX IF NOT EXISTS idx_group_reply ON ugroup_messages (group_id, reply_to);",
+    "CREATE INDEX IF NOT EXISTS idx_messages_expires_at ON messages (expires_at);",
+    "CREATE INDEX IF NOT EXISTS idx_group_messages_expires_at ON ugroup_messages (expires_at);",
+    "CREATE INDEX IF NOT EXISTS idx_messages_fts ON messages USING GIN (to_tsvector('chinese_zh', message));",
+    "CREATE INDEX IF NOT EXISTS idx_group_messages_fts ON ugroup_messages USING GIN (to_tsvector('chinese_zh', message));",
+    "CREATE INDEX IF NOT EXISTS idx_mention_user_group ON message_mentions (user_id, group_id, message_id);",
+    "CREATE INDEX IF NOT EXISTS idx_message_edits ON message_edits (conversation_type, message_id, edited_at);",
+    "CREATE INDEX IF NOT EXISTS idx_scheduled_status_send_at ON scheduled_messages (status, send_at);",
+    "CREATE INDEX IF NOT EXISTS idx_scheduled_sender ON scheduled_messages (sender_id, send_at);",
+    "CREATE INDEX IF NOT EXISTS idx_pinned_conversation ON pinned_messages (conversation_type, conversation_id, peer_id, pinned_at);",
     "CREATE INDEX IF NOT EXISTS idx_receiver_undelivered ON offline_messages (receiver_id, delivered, is_group, timestamp);"
 ]
 
@@ -188,7 +438,7 @@ def connect_db():
 
 def create_tables():
     """
-    执行 SQL_QUERIES 中的建表语句与索引创建语句
+    执行 SQL_QUERIES 中的建表语句、MIGRATION_QUERIES 中的迁移语句、视图与索引创建语句
     """
     try:
         connection = connect_db()
@@ -199,6 +449,14 @@ def create_tables():
                 cursor.execute(query)
                 print(f"执行成功：{query.strip().splitlines()[0]}")
 
+            for migration_query in MIGRATION_QUERIES:
+                cursor.execute(migration_query)
+                print(f"迁移成功：{migration_query.strip()}")
+
+            for view_query in VIEW_QUERIES:
+                cursor.execute(view_query)
+                print(f"视图创建成功：{view_query.strip().splitlines()[0]}")
+
             for idx_query in INDEX_QUERIES:
                 cursor.execute(idx_query)
                 print(f"索引创建成功：{idx_query.strip().split()[2]}")